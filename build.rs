@@ -0,0 +1,4 @@
+// Rebuild whenever a migration changes, they are embedded into the binary by `sqlx::migrate!`.
+fn main() {
+    println!("cargo:rerun-if-changed=migrations");
+}
@@ -51,6 +51,5 @@ DROP TYPE IF EXISTS rule_type;
 
 -- Drop extensions
 DROP EXTENSION IF EXISTS "ltree";
-DROP EXTENSION IF EXISTS "pg_partman";
 DROP EXTENSION IF EXISTS "btree_gist";
 DROP EXTENSION IF EXISTS "uuid-ossp";
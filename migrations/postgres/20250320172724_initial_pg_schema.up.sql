@@ -12,7 +12,6 @@
 -- Extensions necesary for the project
 CREATE EXTENSION IF NOT EXISTS "uuid-ossp";     -- For generating UUIDs
 CREATE EXTENSION IF NOT EXISTS "btree_gist";    -- For indexing jsonb fields
-CREATE EXTENSION IF NOT EXISTS "pg_partman";    -- For partitioning tables
 CREATE EXTENSION IF NOT EXISTS "ltree";         -- For storing hierarchical data
 
 -- Define types for the database
@@ -152,11 +151,11 @@ CREATE TABLE IF NOT EXISTS relationship_tuples (
     zookie_token VARCHAR(255) NOT NULL, -- Consistency token for this relationship
 
     -- Enforce foreign key constraints
-    FOREIGN KEY (namespace_id, relation) REFERENCES relations(namespace_id, name) ON DELETE RESTRICT,
+    FOREIGN KEY (namespace_id, relation) REFERENCES relations(namespace_id, name) ON DELETE RESTRICT
+);
 
-    -- Unique constraints to prevent duplicates
-    UNIQUE (namespace_id, object_id, relation, subject_type, subject_id, COALESCE(userset_namespace, ''), COALESCE(userset_relation, ''))
-) PARTITION BY LIST (namespace_id);
+-- Unique constraints to prevent duplicates, expressions are only accepted in a unique index
+CREATE UNIQUE INDEX IF NOT EXISTS idx_tuples_unique ON relationship_tuples (namespace_id, object_id, relation, subject_type, subject_id, COALESCE(userset_namespace, ''), COALESCE(userset_relation, ''));
 
 -- Creating indices for common access patterns
 CREATE INDEX IF NOT EXISTS idx_tuples_object ON relationship_tuples (namespace_id, object_id, relation);
@@ -409,13 +408,13 @@ DECLARE
 BEGIN
     -- Remove expired zookies
     DELETE FROM zookies
-    WHERE expired_at < CURRENT_TIMESTAMP
-    RETURNING COUNT(*) INTO zookies_removed;
+    WHERE expired_at < CURRENT_TIMESTAMP;
+    GET DIAGNOSTICS zookies_removed = ROW_COUNT;
 
     -- Remove expired cache entries
     DELETE FROM permissions_cache
-    WHERE valid_until < CURRENT_TIMESTAMP
-    RETURNING COUNT(*) INTO cache_removed;
+    WHERE valid_until < CURRENT_TIMESTAMP;
+    GET DIAGNOSTICS cache_removed = ROW_COUNT;
 
     RETURN zookies_removed + cache_removed;
 END;
@@ -469,9 +468,10 @@ BEGIN
         current_actor,
         TG_OP,
         TG_TABLE_NAME,
+        -- Not every audited table has an `id` column (zookies, replication_status)
         CASE
-            WHEN TG_OP = 'DELETE' THEN OLD.id
-            ELSE NEW.id::TEXT
+            WHEN TG_OP = 'DELETE' THEN COALESCE(to_jsonb(OLD)->>'id', to_jsonb(OLD)->>'token', to_jsonb(OLD)->>'node_id')
+            ELSE COALESCE(to_jsonb(NEW)->>'id', to_jsonb(NEW)->>'token', to_jsonb(NEW)->>'node_id')
         END,
         CASE
             WHEN TG_OP = 'INSERT' THEN to_jsonb(NEW)
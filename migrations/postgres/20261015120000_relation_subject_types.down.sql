@@ -0,0 +1,6 @@
+-- =================================================================================================
+-- Reversion Date: 2026-10-15 12:00:00.000000
+-- Description: Reversion of `relations.subject_types`
+-- =================================================================================================
+
+ALTER TABLE relations DROP COLUMN IF EXISTS subject_types;
@@ -0,0 +1,8 @@
+-- =================================================================================================
+-- Creation Date: 2026-10-15 12:00:00.000000
+-- Description: Subject types a relation accepts, as declared in the schema language
+-- =================================================================================================
+
+-- JSON array of `namespace`, `namespace:*` or `namespace#relation` entries, NULL when the relation
+-- was not defined through a schema. Informational only, tuple writes do not enforce it.
+ALTER TABLE relations ADD COLUMN IF NOT EXISTS subject_types JSONB NULL;
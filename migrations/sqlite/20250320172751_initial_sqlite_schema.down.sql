@@ -1 +1,59 @@
--- Add down migration script here
+-- =================================================================================================
+-- Reversion Date: 2025-03-20 17:27:51.000000
+-- Author: Revanth Shalon Raj
+-- Description: Reversion of the SQLite port of the database schema for authorization service inspired by Zanzibar
+-- Version: 1.0
+-- =================================================================================================
+
+-- Drop triggers
+DROP TRIGGER IF EXISTS log_permissions_cache_delete;
+DROP TRIGGER IF EXISTS log_permissions_cache_update;
+DROP TRIGGER IF EXISTS log_permissions_cache_insert;
+DROP TRIGGER IF EXISTS log_auth_decisions_delete;
+DROP TRIGGER IF EXISTS log_auth_decisions_update;
+DROP TRIGGER IF EXISTS log_auth_decisions_insert;
+DROP TRIGGER IF EXISTS log_replication_status_delete;
+DROP TRIGGER IF EXISTS log_replication_status_update;
+DROP TRIGGER IF EXISTS log_replication_status_insert;
+DROP TRIGGER IF EXISTS log_transaction_log_delete;
+DROP TRIGGER IF EXISTS log_transaction_log_update;
+DROP TRIGGER IF EXISTS log_transaction_log_insert;
+DROP TRIGGER IF EXISTS log_zookies_delete;
+DROP TRIGGER IF EXISTS log_zookies_update;
+DROP TRIGGER IF EXISTS log_zookies_insert;
+DROP TRIGGER IF EXISTS log_relationship_tuples_delete;
+DROP TRIGGER IF EXISTS log_relationship_tuples_update;
+DROP TRIGGER IF EXISTS log_relationship_tuples_insert;
+DROP TRIGGER IF EXISTS log_relation_rules_delete;
+DROP TRIGGER IF EXISTS log_relation_rules_update;
+DROP TRIGGER IF EXISTS log_relation_rules_insert;
+DROP TRIGGER IF EXISTS log_relations_delete;
+DROP TRIGGER IF EXISTS log_relations_update;
+DROP TRIGGER IF EXISTS log_relations_insert;
+DROP TRIGGER IF EXISTS log_namespaces_delete;
+DROP TRIGGER IF EXISTS log_namespaces_update;
+DROP TRIGGER IF EXISTS log_namespaces_insert;
+
+DROP TRIGGER IF EXISTS update_relation_rules_timestamp;
+DROP TRIGGER IF EXISTS update_relations_timestamp;
+DROP TRIGGER IF EXISTS update_namespaces_timestamp;
+
+-- Drop performance optimization tables
+DROP TABLE IF EXISTS permissions_cache;
+
+-- Drop monitoring and auditing tables
+DROP TABLE IF EXISTS audit_log;
+DROP TABLE IF EXISTS auth_decisions;
+
+-- Drop consistency management tables
+DROP TABLE IF EXISTS replication_status;
+DROP TABLE IF EXISTS transaction_log;
+DROP TABLE IF EXISTS zookies;
+
+-- Drop core permission data tables
+DROP TABLE IF EXISTS relationship_tuples;
+
+-- Drop core configuration tables
+DROP TABLE IF EXISTS relation_rules;
+DROP TABLE IF EXISTS relations;
+DROP TABLE IF EXISTS namespaces;
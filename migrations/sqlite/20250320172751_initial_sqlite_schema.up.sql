@@ -1 +1,1117 @@
--- Add up migration script here
+-- =================================================================================================
+-- Creation Date: 2025-03-20 17:27:51.000000
+-- Author: Revanth Shalon Raj
+-- Description: SQLite port of the database schema for authorization service inspired by Zanzibar
+-- Version: 1.0
+-- =================================================================================================
+-- This schema mirrors `migrations/postgres/20250320172724_initial_pg_schema.up.sql` table for table
+-- so that Heimdall behaves identically when running off a single SQLite file. Refer to the Postgres
+-- migration for the description of every table and column, only the differences are noted here:
+--   * UUIDs are stored as 16 byte BLOBs (the representation sqlx uses for `uuid::Uuid`), defaults
+--     are generated with `randomblob(16)`.
+--   * Timestamps are stored as ISO-8601 TEXT in UTC (`2025-03-20T17:27:51.000+00:00`), which sorts
+--     and compares correctly as plain text.
+--   * ENUM types become TEXT columns guarded by CHECK constraints.
+--   * JSONB columns become TEXT columns guarded by `json_valid`.
+--   * INET becomes TEXT.
+--   * There are no stored functions, `update_timestamp` and `log_change` are expanded into one
+--     trigger per table and operation, and `cleanup_zookies` is performed by the application.
+--   * There are no session settings, so audit entries are always attributed to the `system` actor.
+
+-- =================================================================================================
+-- Core Configuration Tables
+-- =================================================================================================
+
+-- Namespaces define object types in the system
+CREATE TABLE IF NOT EXISTS namespaces (
+    id VARCHAR(64) PRIMARY KEY,
+    name VARCHAR(255) NOT NULL UNIQUE,
+    description TEXT NULL,
+    created_at TEXT NOT NULL DEFAULT (strftime('%Y-%m-%dT%H:%M:%f+00:00', 'now')),
+    updated_at TEXT NOT NULL DEFAULT (strftime('%Y-%m-%dT%H:%M:%f+00:00', 'now'))
+);
+
+-- Relations define the types of permissions available for each namespace
+CREATE TABLE IF NOT EXISTS relations (
+    id BLOB PRIMARY KEY DEFAULT (randomblob(16)),
+    namespace_id VARCHAR(64) NOT NULL REFERENCES namespaces(id) ON DELETE RESTRICT,
+    name VARCHAR(64) NOT NULL,
+    description TEXT NULL,
+    created_at TEXT NOT NULL DEFAULT (strftime('%Y-%m-%dT%H:%M:%f+00:00', 'now')),
+    updated_at TEXT NOT NULL DEFAULT (strftime('%Y-%m-%dT%H:%M:%f+00:00', 'now')),
+    deleted_at TEXT NULL,
+    UNIQUE (namespace_id, name)
+);
+
+-- Relation rules define how permissions are computed and inherited
+CREATE TABLE IF NOT EXISTS relation_rules (
+    id BLOB PRIMARY KEY DEFAULT (randomblob(16)),
+    namespace_id VARCHAR(64) NOT NULL REFERENCES namespaces(id) ON DELETE RESTRICT,
+    relation_name VARCHAR(64) NOT NULL,
+    rule_type TEXT NOT NULL CHECK (rule_type IN ('direct', 'union', 'intersection', 'exclusion', 'tuple-to-userset')),
+
+    -- For `tuple-to-userset` rule_type
+    ttu_object_namespace VARCHAR(64) NULL,
+    ttu_relation VARCHAR(64) NULL,
+
+    -- For `union`, `intersection`, `exclusion` rule_types
+    child_relations TEXT NULL CHECK (child_relations IS NULL OR json_valid(child_relations)),
+
+    -- Rule expression in zanibar syntax
+    expression TEXT NULL,
+
+    -- Rule precedence (lower numbers are evaluated first)
+    priority INT NOT NULL DEFAULT 100,
+
+    created_at TEXT NOT NULL DEFAULT (strftime('%Y-%m-%dT%H:%M:%f+00:00', 'now')),
+    updated_at TEXT NOT NULL DEFAULT (strftime('%Y-%m-%dT%H:%M:%f+00:00', 'now')),
+    deleted_at TEXT NULL,
+
+    FOREIGN KEY (namespace_id, relation_name) REFERENCES relations(namespace_id, name) ON DELETE RESTRICT,
+    UNIQUE (namespace_id, relation_name, priority)
+);
+
+-- =================================================================================================
+-- Core Permission Data Tables
+-- =================================================================================================
+
+-- Relationship tuples store the actual permission relationships in the system
+CREATE TABLE IF NOT EXISTS relationship_tuples (
+    id BLOB PRIMARY KEY DEFAULT (randomblob(16)),
+    namespace_id VARCHAR(64) NOT NULL,
+    object_id VARCHAR(255) NOT NULL, -- the object whose permissions are being set (e.g., document_id)
+    relation VARCHAR(64) NOT NULL, -- the permission being granted (e.g., 'viewer')
+
+    -- Subject can be a user or object (group, role, etc.)
+    subject_type VARCHAR(64) NOT NULL, -- 'user', 'group', 'role', etc.
+    subject_id VARCHAR(255) NOT NULL, -- the ID of the subject (e.g., user_id)
+
+    -- Optional fields for additional context
+    userset_namespace VARCHAR(64) NULL, -- for tuple-to-userset rules
+    userset_relation VARCHAR(64) NULL, -- for tuple-to-userset rules
+
+    created_at TEXT NOT NULL DEFAULT (strftime('%Y-%m-%dT%H:%M:%f+00:00', 'now')),
+    updated_at TEXT NOT NULL DEFAULT (strftime('%Y-%m-%dT%H:%M:%f+00:00', 'now')),
+    zookie_token VARCHAR(255) NOT NULL, -- Consistency token for this relationship
+
+    -- Enforce foreign key constraints
+    FOREIGN KEY (namespace_id, relation) REFERENCES relations(namespace_id, name) ON DELETE RESTRICT
+);
+
+-- Unique constraints to prevent duplicates, expressions are only accepted in a unique index
+CREATE UNIQUE INDEX IF NOT EXISTS idx_tuples_unique ON relationship_tuples (namespace_id, object_id, relation, subject_type, subject_id, COALESCE(userset_namespace, ''), COALESCE(userset_relation, ''));
+
+-- Creating indices for common access patterns
+CREATE INDEX IF NOT EXISTS idx_tuples_object ON relationship_tuples (namespace_id, object_id, relation);
+CREATE INDEX IF NOT EXISTS idx_tuples_subject ON relationship_tuples (subject_type, subject_id);
+CREATE INDEX IF NOT EXISTS idx_tuples_userset ON relationship_tuples (userset_namespace, userset_relation) WHERE userset_namespace IS NOT NULL;
+CREATE INDEX IF NOT EXISTS idx_tuples_zookie ON relationship_tuples (zookie_token);
+
+-- =================================================================================================
+-- Consistency Management Tables
+-- =================================================================================================
+
+-- Zookies manage consistency tokens for distributed permission validation
+CREATE TABLE IF NOT EXISTS zookies (
+    token VARCHAR(255) PRIMARY KEY,
+    timestamp TEXT NOT NULL DEFAULT (strftime('%Y-%m-%dT%H:%M:%f+00:00', 'now')),
+    version BIGINT NOT NULL,
+    transaction_id BLOB NOT NULL,
+    shard_id INT NOT NULL,
+    created_at TEXT NOT NULL DEFAULT (strftime('%Y-%m-%dT%H:%M:%f+00:00', 'now')),
+    expired_at TEXT NOT NULL DEFAULT (strftime('%Y-%m-%dT%H:%M:%f+00:00', 'now', '+7 days'))
+);
+
+CREATE INDEX IF NOT EXISTS idx_zookies_version ON zookies (version);
+CREATE INDEX IF NOT EXISTS idx_zookies_expires ON zookies (expired_at);
+
+-- Transaction log records all changes to permission relationships for consistency and auditability
+CREATE TABLE IF NOT EXISTS transaction_log (
+    id BLOB PRIMARY KEY DEFAULT (randomblob(16)),
+    timestamp TEXT NOT NULL DEFAULT (strftime('%Y-%m-%dT%H:%M:%f+00:00', 'now')),
+    version_number BIGINT NOT NULL,
+    operation TEXT NOT NULL CHECK (operation IN ('create', 'update', 'delete')),
+
+    namespace_id VARCHAR(64) NOT NULL,
+    object_id VARCHAR(255) NOT NULL,
+    relation VARCHAR(64) NOT NULL,
+    subject_type VARCHAR(64) NOT NULL,
+    subject_id VARCHAR(255) NOT NULL,
+    userset_namespace VARCHAR(64) NULL,
+    userset_relation VARCHAR(64) NULL,
+
+    -- Metadata
+    zookie_token VARCHAR(255) NOT NULL,
+    payload TEXT NOT NULL CHECK (json_valid(payload)),
+    status VARCHAR(16) NOT NULL DEFAULT 'COMMITTED' CHECK (status IN ('PENDING', 'COMMITTED', 'FAILED', 'REPLICATED'))
+);
+
+CREATE INDEX IF NOT EXISTS idx_transaction_log_version ON transaction_log (version_number);
+CREATE INDEX IF NOT EXISTS idx_transaction_log_status ON transaction_log (status, version_number);
+CREATE INDEX IF NOT EXISTS idx_transaction_log_namespace_object ON transaction_log (namespace_id, object_id);
+
+-- Replication status tracks the state of database nodes in a distributed system
+CREATE TABLE IF NOT EXISTS replication_status (
+    node_id VARCHAR(64) PRIMARY KEY,
+    last_applied_version BIGINT NOT NULL,
+    last_applied_timestamp TEXT NOT NULL,
+    heartbeat_at TEXT NOT NULL DEFAULT (strftime('%Y-%m-%dT%H:%M:%f+00:00', 'now')),
+    is_primary BOOLEAN NOT NULL DEFAULT FALSE,
+    status VARCHAR(16) NOT NULL DEFAULT 'ACTIVE' CHECK (status IN ('ACTIVE', 'INACTIVE', 'DEGRADED')),
+    sync_lag_ms INT NULL
+);
+
+-- =================================================================================================
+-- Monitoring and Auditing Tables
+-- =================================================================================================
+
+-- Auth decisions records each permission check for monitoring and auditing
+CREATE TABLE IF NOT EXISTS auth_decisions (
+    id BLOB PRIMARY KEY DEFAULT (randomblob(16)),
+    timestamp TEXT NOT NULL DEFAULT (strftime('%Y-%m-%dT%H:%M:%f+00:00', 'now')),
+
+    -- Request Details
+    request_id BLOB NOT NULL,
+    subject_type VARCHAR(64) NOT NULL,
+    subject_id VARCHAR(255) NOT NULL,
+    namespace_id VARCHAR(64) NOT NULL,
+    object_id VARCHAR(255) NOT NULL,
+    relation VARCHAR(64) NOT NULL,
+
+    -- Decision Details
+    permitted BOOLEAN NOT NULL,
+    cached BOOLEAN NOT NULL DEFAULT FALSE,
+
+    -- Performance Metrics
+    latency_ms INT NOT NULL,
+    evaluation_path TEXT NOT NULL CHECK (json_valid(evaluation_path)),
+
+    -- Consistency Info
+    zookie_token VARCHAR(255) NULL,
+    waited_for_consistency BOOLEAN NOT NULL DEFAULT FALSE,
+    consistency_wait_ms INT NULL
+);
+
+CREATE INDEX IF NOT EXISTS idx_auth_decisions_request ON auth_decisions (request_id);
+CREATE INDEX IF NOT EXISTS idx_auth_decisions_subject ON auth_decisions (subject_type, subject_id);
+CREATE INDEX IF NOT EXISTS idx_auth_decisions_object ON auth_decisions (namespace_id, object_id);
+CREATE INDEX IF NOT EXISTS idx_auth_decisions_timestamp ON auth_decisions (timestamp);
+
+-- Audit log records all administrative and security-relevant actions in the system
+CREATE TABLE IF NOT EXISTS audit_log (
+    id BLOB PRIMARY KEY DEFAULT (randomblob(16)),
+    timestamp TEXT NOT NULL DEFAULT (strftime('%Y-%m-%dT%H:%M:%f+00:00', 'now')),
+    actor VARCHAR(255) NOT NULL,
+    action VARCHAR(64) NOT NULL,
+    resource_type  VARCHAR(64) NOT NULL,
+    resource_id VARCHAR(255) NOT NULL,
+    details TEXT NOT NULL CHECK (json_valid(details)),
+    trace_id BLOB NULL,
+    client_ip TEXT NULL,
+    client_info TEXT NULL CHECK (client_info IS NULL OR json_valid(client_info))
+);
+
+CREATE INDEX IF NOT EXISTS idx_audit_timestamp ON audit_log (timestamp);
+CREATE INDEX IF NOT EXISTS idx_audit_actor ON audit_log (actor);
+CREATE INDEX IF NOT EXISTS idx_audit_resource ON audit_log (resource_type, resource_id);
+
+-- =================================================================================================
+-- Performance Optimization Tables
+-- =================================================================================================
+
+-- Permissions cache stores pre-computed permission check results for fast access
+CREATE TABLE IF NOT EXISTS permissions_cache (
+    id BLOB PRIMARY KEY DEFAULT (randomblob(16)),
+    namespace_id VARCHAR(64) NOT NULL,
+    object_id VARCHAR(255) NOT NULL,
+    relation VARCHAR(64) NOT NULL,
+    subject_type VARCHAR(64) NOT NULL,
+    subject_id VARCHAR(255) NOT NULL,
+    permitted BOOLEAN NOT NULL,
+    computed_at TEXT NOT NULL DEFAULT (strftime('%Y-%m-%dT%H:%M:%f+00:00', 'now')),
+    valid_until TEXT NOT NULL,
+    max_zookie_version BIGINT NOT NULL,
+    cache_key VARCHAR(255) NOT NULL UNIQUE
+);
+
+CREATE INDEX IF NOT EXISTS idx_permissions_cache_lookup ON permissions_cache (namespace_id, object_id, relation, subject_type, subject_id);
+CREATE INDEX IF NOT EXISTS idx_permissions_cache_expiry ON permissions_cache (valid_until);
+CREATE INDEX IF NOT EXISTS idx_cache_zookie ON permissions_cache (max_zookie_version);
+
+-- =================================================================================================
+-- Triggers
+-- =================================================================================================
+
+-- Trigger: update_namespaces_timestamp
+-- Automatically updates the timestamp when a namespace record is modified, the WHEN clause keeps explicit
+-- `updated_at` assignments intact and prevents the nested UPDATE from firing the trigger again
+CREATE TRIGGER IF NOT EXISTS update_namespaces_timestamp
+AFTER UPDATE ON namespaces
+FOR EACH ROW WHEN NEW.updated_at = OLD.updated_at
+BEGIN
+    UPDATE namespaces SET updated_at = (strftime('%Y-%m-%dT%H:%M:%f+00:00', 'now')) WHERE id = NEW.id;
+END;
+
+-- Trigger: update_relations_timestamp
+-- Automatically updates the timestamp when a relation record is modified, the WHEN clause keeps explicit
+-- `updated_at` assignments intact and prevents the nested UPDATE from firing the trigger again
+CREATE TRIGGER IF NOT EXISTS update_relations_timestamp
+AFTER UPDATE ON relations
+FOR EACH ROW WHEN NEW.updated_at = OLD.updated_at
+BEGIN
+    UPDATE relations SET updated_at = (strftime('%Y-%m-%dT%H:%M:%f+00:00', 'now')) WHERE id = NEW.id;
+END;
+
+-- Trigger: update_relation_rules_timestamp
+-- Automatically updates the timestamp when a relation rule is modified, the WHEN clause keeps explicit
+-- `updated_at` assignments intact and prevents the nested UPDATE from firing the trigger again
+CREATE TRIGGER IF NOT EXISTS update_relation_rules_timestamp
+AFTER UPDATE ON relation_rules
+FOR EACH ROW WHEN NEW.updated_at = OLD.updated_at
+BEGIN
+    UPDATE relation_rules SET updated_at = (strftime('%Y-%m-%dT%H:%M:%f+00:00', 'now')) WHERE id = NEW.id;
+END;
+-- Triggers: log_namespaces_insert, log_namespaces_update, log_namespaces_delete
+-- Records all changes to namespace definitions in the audit log
+-- Updates that only refresh `updated_at` come from update_namespaces_timestamp and are skipped
+CREATE TRIGGER IF NOT EXISTS log_namespaces_insert
+AFTER INSERT ON namespaces
+FOR EACH ROW
+BEGIN
+    INSERT INTO audit_log (actor, action, resource_type, resource_id, details)
+    VALUES (
+        'system',
+        'INSERT',
+        'namespaces',
+        NEW.id,
+        json_object(
+            'id', NEW.id,
+            'name', NEW.name,
+            'description', NEW.description,
+            'created_at', NEW.created_at,
+            'updated_at', NEW.updated_at
+        )
+    );
+END;
+
+CREATE TRIGGER IF NOT EXISTS log_namespaces_update
+AFTER UPDATE ON namespaces
+FOR EACH ROW WHEN NOT (
+    NEW.updated_at IS NOT OLD.updated_at
+    AND NEW.id IS OLD.id
+    AND NEW.name IS OLD.name
+    AND NEW.description IS OLD.description
+    AND NEW.created_at IS OLD.created_at
+)
+BEGIN
+    INSERT INTO audit_log (actor, action, resource_type, resource_id, details)
+    VALUES (
+        'system',
+        'UPDATE',
+        'namespaces',
+        NEW.id,
+        json_object(
+            'previous', json_object(
+                'id', OLD.id,
+                'name', OLD.name,
+                'description', OLD.description,
+                'created_at', OLD.created_at,
+                'updated_at', OLD.updated_at
+            ),
+            'new', json_object(
+                'id', NEW.id,
+                'name', NEW.name,
+                'description', NEW.description,
+                'created_at', NEW.created_at,
+                'updated_at', NEW.updated_at
+            )
+        )
+    );
+END;
+
+CREATE TRIGGER IF NOT EXISTS log_namespaces_delete
+AFTER DELETE ON namespaces
+FOR EACH ROW
+BEGIN
+    INSERT INTO audit_log (actor, action, resource_type, resource_id, details)
+    VALUES (
+        'system',
+        'DELETE',
+        'namespaces',
+        OLD.id,
+        json_object(
+            'id', OLD.id,
+            'name', OLD.name,
+            'description', OLD.description,
+            'created_at', OLD.created_at,
+            'updated_at', OLD.updated_at
+        )
+    );
+END;
+
+-- Triggers: log_relations_insert, log_relations_update, log_relations_delete
+-- Records all changes to relation definitions in the audit log
+-- Updates that only refresh `updated_at` come from update_relations_timestamp and are skipped
+CREATE TRIGGER IF NOT EXISTS log_relations_insert
+AFTER INSERT ON relations
+FOR EACH ROW
+BEGIN
+    INSERT INTO audit_log (actor, action, resource_type, resource_id, details)
+    VALUES (
+        'system',
+        'INSERT',
+        'relations',
+        lower(substr(hex(NEW.id), 1, 8) || '-' || substr(hex(NEW.id), 9, 4) || '-' || substr(hex(NEW.id), 13, 4) || '-' || substr(hex(NEW.id), 17, 4) || '-' || substr(hex(NEW.id), 21)),
+        json_object(
+            'id', lower(substr(hex(NEW.id), 1, 8) || '-' || substr(hex(NEW.id), 9, 4) || '-' || substr(hex(NEW.id), 13, 4) || '-' || substr(hex(NEW.id), 17, 4) || '-' || substr(hex(NEW.id), 21)),
+            'namespace_id', NEW.namespace_id,
+            'name', NEW.name,
+            'description', NEW.description,
+            'created_at', NEW.created_at,
+            'updated_at', NEW.updated_at,
+            'deleted_at', NEW.deleted_at
+        )
+    );
+END;
+
+CREATE TRIGGER IF NOT EXISTS log_relations_update
+AFTER UPDATE ON relations
+FOR EACH ROW WHEN NOT (
+    NEW.updated_at IS NOT OLD.updated_at
+    AND NEW.id IS OLD.id
+    AND NEW.namespace_id IS OLD.namespace_id
+    AND NEW.name IS OLD.name
+    AND NEW.description IS OLD.description
+    AND NEW.created_at IS OLD.created_at
+    AND NEW.deleted_at IS OLD.deleted_at
+)
+BEGIN
+    INSERT INTO audit_log (actor, action, resource_type, resource_id, details)
+    VALUES (
+        'system',
+        'UPDATE',
+        'relations',
+        lower(substr(hex(NEW.id), 1, 8) || '-' || substr(hex(NEW.id), 9, 4) || '-' || substr(hex(NEW.id), 13, 4) || '-' || substr(hex(NEW.id), 17, 4) || '-' || substr(hex(NEW.id), 21)),
+        json_object(
+            'previous', json_object(
+                'id', lower(substr(hex(OLD.id), 1, 8) || '-' || substr(hex(OLD.id), 9, 4) || '-' || substr(hex(OLD.id), 13, 4) || '-' || substr(hex(OLD.id), 17, 4) || '-' || substr(hex(OLD.id), 21)),
+                'namespace_id', OLD.namespace_id,
+                'name', OLD.name,
+                'description', OLD.description,
+                'created_at', OLD.created_at,
+                'updated_at', OLD.updated_at,
+                'deleted_at', OLD.deleted_at
+            ),
+            'new', json_object(
+                'id', lower(substr(hex(NEW.id), 1, 8) || '-' || substr(hex(NEW.id), 9, 4) || '-' || substr(hex(NEW.id), 13, 4) || '-' || substr(hex(NEW.id), 17, 4) || '-' || substr(hex(NEW.id), 21)),
+                'namespace_id', NEW.namespace_id,
+                'name', NEW.name,
+                'description', NEW.description,
+                'created_at', NEW.created_at,
+                'updated_at', NEW.updated_at,
+                'deleted_at', NEW.deleted_at
+            )
+        )
+    );
+END;
+
+CREATE TRIGGER IF NOT EXISTS log_relations_delete
+AFTER DELETE ON relations
+FOR EACH ROW
+BEGIN
+    INSERT INTO audit_log (actor, action, resource_type, resource_id, details)
+    VALUES (
+        'system',
+        'DELETE',
+        'relations',
+        lower(substr(hex(OLD.id), 1, 8) || '-' || substr(hex(OLD.id), 9, 4) || '-' || substr(hex(OLD.id), 13, 4) || '-' || substr(hex(OLD.id), 17, 4) || '-' || substr(hex(OLD.id), 21)),
+        json_object(
+            'id', lower(substr(hex(OLD.id), 1, 8) || '-' || substr(hex(OLD.id), 9, 4) || '-' || substr(hex(OLD.id), 13, 4) || '-' || substr(hex(OLD.id), 17, 4) || '-' || substr(hex(OLD.id), 21)),
+            'namespace_id', OLD.namespace_id,
+            'name', OLD.name,
+            'description', OLD.description,
+            'created_at', OLD.created_at,
+            'updated_at', OLD.updated_at,
+            'deleted_at', OLD.deleted_at
+        )
+    );
+END;
+
+-- Triggers: log_relation_rules_insert, log_relation_rules_update, log_relation_rules_delete
+-- Records all changes to relation rules in the audit log
+-- Updates that only refresh `updated_at` come from update_relation_rules_timestamp and are skipped
+CREATE TRIGGER IF NOT EXISTS log_relation_rules_insert
+AFTER INSERT ON relation_rules
+FOR EACH ROW
+BEGIN
+    INSERT INTO audit_log (actor, action, resource_type, resource_id, details)
+    VALUES (
+        'system',
+        'INSERT',
+        'relation_rules',
+        lower(substr(hex(NEW.id), 1, 8) || '-' || substr(hex(NEW.id), 9, 4) || '-' || substr(hex(NEW.id), 13, 4) || '-' || substr(hex(NEW.id), 17, 4) || '-' || substr(hex(NEW.id), 21)),
+        json_object(
+            'id', lower(substr(hex(NEW.id), 1, 8) || '-' || substr(hex(NEW.id), 9, 4) || '-' || substr(hex(NEW.id), 13, 4) || '-' || substr(hex(NEW.id), 17, 4) || '-' || substr(hex(NEW.id), 21)),
+            'namespace_id', NEW.namespace_id,
+            'relation_name', NEW.relation_name,
+            'rule_type', NEW.rule_type,
+            'ttu_object_namespace', NEW.ttu_object_namespace,
+            'ttu_relation', NEW.ttu_relation,
+            'child_relations', json(NEW.child_relations),
+            'expression', NEW.expression,
+            'priority', NEW.priority,
+            'created_at', NEW.created_at,
+            'updated_at', NEW.updated_at,
+            'deleted_at', NEW.deleted_at
+        )
+    );
+END;
+
+CREATE TRIGGER IF NOT EXISTS log_relation_rules_update
+AFTER UPDATE ON relation_rules
+FOR EACH ROW WHEN NOT (
+    NEW.updated_at IS NOT OLD.updated_at
+    AND NEW.id IS OLD.id
+    AND NEW.namespace_id IS OLD.namespace_id
+    AND NEW.relation_name IS OLD.relation_name
+    AND NEW.rule_type IS OLD.rule_type
+    AND NEW.ttu_object_namespace IS OLD.ttu_object_namespace
+    AND NEW.ttu_relation IS OLD.ttu_relation
+    AND NEW.child_relations IS OLD.child_relations
+    AND NEW.expression IS OLD.expression
+    AND NEW.priority IS OLD.priority
+    AND NEW.created_at IS OLD.created_at
+    AND NEW.deleted_at IS OLD.deleted_at
+)
+BEGIN
+    INSERT INTO audit_log (actor, action, resource_type, resource_id, details)
+    VALUES (
+        'system',
+        'UPDATE',
+        'relation_rules',
+        lower(substr(hex(NEW.id), 1, 8) || '-' || substr(hex(NEW.id), 9, 4) || '-' || substr(hex(NEW.id), 13, 4) || '-' || substr(hex(NEW.id), 17, 4) || '-' || substr(hex(NEW.id), 21)),
+        json_object(
+            'previous', json_object(
+                'id', lower(substr(hex(OLD.id), 1, 8) || '-' || substr(hex(OLD.id), 9, 4) || '-' || substr(hex(OLD.id), 13, 4) || '-' || substr(hex(OLD.id), 17, 4) || '-' || substr(hex(OLD.id), 21)),
+                'namespace_id', OLD.namespace_id,
+                'relation_name', OLD.relation_name,
+                'rule_type', OLD.rule_type,
+                'ttu_object_namespace', OLD.ttu_object_namespace,
+                'ttu_relation', OLD.ttu_relation,
+                'child_relations', json(OLD.child_relations),
+                'expression', OLD.expression,
+                'priority', OLD.priority,
+                'created_at', OLD.created_at,
+                'updated_at', OLD.updated_at,
+                'deleted_at', OLD.deleted_at
+            ),
+            'new', json_object(
+                'id', lower(substr(hex(NEW.id), 1, 8) || '-' || substr(hex(NEW.id), 9, 4) || '-' || substr(hex(NEW.id), 13, 4) || '-' || substr(hex(NEW.id), 17, 4) || '-' || substr(hex(NEW.id), 21)),
+                'namespace_id', NEW.namespace_id,
+                'relation_name', NEW.relation_name,
+                'rule_type', NEW.rule_type,
+                'ttu_object_namespace', NEW.ttu_object_namespace,
+                'ttu_relation', NEW.ttu_relation,
+                'child_relations', json(NEW.child_relations),
+                'expression', NEW.expression,
+                'priority', NEW.priority,
+                'created_at', NEW.created_at,
+                'updated_at', NEW.updated_at,
+                'deleted_at', NEW.deleted_at
+            )
+        )
+    );
+END;
+
+CREATE TRIGGER IF NOT EXISTS log_relation_rules_delete
+AFTER DELETE ON relation_rules
+FOR EACH ROW
+BEGIN
+    INSERT INTO audit_log (actor, action, resource_type, resource_id, details)
+    VALUES (
+        'system',
+        'DELETE',
+        'relation_rules',
+        lower(substr(hex(OLD.id), 1, 8) || '-' || substr(hex(OLD.id), 9, 4) || '-' || substr(hex(OLD.id), 13, 4) || '-' || substr(hex(OLD.id), 17, 4) || '-' || substr(hex(OLD.id), 21)),
+        json_object(
+            'id', lower(substr(hex(OLD.id), 1, 8) || '-' || substr(hex(OLD.id), 9, 4) || '-' || substr(hex(OLD.id), 13, 4) || '-' || substr(hex(OLD.id), 17, 4) || '-' || substr(hex(OLD.id), 21)),
+            'namespace_id', OLD.namespace_id,
+            'relation_name', OLD.relation_name,
+            'rule_type', OLD.rule_type,
+            'ttu_object_namespace', OLD.ttu_object_namespace,
+            'ttu_relation', OLD.ttu_relation,
+            'child_relations', json(OLD.child_relations),
+            'expression', OLD.expression,
+            'priority', OLD.priority,
+            'created_at', OLD.created_at,
+            'updated_at', OLD.updated_at,
+            'deleted_at', OLD.deleted_at
+        )
+    );
+END;
+
+-- Triggers: log_relationship_tuples_insert, log_relationship_tuples_update, log_relationship_tuples_delete
+-- Records all permission relationship changes in the audit log
+CREATE TRIGGER IF NOT EXISTS log_relationship_tuples_insert
+AFTER INSERT ON relationship_tuples
+FOR EACH ROW
+BEGIN
+    INSERT INTO audit_log (actor, action, resource_type, resource_id, details)
+    VALUES (
+        'system',
+        'INSERT',
+        'relationship_tuples',
+        lower(substr(hex(NEW.id), 1, 8) || '-' || substr(hex(NEW.id), 9, 4) || '-' || substr(hex(NEW.id), 13, 4) || '-' || substr(hex(NEW.id), 17, 4) || '-' || substr(hex(NEW.id), 21)),
+        json_object(
+            'id', lower(substr(hex(NEW.id), 1, 8) || '-' || substr(hex(NEW.id), 9, 4) || '-' || substr(hex(NEW.id), 13, 4) || '-' || substr(hex(NEW.id), 17, 4) || '-' || substr(hex(NEW.id), 21)),
+            'namespace_id', NEW.namespace_id,
+            'object_id', NEW.object_id,
+            'relation', NEW.relation,
+            'subject_type', NEW.subject_type,
+            'subject_id', NEW.subject_id,
+            'userset_namespace', NEW.userset_namespace,
+            'userset_relation', NEW.userset_relation,
+            'created_at', NEW.created_at,
+            'updated_at', NEW.updated_at,
+            'zookie_token', NEW.zookie_token
+        )
+    );
+END;
+
+CREATE TRIGGER IF NOT EXISTS log_relationship_tuples_update
+AFTER UPDATE ON relationship_tuples
+FOR EACH ROW
+BEGIN
+    INSERT INTO audit_log (actor, action, resource_type, resource_id, details)
+    VALUES (
+        'system',
+        'UPDATE',
+        'relationship_tuples',
+        lower(substr(hex(NEW.id), 1, 8) || '-' || substr(hex(NEW.id), 9, 4) || '-' || substr(hex(NEW.id), 13, 4) || '-' || substr(hex(NEW.id), 17, 4) || '-' || substr(hex(NEW.id), 21)),
+        json_object(
+            'previous', json_object(
+                'id', lower(substr(hex(OLD.id), 1, 8) || '-' || substr(hex(OLD.id), 9, 4) || '-' || substr(hex(OLD.id), 13, 4) || '-' || substr(hex(OLD.id), 17, 4) || '-' || substr(hex(OLD.id), 21)),
+                'namespace_id', OLD.namespace_id,
+                'object_id', OLD.object_id,
+                'relation', OLD.relation,
+                'subject_type', OLD.subject_type,
+                'subject_id', OLD.subject_id,
+                'userset_namespace', OLD.userset_namespace,
+                'userset_relation', OLD.userset_relation,
+                'created_at', OLD.created_at,
+                'updated_at', OLD.updated_at,
+                'zookie_token', OLD.zookie_token
+            ),
+            'new', json_object(
+                'id', lower(substr(hex(NEW.id), 1, 8) || '-' || substr(hex(NEW.id), 9, 4) || '-' || substr(hex(NEW.id), 13, 4) || '-' || substr(hex(NEW.id), 17, 4) || '-' || substr(hex(NEW.id), 21)),
+                'namespace_id', NEW.namespace_id,
+                'object_id', NEW.object_id,
+                'relation', NEW.relation,
+                'subject_type', NEW.subject_type,
+                'subject_id', NEW.subject_id,
+                'userset_namespace', NEW.userset_namespace,
+                'userset_relation', NEW.userset_relation,
+                'created_at', NEW.created_at,
+                'updated_at', NEW.updated_at,
+                'zookie_token', NEW.zookie_token
+            )
+        )
+    );
+END;
+
+CREATE TRIGGER IF NOT EXISTS log_relationship_tuples_delete
+AFTER DELETE ON relationship_tuples
+FOR EACH ROW
+BEGIN
+    INSERT INTO audit_log (actor, action, resource_type, resource_id, details)
+    VALUES (
+        'system',
+        'DELETE',
+        'relationship_tuples',
+        lower(substr(hex(OLD.id), 1, 8) || '-' || substr(hex(OLD.id), 9, 4) || '-' || substr(hex(OLD.id), 13, 4) || '-' || substr(hex(OLD.id), 17, 4) || '-' || substr(hex(OLD.id), 21)),
+        json_object(
+            'id', lower(substr(hex(OLD.id), 1, 8) || '-' || substr(hex(OLD.id), 9, 4) || '-' || substr(hex(OLD.id), 13, 4) || '-' || substr(hex(OLD.id), 17, 4) || '-' || substr(hex(OLD.id), 21)),
+            'namespace_id', OLD.namespace_id,
+            'object_id', OLD.object_id,
+            'relation', OLD.relation,
+            'subject_type', OLD.subject_type,
+            'subject_id', OLD.subject_id,
+            'userset_namespace', OLD.userset_namespace,
+            'userset_relation', OLD.userset_relation,
+            'created_at', OLD.created_at,
+            'updated_at', OLD.updated_at,
+            'zookie_token', OLD.zookie_token
+        )
+    );
+END;
+
+-- Triggers: log_zookies_insert, log_zookies_update, log_zookies_delete
+-- Records all consistency token changes in the audit log
+CREATE TRIGGER IF NOT EXISTS log_zookies_insert
+AFTER INSERT ON zookies
+FOR EACH ROW
+BEGIN
+    INSERT INTO audit_log (actor, action, resource_type, resource_id, details)
+    VALUES (
+        'system',
+        'INSERT',
+        'zookies',
+        NEW.token,
+        json_object(
+            'token', NEW.token,
+            'timestamp', NEW.timestamp,
+            'version', NEW.version,
+            'transaction_id', lower(substr(hex(NEW.transaction_id), 1, 8) || '-' || substr(hex(NEW.transaction_id), 9, 4) || '-' || substr(hex(NEW.transaction_id), 13, 4) || '-' || substr(hex(NEW.transaction_id), 17, 4) || '-' || substr(hex(NEW.transaction_id), 21)),
+            'shard_id', NEW.shard_id,
+            'created_at', NEW.created_at,
+            'expired_at', NEW.expired_at
+        )
+    );
+END;
+
+CREATE TRIGGER IF NOT EXISTS log_zookies_update
+AFTER UPDATE ON zookies
+FOR EACH ROW
+BEGIN
+    INSERT INTO audit_log (actor, action, resource_type, resource_id, details)
+    VALUES (
+        'system',
+        'UPDATE',
+        'zookies',
+        NEW.token,
+        json_object(
+            'previous', json_object(
+                'token', OLD.token,
+                'timestamp', OLD.timestamp,
+                'version', OLD.version,
+                'transaction_id', lower(substr(hex(OLD.transaction_id), 1, 8) || '-' || substr(hex(OLD.transaction_id), 9, 4) || '-' || substr(hex(OLD.transaction_id), 13, 4) || '-' || substr(hex(OLD.transaction_id), 17, 4) || '-' || substr(hex(OLD.transaction_id), 21)),
+                'shard_id', OLD.shard_id,
+                'created_at', OLD.created_at,
+                'expired_at', OLD.expired_at
+            ),
+            'new', json_object(
+                'token', NEW.token,
+                'timestamp', NEW.timestamp,
+                'version', NEW.version,
+                'transaction_id', lower(substr(hex(NEW.transaction_id), 1, 8) || '-' || substr(hex(NEW.transaction_id), 9, 4) || '-' || substr(hex(NEW.transaction_id), 13, 4) || '-' || substr(hex(NEW.transaction_id), 17, 4) || '-' || substr(hex(NEW.transaction_id), 21)),
+                'shard_id', NEW.shard_id,
+                'created_at', NEW.created_at,
+                'expired_at', NEW.expired_at
+            )
+        )
+    );
+END;
+
+CREATE TRIGGER IF NOT EXISTS log_zookies_delete
+AFTER DELETE ON zookies
+FOR EACH ROW
+BEGIN
+    INSERT INTO audit_log (actor, action, resource_type, resource_id, details)
+    VALUES (
+        'system',
+        'DELETE',
+        'zookies',
+        OLD.token,
+        json_object(
+            'token', OLD.token,
+            'timestamp', OLD.timestamp,
+            'version', OLD.version,
+            'transaction_id', lower(substr(hex(OLD.transaction_id), 1, 8) || '-' || substr(hex(OLD.transaction_id), 9, 4) || '-' || substr(hex(OLD.transaction_id), 13, 4) || '-' || substr(hex(OLD.transaction_id), 17, 4) || '-' || substr(hex(OLD.transaction_id), 21)),
+            'shard_id', OLD.shard_id,
+            'created_at', OLD.created_at,
+            'expired_at', OLD.expired_at
+        )
+    );
+END;
+
+-- Triggers: log_transaction_log_insert, log_transaction_log_update, log_transaction_log_delete
+-- Records all transaction log changes in the audit log for meta-auditing
+CREATE TRIGGER IF NOT EXISTS log_transaction_log_insert
+AFTER INSERT ON transaction_log
+FOR EACH ROW
+BEGIN
+    INSERT INTO audit_log (actor, action, resource_type, resource_id, details)
+    VALUES (
+        'system',
+        'INSERT',
+        'transaction_log',
+        lower(substr(hex(NEW.id), 1, 8) || '-' || substr(hex(NEW.id), 9, 4) || '-' || substr(hex(NEW.id), 13, 4) || '-' || substr(hex(NEW.id), 17, 4) || '-' || substr(hex(NEW.id), 21)),
+        json_object(
+            'id', lower(substr(hex(NEW.id), 1, 8) || '-' || substr(hex(NEW.id), 9, 4) || '-' || substr(hex(NEW.id), 13, 4) || '-' || substr(hex(NEW.id), 17, 4) || '-' || substr(hex(NEW.id), 21)),
+            'timestamp', NEW.timestamp,
+            'version_number', NEW.version_number,
+            'operation', NEW.operation,
+            'namespace_id', NEW.namespace_id,
+            'object_id', NEW.object_id,
+            'relation', NEW.relation,
+            'subject_type', NEW.subject_type,
+            'subject_id', NEW.subject_id,
+            'userset_namespace', NEW.userset_namespace,
+            'userset_relation', NEW.userset_relation,
+            'zookie_token', NEW.zookie_token,
+            'payload', json(NEW.payload),
+            'status', NEW.status
+        )
+    );
+END;
+
+CREATE TRIGGER IF NOT EXISTS log_transaction_log_update
+AFTER UPDATE ON transaction_log
+FOR EACH ROW
+BEGIN
+    INSERT INTO audit_log (actor, action, resource_type, resource_id, details)
+    VALUES (
+        'system',
+        'UPDATE',
+        'transaction_log',
+        lower(substr(hex(NEW.id), 1, 8) || '-' || substr(hex(NEW.id), 9, 4) || '-' || substr(hex(NEW.id), 13, 4) || '-' || substr(hex(NEW.id), 17, 4) || '-' || substr(hex(NEW.id), 21)),
+        json_object(
+            'previous', json_object(
+                'id', lower(substr(hex(OLD.id), 1, 8) || '-' || substr(hex(OLD.id), 9, 4) || '-' || substr(hex(OLD.id), 13, 4) || '-' || substr(hex(OLD.id), 17, 4) || '-' || substr(hex(OLD.id), 21)),
+                'timestamp', OLD.timestamp,
+                'version_number', OLD.version_number,
+                'operation', OLD.operation,
+                'namespace_id', OLD.namespace_id,
+                'object_id', OLD.object_id,
+                'relation', OLD.relation,
+                'subject_type', OLD.subject_type,
+                'subject_id', OLD.subject_id,
+                'userset_namespace', OLD.userset_namespace,
+                'userset_relation', OLD.userset_relation,
+                'zookie_token', OLD.zookie_token,
+                'payload', json(OLD.payload),
+                'status', OLD.status
+            ),
+            'new', json_object(
+                'id', lower(substr(hex(NEW.id), 1, 8) || '-' || substr(hex(NEW.id), 9, 4) || '-' || substr(hex(NEW.id), 13, 4) || '-' || substr(hex(NEW.id), 17, 4) || '-' || substr(hex(NEW.id), 21)),
+                'timestamp', NEW.timestamp,
+                'version_number', NEW.version_number,
+                'operation', NEW.operation,
+                'namespace_id', NEW.namespace_id,
+                'object_id', NEW.object_id,
+                'relation', NEW.relation,
+                'subject_type', NEW.subject_type,
+                'subject_id', NEW.subject_id,
+                'userset_namespace', NEW.userset_namespace,
+                'userset_relation', NEW.userset_relation,
+                'zookie_token', NEW.zookie_token,
+                'payload', json(NEW.payload),
+                'status', NEW.status
+            )
+        )
+    );
+END;
+
+CREATE TRIGGER IF NOT EXISTS log_transaction_log_delete
+AFTER DELETE ON transaction_log
+FOR EACH ROW
+BEGIN
+    INSERT INTO audit_log (actor, action, resource_type, resource_id, details)
+    VALUES (
+        'system',
+        'DELETE',
+        'transaction_log',
+        lower(substr(hex(OLD.id), 1, 8) || '-' || substr(hex(OLD.id), 9, 4) || '-' || substr(hex(OLD.id), 13, 4) || '-' || substr(hex(OLD.id), 17, 4) || '-' || substr(hex(OLD.id), 21)),
+        json_object(
+            'id', lower(substr(hex(OLD.id), 1, 8) || '-' || substr(hex(OLD.id), 9, 4) || '-' || substr(hex(OLD.id), 13, 4) || '-' || substr(hex(OLD.id), 17, 4) || '-' || substr(hex(OLD.id), 21)),
+            'timestamp', OLD.timestamp,
+            'version_number', OLD.version_number,
+            'operation', OLD.operation,
+            'namespace_id', OLD.namespace_id,
+            'object_id', OLD.object_id,
+            'relation', OLD.relation,
+            'subject_type', OLD.subject_type,
+            'subject_id', OLD.subject_id,
+            'userset_namespace', OLD.userset_namespace,
+            'userset_relation', OLD.userset_relation,
+            'zookie_token', OLD.zookie_token,
+            'payload', json(OLD.payload),
+            'status', OLD.status
+        )
+    );
+END;
+
+-- Triggers: log_replication_status_insert, log_replication_status_update, log_replication_status_delete
+-- Records all replication status changes for monitoring distributed system health
+CREATE TRIGGER IF NOT EXISTS log_replication_status_insert
+AFTER INSERT ON replication_status
+FOR EACH ROW
+BEGIN
+    INSERT INTO audit_log (actor, action, resource_type, resource_id, details)
+    VALUES (
+        'system',
+        'INSERT',
+        'replication_status',
+        NEW.node_id,
+        json_object(
+            'node_id', NEW.node_id,
+            'last_applied_version', NEW.last_applied_version,
+            'last_applied_timestamp', NEW.last_applied_timestamp,
+            'heartbeat_at', NEW.heartbeat_at,
+            'is_primary', json(CASE WHEN NEW.is_primary THEN 'true' ELSE 'false' END),
+            'status', NEW.status,
+            'sync_lag_ms', NEW.sync_lag_ms
+        )
+    );
+END;
+
+CREATE TRIGGER IF NOT EXISTS log_replication_status_update
+AFTER UPDATE ON replication_status
+FOR EACH ROW
+BEGIN
+    INSERT INTO audit_log (actor, action, resource_type, resource_id, details)
+    VALUES (
+        'system',
+        'UPDATE',
+        'replication_status',
+        NEW.node_id,
+        json_object(
+            'previous', json_object(
+                'node_id', OLD.node_id,
+                'last_applied_version', OLD.last_applied_version,
+                'last_applied_timestamp', OLD.last_applied_timestamp,
+                'heartbeat_at', OLD.heartbeat_at,
+                'is_primary', json(CASE WHEN OLD.is_primary THEN 'true' ELSE 'false' END),
+                'status', OLD.status,
+                'sync_lag_ms', OLD.sync_lag_ms
+            ),
+            'new', json_object(
+                'node_id', NEW.node_id,
+                'last_applied_version', NEW.last_applied_version,
+                'last_applied_timestamp', NEW.last_applied_timestamp,
+                'heartbeat_at', NEW.heartbeat_at,
+                'is_primary', json(CASE WHEN NEW.is_primary THEN 'true' ELSE 'false' END),
+                'status', NEW.status,
+                'sync_lag_ms', NEW.sync_lag_ms
+            )
+        )
+    );
+END;
+
+CREATE TRIGGER IF NOT EXISTS log_replication_status_delete
+AFTER DELETE ON replication_status
+FOR EACH ROW
+BEGIN
+    INSERT INTO audit_log (actor, action, resource_type, resource_id, details)
+    VALUES (
+        'system',
+        'DELETE',
+        'replication_status',
+        OLD.node_id,
+        json_object(
+            'node_id', OLD.node_id,
+            'last_applied_version', OLD.last_applied_version,
+            'last_applied_timestamp', OLD.last_applied_timestamp,
+            'heartbeat_at', OLD.heartbeat_at,
+            'is_primary', json(CASE WHEN OLD.is_primary THEN 'true' ELSE 'false' END),
+            'status', OLD.status,
+            'sync_lag_ms', OLD.sync_lag_ms
+        )
+    );
+END;
+
+-- Triggers: log_auth_decisions_insert, log_auth_decisions_update, log_auth_decisions_delete
+-- Records all changes to authorization decision records for compliance tracking
+CREATE TRIGGER IF NOT EXISTS log_auth_decisions_insert
+AFTER INSERT ON auth_decisions
+FOR EACH ROW
+BEGIN
+    INSERT INTO audit_log (actor, action, resource_type, resource_id, details)
+    VALUES (
+        'system',
+        'INSERT',
+        'auth_decisions',
+        lower(substr(hex(NEW.id), 1, 8) || '-' || substr(hex(NEW.id), 9, 4) || '-' || substr(hex(NEW.id), 13, 4) || '-' || substr(hex(NEW.id), 17, 4) || '-' || substr(hex(NEW.id), 21)),
+        json_object(
+            'id', lower(substr(hex(NEW.id), 1, 8) || '-' || substr(hex(NEW.id), 9, 4) || '-' || substr(hex(NEW.id), 13, 4) || '-' || substr(hex(NEW.id), 17, 4) || '-' || substr(hex(NEW.id), 21)),
+            'timestamp', NEW.timestamp,
+            'request_id', lower(substr(hex(NEW.request_id), 1, 8) || '-' || substr(hex(NEW.request_id), 9, 4) || '-' || substr(hex(NEW.request_id), 13, 4) || '-' || substr(hex(NEW.request_id), 17, 4) || '-' || substr(hex(NEW.request_id), 21)),
+            'subject_type', NEW.subject_type,
+            'subject_id', NEW.subject_id,
+            'namespace_id', NEW.namespace_id,
+            'object_id', NEW.object_id,
+            'relation', NEW.relation,
+            'permitted', json(CASE WHEN NEW.permitted THEN 'true' ELSE 'false' END),
+            'cached', json(CASE WHEN NEW.cached THEN 'true' ELSE 'false' END),
+            'latency_ms', NEW.latency_ms,
+            'evaluation_path', json(NEW.evaluation_path),
+            'zookie_token', NEW.zookie_token,
+            'waited_for_consistency', json(CASE WHEN NEW.waited_for_consistency THEN 'true' ELSE 'false' END),
+            'consistency_wait_ms', NEW.consistency_wait_ms
+        )
+    );
+END;
+
+CREATE TRIGGER IF NOT EXISTS log_auth_decisions_update
+AFTER UPDATE ON auth_decisions
+FOR EACH ROW
+BEGIN
+    INSERT INTO audit_log (actor, action, resource_type, resource_id, details)
+    VALUES (
+        'system',
+        'UPDATE',
+        'auth_decisions',
+        lower(substr(hex(NEW.id), 1, 8) || '-' || substr(hex(NEW.id), 9, 4) || '-' || substr(hex(NEW.id), 13, 4) || '-' || substr(hex(NEW.id), 17, 4) || '-' || substr(hex(NEW.id), 21)),
+        json_object(
+            'previous', json_object(
+                'id', lower(substr(hex(OLD.id), 1, 8) || '-' || substr(hex(OLD.id), 9, 4) || '-' || substr(hex(OLD.id), 13, 4) || '-' || substr(hex(OLD.id), 17, 4) || '-' || substr(hex(OLD.id), 21)),
+                'timestamp', OLD.timestamp,
+                'request_id', lower(substr(hex(OLD.request_id), 1, 8) || '-' || substr(hex(OLD.request_id), 9, 4) || '-' || substr(hex(OLD.request_id), 13, 4) || '-' || substr(hex(OLD.request_id), 17, 4) || '-' || substr(hex(OLD.request_id), 21)),
+                'subject_type', OLD.subject_type,
+                'subject_id', OLD.subject_id,
+                'namespace_id', OLD.namespace_id,
+                'object_id', OLD.object_id,
+                'relation', OLD.relation,
+                'permitted', json(CASE WHEN OLD.permitted THEN 'true' ELSE 'false' END),
+                'cached', json(CASE WHEN OLD.cached THEN 'true' ELSE 'false' END),
+                'latency_ms', OLD.latency_ms,
+                'evaluation_path', json(OLD.evaluation_path),
+                'zookie_token', OLD.zookie_token,
+                'waited_for_consistency', json(CASE WHEN OLD.waited_for_consistency THEN 'true' ELSE 'false' END),
+                'consistency_wait_ms', OLD.consistency_wait_ms
+            ),
+            'new', json_object(
+                'id', lower(substr(hex(NEW.id), 1, 8) || '-' || substr(hex(NEW.id), 9, 4) || '-' || substr(hex(NEW.id), 13, 4) || '-' || substr(hex(NEW.id), 17, 4) || '-' || substr(hex(NEW.id), 21)),
+                'timestamp', NEW.timestamp,
+                'request_id', lower(substr(hex(NEW.request_id), 1, 8) || '-' || substr(hex(NEW.request_id), 9, 4) || '-' || substr(hex(NEW.request_id), 13, 4) || '-' || substr(hex(NEW.request_id), 17, 4) || '-' || substr(hex(NEW.request_id), 21)),
+                'subject_type', NEW.subject_type,
+                'subject_id', NEW.subject_id,
+                'namespace_id', NEW.namespace_id,
+                'object_id', NEW.object_id,
+                'relation', NEW.relation,
+                'permitted', json(CASE WHEN NEW.permitted THEN 'true' ELSE 'false' END),
+                'cached', json(CASE WHEN NEW.cached THEN 'true' ELSE 'false' END),
+                'latency_ms', NEW.latency_ms,
+                'evaluation_path', json(NEW.evaluation_path),
+                'zookie_token', NEW.zookie_token,
+                'waited_for_consistency', json(CASE WHEN NEW.waited_for_consistency THEN 'true' ELSE 'false' END),
+                'consistency_wait_ms', NEW.consistency_wait_ms
+            )
+        )
+    );
+END;
+
+CREATE TRIGGER IF NOT EXISTS log_auth_decisions_delete
+AFTER DELETE ON auth_decisions
+FOR EACH ROW
+BEGIN
+    INSERT INTO audit_log (actor, action, resource_type, resource_id, details)
+    VALUES (
+        'system',
+        'DELETE',
+        'auth_decisions',
+        lower(substr(hex(OLD.id), 1, 8) || '-' || substr(hex(OLD.id), 9, 4) || '-' || substr(hex(OLD.id), 13, 4) || '-' || substr(hex(OLD.id), 17, 4) || '-' || substr(hex(OLD.id), 21)),
+        json_object(
+            'id', lower(substr(hex(OLD.id), 1, 8) || '-' || substr(hex(OLD.id), 9, 4) || '-' || substr(hex(OLD.id), 13, 4) || '-' || substr(hex(OLD.id), 17, 4) || '-' || substr(hex(OLD.id), 21)),
+            'timestamp', OLD.timestamp,
+            'request_id', lower(substr(hex(OLD.request_id), 1, 8) || '-' || substr(hex(OLD.request_id), 9, 4) || '-' || substr(hex(OLD.request_id), 13, 4) || '-' || substr(hex(OLD.request_id), 17, 4) || '-' || substr(hex(OLD.request_id), 21)),
+            'subject_type', OLD.subject_type,
+            'subject_id', OLD.subject_id,
+            'namespace_id', OLD.namespace_id,
+            'object_id', OLD.object_id,
+            'relation', OLD.relation,
+            'permitted', json(CASE WHEN OLD.permitted THEN 'true' ELSE 'false' END),
+            'cached', json(CASE WHEN OLD.cached THEN 'true' ELSE 'false' END),
+            'latency_ms', OLD.latency_ms,
+            'evaluation_path', json(OLD.evaluation_path),
+            'zookie_token', OLD.zookie_token,
+            'waited_for_consistency', json(CASE WHEN OLD.waited_for_consistency THEN 'true' ELSE 'false' END),
+            'consistency_wait_ms', OLD.consistency_wait_ms
+        )
+    );
+END;
+
+-- Triggers: log_permissions_cache_insert, log_permissions_cache_update, log_permissions_cache_delete
+-- Records all changes to the permissions cache for debugging and auditing
+CREATE TRIGGER IF NOT EXISTS log_permissions_cache_insert
+AFTER INSERT ON permissions_cache
+FOR EACH ROW
+BEGIN
+    INSERT INTO audit_log (actor, action, resource_type, resource_id, details)
+    VALUES (
+        'system',
+        'INSERT',
+        'permissions_cache',
+        lower(substr(hex(NEW.id), 1, 8) || '-' || substr(hex(NEW.id), 9, 4) || '-' || substr(hex(NEW.id), 13, 4) || '-' || substr(hex(NEW.id), 17, 4) || '-' || substr(hex(NEW.id), 21)),
+        json_object(
+            'id', lower(substr(hex(NEW.id), 1, 8) || '-' || substr(hex(NEW.id), 9, 4) || '-' || substr(hex(NEW.id), 13, 4) || '-' || substr(hex(NEW.id), 17, 4) || '-' || substr(hex(NEW.id), 21)),
+            'namespace_id', NEW.namespace_id,
+            'object_id', NEW.object_id,
+            'relation', NEW.relation,
+            'subject_type', NEW.subject_type,
+            'subject_id', NEW.subject_id,
+            'permitted', json(CASE WHEN NEW.permitted THEN 'true' ELSE 'false' END),
+            'computed_at', NEW.computed_at,
+            'valid_until', NEW.valid_until,
+            'max_zookie_version', NEW.max_zookie_version,
+            'cache_key', NEW.cache_key
+        )
+    );
+END;
+
+CREATE TRIGGER IF NOT EXISTS log_permissions_cache_update
+AFTER UPDATE ON permissions_cache
+FOR EACH ROW
+BEGIN
+    INSERT INTO audit_log (actor, action, resource_type, resource_id, details)
+    VALUES (
+        'system',
+        'UPDATE',
+        'permissions_cache',
+        lower(substr(hex(NEW.id), 1, 8) || '-' || substr(hex(NEW.id), 9, 4) || '-' || substr(hex(NEW.id), 13, 4) || '-' || substr(hex(NEW.id), 17, 4) || '-' || substr(hex(NEW.id), 21)),
+        json_object(
+            'previous', json_object(
+                'id', lower(substr(hex(OLD.id), 1, 8) || '-' || substr(hex(OLD.id), 9, 4) || '-' || substr(hex(OLD.id), 13, 4) || '-' || substr(hex(OLD.id), 17, 4) || '-' || substr(hex(OLD.id), 21)),
+                'namespace_id', OLD.namespace_id,
+                'object_id', OLD.object_id,
+                'relation', OLD.relation,
+                'subject_type', OLD.subject_type,
+                'subject_id', OLD.subject_id,
+                'permitted', json(CASE WHEN OLD.permitted THEN 'true' ELSE 'false' END),
+                'computed_at', OLD.computed_at,
+                'valid_until', OLD.valid_until,
+                'max_zookie_version', OLD.max_zookie_version,
+                'cache_key', OLD.cache_key
+            ),
+            'new', json_object(
+                'id', lower(substr(hex(NEW.id), 1, 8) || '-' || substr(hex(NEW.id), 9, 4) || '-' || substr(hex(NEW.id), 13, 4) || '-' || substr(hex(NEW.id), 17, 4) || '-' || substr(hex(NEW.id), 21)),
+                'namespace_id', NEW.namespace_id,
+                'object_id', NEW.object_id,
+                'relation', NEW.relation,
+                'subject_type', NEW.subject_type,
+                'subject_id', NEW.subject_id,
+                'permitted', json(CASE WHEN NEW.permitted THEN 'true' ELSE 'false' END),
+                'computed_at', NEW.computed_at,
+                'valid_until', NEW.valid_until,
+                'max_zookie_version', NEW.max_zookie_version,
+                'cache_key', NEW.cache_key
+            )
+        )
+    );
+END;
+
+CREATE TRIGGER IF NOT EXISTS log_permissions_cache_delete
+AFTER DELETE ON permissions_cache
+FOR EACH ROW
+BEGIN
+    INSERT INTO audit_log (actor, action, resource_type, resource_id, details)
+    VALUES (
+        'system',
+        'DELETE',
+        'permissions_cache',
+        lower(substr(hex(OLD.id), 1, 8) || '-' || substr(hex(OLD.id), 9, 4) || '-' || substr(hex(OLD.id), 13, 4) || '-' || substr(hex(OLD.id), 17, 4) || '-' || substr(hex(OLD.id), 21)),
+        json_object(
+            'id', lower(substr(hex(OLD.id), 1, 8) || '-' || substr(hex(OLD.id), 9, 4) || '-' || substr(hex(OLD.id), 13, 4) || '-' || substr(hex(OLD.id), 17, 4) || '-' || substr(hex(OLD.id), 21)),
+            'namespace_id', OLD.namespace_id,
+            'object_id', OLD.object_id,
+            'relation', OLD.relation,
+            'subject_type', OLD.subject_type,
+            'subject_id', OLD.subject_id,
+            'permitted', json(CASE WHEN OLD.permitted THEN 'true' ELSE 'false' END),
+            'computed_at', OLD.computed_at,
+            'valid_until', OLD.valid_until,
+            'max_zookie_version', OLD.max_zookie_version,
+            'cache_key', OLD.cache_key
+        )
+    );
+END;
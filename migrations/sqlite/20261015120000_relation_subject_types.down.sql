@@ -0,0 +1,95 @@
+-- =================================================================================================
+-- Reversion Date: 2026-10-15 12:00:00.000000
+-- Description: SQLite port of `relations.subject_types`, see the Postgres migration
+-- =================================================================================================
+
+DROP TRIGGER IF EXISTS log_relations_delete;
+DROP TRIGGER IF EXISTS log_relations_update;
+DROP TRIGGER IF EXISTS log_relations_insert;
+
+ALTER TABLE relations DROP COLUMN subject_types;
+
+CREATE TRIGGER IF NOT EXISTS log_relations_insert
+AFTER INSERT ON relations
+FOR EACH ROW
+BEGIN
+    INSERT INTO audit_log (actor, action, resource_type, resource_id, details)
+    VALUES (
+        'system',
+        'INSERT',
+        'relations',
+        lower(substr(hex(NEW.id), 1, 8) || '-' || substr(hex(NEW.id), 9, 4) || '-' || substr(hex(NEW.id), 13, 4) || '-' || substr(hex(NEW.id), 17, 4) || '-' || substr(hex(NEW.id), 21)),
+        json_object(
+            'id', lower(substr(hex(NEW.id), 1, 8) || '-' || substr(hex(NEW.id), 9, 4) || '-' || substr(hex(NEW.id), 13, 4) || '-' || substr(hex(NEW.id), 17, 4) || '-' || substr(hex(NEW.id), 21)),
+            'namespace_id', NEW.namespace_id,
+            'name', NEW.name,
+            'description', NEW.description,
+            'created_at', NEW.created_at,
+            'updated_at', NEW.updated_at,
+            'deleted_at', NEW.deleted_at
+        )
+    );
+END;
+
+CREATE TRIGGER IF NOT EXISTS log_relations_update
+AFTER UPDATE ON relations
+FOR EACH ROW WHEN NOT (
+    NEW.updated_at IS NOT OLD.updated_at
+    AND NEW.id IS OLD.id
+    AND NEW.namespace_id IS OLD.namespace_id
+    AND NEW.name IS OLD.name
+    AND NEW.description IS OLD.description
+    AND NEW.created_at IS OLD.created_at
+    AND NEW.deleted_at IS OLD.deleted_at
+)
+BEGIN
+    INSERT INTO audit_log (actor, action, resource_type, resource_id, details)
+    VALUES (
+        'system',
+        'UPDATE',
+        'relations',
+        lower(substr(hex(NEW.id), 1, 8) || '-' || substr(hex(NEW.id), 9, 4) || '-' || substr(hex(NEW.id), 13, 4) || '-' || substr(hex(NEW.id), 17, 4) || '-' || substr(hex(NEW.id), 21)),
+        json_object(
+            'previous', json_object(
+                'id', lower(substr(hex(OLD.id), 1, 8) || '-' || substr(hex(OLD.id), 9, 4) || '-' || substr(hex(OLD.id), 13, 4) || '-' || substr(hex(OLD.id), 17, 4) || '-' || substr(hex(OLD.id), 21)),
+                'namespace_id', OLD.namespace_id,
+                'name', OLD.name,
+                'description', OLD.description,
+                'created_at', OLD.created_at,
+                'updated_at', OLD.updated_at,
+                'deleted_at', OLD.deleted_at
+            ),
+            'new', json_object(
+                'id', lower(substr(hex(NEW.id), 1, 8) || '-' || substr(hex(NEW.id), 9, 4) || '-' || substr(hex(NEW.id), 13, 4) || '-' || substr(hex(NEW.id), 17, 4) || '-' || substr(hex(NEW.id), 21)),
+                'namespace_id', NEW.namespace_id,
+                'name', NEW.name,
+                'description', NEW.description,
+                'created_at', NEW.created_at,
+                'updated_at', NEW.updated_at,
+                'deleted_at', NEW.deleted_at
+            )
+        )
+    );
+END;
+
+CREATE TRIGGER IF NOT EXISTS log_relations_delete
+AFTER DELETE ON relations
+FOR EACH ROW
+BEGIN
+    INSERT INTO audit_log (actor, action, resource_type, resource_id, details)
+    VALUES (
+        'system',
+        'DELETE',
+        'relations',
+        lower(substr(hex(OLD.id), 1, 8) || '-' || substr(hex(OLD.id), 9, 4) || '-' || substr(hex(OLD.id), 13, 4) || '-' || substr(hex(OLD.id), 17, 4) || '-' || substr(hex(OLD.id), 21)),
+        json_object(
+            'id', lower(substr(hex(OLD.id), 1, 8) || '-' || substr(hex(OLD.id), 9, 4) || '-' || substr(hex(OLD.id), 13, 4) || '-' || substr(hex(OLD.id), 17, 4) || '-' || substr(hex(OLD.id), 21)),
+            'namespace_id', OLD.namespace_id,
+            'name', OLD.name,
+            'description', OLD.description,
+            'created_at', OLD.created_at,
+            'updated_at', OLD.updated_at,
+            'deleted_at', OLD.deleted_at
+        )
+    );
+END;
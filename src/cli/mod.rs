@@ -0,0 +1,66 @@
+use std::path::PathBuf;
+
+use clap::{Parser, Subcommand};
+
+use crate::config::ConfigArgs;
+
+#[derive(Debug, Parser)]
+#[command(name = "heimdall", version, about)]
+pub struct Cli {
+    #[command(flatten)]
+    pub config_args: ConfigArgs,
+
+    #[command(subcommand)]
+    pub command: Option<Command>,
+}
+
+#[derive(Debug, Subcommand)]
+pub enum Command {
+    /// Start the HTTP server, this is the default when no command is given
+    Serve,
+    /// Manage the database schema of the configured backend
+    #[command(subcommand)]
+    Migrate(MigrateCommand),
+    /// Read and write the namespaces, relations and rules in the schema language
+    #[command(subcommand)]
+    Schema(SchemaCommand),
+}
+
+#[derive(Debug, Subcommand)]
+pub enum MigrateCommand {
+    /// Apply all pending migrations
+    Up,
+    /// Revert the latest applied migration
+    Down {
+        /// Revert every migration newer than this version instead, 0 reverts all of them
+        #[arg(long)]
+        target: Option<i64>,
+    },
+    /// List the embedded migrations and whether they have been applied
+    Status,
+}
+
+#[derive(Debug, Subcommand)]
+pub enum SchemaCommand {
+    /// Make the stored schema match a schema file, deleting what the file leaves out
+    Apply {
+        /// Schema file to apply, `-` reads standard input
+        file: PathBuf,
+        /// Delete the tuples of removed relations and namespaces that are not migrated
+        #[arg(long)]
+        force: bool,
+        /// Move the tuples of a removed relation to another one, as `namespace#relation=target`
+        #[arg(long, value_name = "NAMESPACE#RELATION=TARGET")]
+        migrate: Vec<String>,
+        /// Print what would change without changing anything
+        #[arg(long)]
+        dry_run: bool,
+    },
+    /// Print the stored schema
+    Export,
+    /// Check a schema file and print it in canonical layout, without touching the database
+    Format {
+        /// Schema file to format, `-` reads standard input
+        file: PathBuf,
+    },
+}
@@ -0,0 +1,73 @@
+use std::{net::IpAddr, path::PathBuf};
+
+use clap::Args;
+use config::{ConfigBuilder, builder::DefaultState};
+
+use super::ConfigError;
+
+/// Command-line flags that take precedence over both the configuration file and the
+/// environment.
+#[derive(Debug, Default, Args)]
+pub struct ConfigArgs {
+    /// Path to a TOML, YAML or JSON configuration file [default: ./heimdall.{toml,yaml,json}]
+    #[arg(short, long, global = true, env = "HEIMDALL_CONFIG")]
+    pub config: Option<PathBuf>,
+
+    /// Address the HTTP server binds to
+    #[arg(long, global = true)]
+    pub ip: Option<IpAddr>,
+
+    /// Port the HTTP server listens on
+    #[arg(short, long, global = true)]
+    pub port: Option<u16>,
+
+    /// Database backend to use
+    #[arg(long, global = true, value_parser = ["postgres", "sqlite"])]
+    pub database_type: Option<String>,
+
+    /// Database server host
+    #[arg(long, global = true)]
+    pub database_host: Option<String>,
+
+    /// Database server port
+    #[arg(long, global = true)]
+    pub database_port: Option<u16>,
+
+    /// Database user name
+    #[arg(long, global = true)]
+    pub database_username: Option<String>,
+
+    /// Log filter in `RUST_LOG` syntax, e.g. `info` or `heimdall=debug,sqlx=warn`
+    #[arg(long, global = true)]
+    pub log_level: Option<String>,
+
+    /// Log output format
+    #[arg(long, global = true, value_parser = ["pretty", "json"])]
+    pub log_format: Option<String>,
+
+    /// Apply pending migrations before the server starts
+    #[arg(long, global = true)]
+    pub auto_migrate: bool,
+}
+
+impl ConfigArgs {
+    pub(super) fn apply_overrides(
+        &self,
+        builder: ConfigBuilder<DefaultState>,
+    ) -> Result<ConfigBuilder<DefaultState>, ConfigError> {
+        let builder = builder
+            .set_override_option("server_config.ip", self.ip.map(|ip| ip.to_string()))?
+            .set_override_option("server_config.port", self.port)?
+            .set_override_option("database_config.database_type", self.database_type.clone())?
+            .set_override_option("database_config.host", self.database_host.clone())?
+            .set_override_option("database_config.port", self.database_port)?
+            .set_override_option("database_config.username", self.database_username.clone())?
+            .set_override_option("logging_config.level", self.log_level.clone())?
+            .set_override_option("logging_config.format", self.log_format.clone())?
+            .set_override_option(
+                "database_config.auto_migrate",
+                self.auto_migrate.then_some(true),
+            )?;
+        Ok(builder)
+    }
+}
@@ -0,0 +1,81 @@
+use std::collections::HashMap;
+
+use serde::{Deserialize, Serialize};
+
+use super::ConfigError;
+
+/// Upper bound for `buffer_size`, so a typo cannot let queued decisions take the whole heap.
+const MAX_BUFFER_SIZE: usize = 1_000_000;
+
+/// Upper bound for `flush_interval_ms`, a minute.
+const MAX_FLUSH_INTERVAL_MS: u64 = 60_000;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct AuditConfig {
+    /// Share of checks recorded in `auth_decisions`, from 0 (none) to 1 (every check).
+    pub sample_rate: f64,
+    /// `sample_rate` of the checks on a namespace, overriding the default one. 0 opts the
+    /// namespace out of `auth_decisions`.
+    pub namespace_sample_rates: HashMap<String, f64>,
+    /// Most decisions waiting to be written. Decisions recorded while it is full are dropped
+    /// rather than slowing down checks.
+    pub buffer_size: usize,
+    /// Most decisions written in one transaction.
+    pub batch_size: usize,
+    /// Longest a decision waits to be written, in milliseconds.
+    pub flush_interval_ms: u64,
+}
+
+impl Default for AuditConfig {
+    fn default() -> Self {
+        Self {
+            sample_rate: 1.0,
+            namespace_sample_rates: HashMap::new(),
+            buffer_size: 10_000,
+            batch_size: 500,
+            flush_interval_ms: 1_000,
+        }
+    }
+}
+
+impl AuditConfig {
+    pub(super) fn validate(&self) -> Result<(), ConfigError> {
+        let invalid_rate = |rate: f64| !(0.0..=1.0).contains(&rate);
+        if invalid_rate(self.sample_rate) {
+            return Err(ConfigError::invalid(
+                "audit_config.sample_rate",
+                "must be between 0 and 1",
+            ));
+        }
+        if let Some(namespace) = self
+            .namespace_sample_rates
+            .iter()
+            .find_map(|(namespace, &rate)| invalid_rate(rate).then_some(namespace))
+        {
+            return Err(ConfigError::invalid(
+                format!("audit_config.namespace_sample_rates.{namespace}"),
+                "must be between 0 and 1",
+            ));
+        }
+        if self.buffer_size == 0 || self.buffer_size > MAX_BUFFER_SIZE {
+            return Err(ConfigError::invalid(
+                "audit_config.buffer_size",
+                format!("must be between 1 and {MAX_BUFFER_SIZE}"),
+            ));
+        }
+        if self.batch_size == 0 || self.batch_size > self.buffer_size {
+            return Err(ConfigError::invalid(
+                "audit_config.batch_size",
+                "must be between 1 and `buffer_size`",
+            ));
+        }
+        if self.flush_interval_ms == 0 || self.flush_interval_ms > MAX_FLUSH_INTERVAL_MS {
+            return Err(ConfigError::invalid(
+                "audit_config.flush_interval_ms",
+                format!("must be between 1 and {MAX_FLUSH_INTERVAL_MS}"),
+            ));
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,59 @@
+use serde::{Deserialize, Serialize};
+
+use super::ConfigError;
+
+/// Upper bound for `permissions_cache_ttl_secs` and `check_cache_ttl_secs`, a day.
+const MAX_TTL_SECS: u64 = 86_400;
+
+/// Upper bound for `check_cache_capacity`, so a typo cannot let the cache take the whole heap.
+const MAX_CHECK_CACHE_CAPACITY: usize = 10_000_000;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct CacheConfig {
+    /// How long a check result stays in `permissions_cache`, in seconds, 0 disables the cache.
+    /// Results are only served to reads whose consistency their version satisfies, so this
+    /// bounds how stale a `minimize_latency` check may be.
+    pub permissions_cache_ttl_secs: u64,
+    /// Most check results each node keeps in memory, 0 disables the in-process cache.
+    pub check_cache_capacity: usize,
+    /// How long a check result stays in the in-process cache, in seconds. Schema changes made
+    /// through another node are only picked up once the results computed before them expire.
+    pub check_cache_ttl_secs: u64,
+}
+
+impl Default for CacheConfig {
+    fn default() -> Self {
+        Self {
+            permissions_cache_ttl_secs: 60,
+            check_cache_capacity: 100_000,
+            check_cache_ttl_secs: 10,
+        }
+    }
+}
+
+impl CacheConfig {
+    pub(super) fn validate(&self) -> Result<(), ConfigError> {
+        if self.permissions_cache_ttl_secs > MAX_TTL_SECS {
+            return Err(ConfigError::invalid(
+                "cache_config.permissions_cache_ttl_secs",
+                format!("must be at most {MAX_TTL_SECS}"),
+            ));
+        }
+        if self.check_cache_capacity > MAX_CHECK_CACHE_CAPACITY {
+            return Err(ConfigError::invalid(
+                "cache_config.check_cache_capacity",
+                format!("must be at most {MAX_CHECK_CACHE_CAPACITY}"),
+            ));
+        }
+        if self.check_cache_capacity > 0
+            && (self.check_cache_ttl_secs == 0 || self.check_cache_ttl_secs > MAX_TTL_SECS)
+        {
+            return Err(ConfigError::invalid(
+                "cache_config.check_cache_ttl_secs",
+                format!("must be between 1 and {MAX_TTL_SECS}"),
+            ));
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,59 @@
+use serde::{Deserialize, Serialize};
+
+use super::ConfigError;
+
+/// Shortest accepted `zookie_secret`, the key length below which HMAC-SHA256 gets weaker.
+const MIN_SECRET_LENGTH: usize = 32;
+
+/// Upper bound for `max_wait_ms`, so a stale replica cannot hold requests indefinitely.
+const MAX_WAIT_LIMIT_MS: u64 = 60_000;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ConsistencyConfig {
+    /// Key zookies are signed with. It has no default, so that no deployment signs with a key
+    /// anyone can look up. Every node of a deployment needs the same one, and changing it
+    /// invalidates every zookie handed out before.
+    pub zookie_secret: String,
+    /// Longest an `at_least_as_fresh` read waits for the version of its zookie, in milliseconds.
+    pub max_wait_ms: u64,
+}
+
+impl Default for ConsistencyConfig {
+    fn default() -> Self {
+        Self {
+            zookie_secret: String::new(),
+            max_wait_ms: 1_000,
+        }
+    }
+}
+
+impl ConsistencyConfig {
+    pub(super) fn validate(&self) -> Result<(), ConfigError> {
+        if self.max_wait_ms > MAX_WAIT_LIMIT_MS {
+            return Err(ConfigError::invalid(
+                "consistency_config.max_wait_ms",
+                format!("must be at most {MAX_WAIT_LIMIT_MS}"),
+            ));
+        }
+        Ok(())
+    }
+
+    /// Checks `zookie_secret`. Only commands that sign or verify zookies need one, so it is not
+    /// part of [`validate`](Self::validate) and `migrate` or `schema format` run without it.
+    pub fn validate_zookie_secret(&self) -> Result<(), ConfigError> {
+        if self.zookie_secret.is_empty() {
+            return Err(ConfigError::invalid(
+                "consistency_config.zookie_secret",
+                "must be set, to the same random value on every node",
+            ));
+        }
+        if self.zookie_secret.len() < MIN_SECRET_LENGTH {
+            return Err(ConfigError::invalid(
+                "consistency_config.zookie_secret",
+                format!("must be at least {MIN_SECRET_LENGTH} bytes long"),
+            ));
+        }
+        Ok(())
+    }
+}
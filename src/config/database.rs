@@ -1,12 +1,24 @@
+use std::path::PathBuf;
+
 use serde::{Deserialize, Serialize};
 
+use super::ConfigError;
+
 #[derive(Debug, Serialize, Deserialize)]
+#[serde(default)]
 pub struct DatabaseConfig {
     pub database_type: DatabaseType,
     pub host: String,
     pub port: u16,
     pub username: String,
     pub password: Option<String>,
+    pub database_name: String,
+    pub ssl_mode: SslMode,
+    /// Database file used by the SQLite backend, created if it does not exist.
+    pub sqlite_path: PathBuf,
+    /// Apply pending migrations when the server starts.
+    pub auto_migrate: bool,
+    pub connection_pool_config: ConnectionPoolConfig,
 }
 
 impl Default for DatabaseConfig {
@@ -17,18 +29,90 @@ impl Default for DatabaseConfig {
             port: 5432,
             username: "development-user".into(),
             password: Some("development-password".into()),
+            database_name: "heimdall".into(),
+            ssl_mode: SslMode::default(),
+            sqlite_path: "heimdall.db".into(),
+            auto_migrate: false,
+            connection_pool_config: ConnectionPoolConfig::default(),
         }
     }
 }
 
-#[derive(Debug, Serialize, Deserialize, Default)]
+impl DatabaseConfig {
+    pub(super) fn validate(&self) -> Result<(), ConfigError> {
+        if let DatabaseType::Postgres = self.database_type {
+            if self.host.trim().is_empty() {
+                return Err(ConfigError::invalid(
+                    "database_config.host",
+                    "must not be empty",
+                ));
+            }
+            if self.port == 0 {
+                return Err(ConfigError::invalid(
+                    "database_config.port",
+                    "must be between 1 and 65535",
+                ));
+            }
+            if self.username.trim().is_empty() {
+                return Err(ConfigError::invalid(
+                    "database_config.username",
+                    "must not be empty",
+                ));
+            }
+            if self.database_name.trim().is_empty() {
+                return Err(ConfigError::invalid(
+                    "database_config.database_name",
+                    "must not be empty",
+                ));
+            }
+        }
+        if self.database_type == DatabaseType::Sqllite && self.sqlite_path.as_os_str().is_empty() {
+            return Err(ConfigError::invalid(
+                "database_config.sqlite_path",
+                "must not be empty",
+            ));
+        }
+        self.connection_pool_config.validate()
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Default)]
 pub enum DatabaseType {
+    #[serde(alias = "postgres", alias = "postgresql")]
     Postgres,
     #[default]
+    #[serde(alias = "sqlite")]
     Sqllite,
 }
 
+/// TLS negotiation for the Postgres backend, mirrors libpq's `sslmode`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Default)]
+#[serde(rename_all = "kebab-case")]
+pub enum SslMode {
+    Disable,
+    Allow,
+    #[default]
+    Prefer,
+    Require,
+    VerifyCa,
+    VerifyFull,
+}
+
+impl SslMode {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Disable => "disable",
+            Self::Allow => "allow",
+            Self::Prefer => "prefer",
+            Self::Require => "require",
+            Self::VerifyCa => "verify-ca",
+            Self::VerifyFull => "verify-full",
+        }
+    }
+}
+
 #[derive(Debug, Serialize, Deserialize)]
+#[serde(default)]
 pub struct ConnectionPoolConfig {
     pub min_connections: u32,
     pub max_connections: u32,
@@ -36,7 +120,10 @@ pub struct ConnectionPoolConfig {
     pub connection_timeout_ms: i64,
     pub idle_timeout_ms: i64,
     pub test_before_aquire: bool,
+    /// Same liveness check as `test_before_aquire`, either flag enables it.
     pub test_on_borrow: bool,
+    /// Connections older than this are closed when they are checked out instead of being handed
+    /// out, regardless of `max_lifetime_seconds` which is only enforced by the background reaper.
     pub max_connection_age_seconds: Option<i64>,
 }
 
@@ -46,7 +133,7 @@ impl Default for ConnectionPoolConfig {
             min_connections: 30,
             max_connections: 100,
             max_lifetime_seconds: 150,
-            connection_timeout_ms: 50,
+            connection_timeout_ms: 5_000,
             idle_timeout_ms: 300,
             test_before_aquire: false,
             test_on_borrow: false,
@@ -54,3 +141,44 @@ impl Default for ConnectionPoolConfig {
         }
     }
 }
+
+impl ConnectionPoolConfig {
+    const KEY: &str = "database_config.connection_pool_config";
+
+    fn validate(&self) -> Result<(), ConfigError> {
+        if self.max_connections == 0 {
+            return Err(ConfigError::invalid(
+                format!("{}.max_connections", Self::KEY),
+                "must be greater than 0",
+            ));
+        }
+        if self.min_connections > self.max_connections {
+            return Err(ConfigError::invalid(
+                format!("{}.min_connections", Self::KEY),
+                format!(
+                    "must not exceed max_connections ({} > {})",
+                    self.min_connections, self.max_connections
+                ),
+            ));
+        }
+
+        let durations = [
+            ("max_lifetime_seconds", Some(self.max_lifetime_seconds)),
+            ("connection_timeout_ms", Some(self.connection_timeout_ms)),
+            ("idle_timeout_ms", Some(self.idle_timeout_ms)),
+            (
+                "max_connection_age_seconds",
+                self.max_connection_age_seconds,
+            ),
+        ];
+        for (field, value) in durations {
+            if value.is_some_and(|value| value < 0) {
+                return Err(ConfigError::invalid(
+                    format!("{}.{field}", Self::KEY),
+                    "must not be negative",
+                ));
+            }
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,43 @@
+use std::fmt;
+
+#[derive(Debug)]
+pub enum ConfigError {
+    /// The configuration sources could not be read or deserialized.
+    Source(config::ConfigError),
+    /// A value was read successfully but is not acceptable, `key` is the dotted path of the
+    /// offending value (e.g. `server_config.port`).
+    Invalid { key: String, reason: String },
+}
+
+impl ConfigError {
+    pub(crate) fn invalid(key: impl Into<String>, reason: impl Into<String>) -> Self {
+        Self::Invalid {
+            key: key.into(),
+            reason: reason.into(),
+        }
+    }
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Source(e) => write!(f, "failed to load configuration: {e}"),
+            Self::Invalid { key, reason } => write!(f, "invalid configuration `{key}`: {reason}"),
+        }
+    }
+}
+
+impl std::error::Error for ConfigError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::Source(e) => Some(e),
+            Self::Invalid { .. } => None,
+        }
+    }
+}
+
+impl From<config::ConfigError> for ConfigError {
+    fn from(value: config::ConfigError) -> Self {
+        Self::Source(value)
+    }
+}
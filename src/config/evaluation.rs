@@ -0,0 +1,32 @@
+use serde::{Deserialize, Serialize};
+
+use super::ConfigError;
+
+/// Upper bound for `max_depth`, far beyond any sensible schema but low enough that the boxed
+/// recursion of the evaluator cannot exhaust memory.
+const MAX_DEPTH_LIMIT: u32 = 1_000;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct EvaluationConfig {
+    /// Most nested rewrites and userset hops a single evaluation may follow before it fails.
+    pub max_depth: u32,
+}
+
+impl Default for EvaluationConfig {
+    fn default() -> Self {
+        Self { max_depth: 50 }
+    }
+}
+
+impl EvaluationConfig {
+    pub(super) fn validate(&self) -> Result<(), ConfigError> {
+        if self.max_depth == 0 || self.max_depth > MAX_DEPTH_LIMIT {
+            return Err(ConfigError::invalid(
+                "evaluation_config.max_depth",
+                format!("must be between 1 and {MAX_DEPTH_LIMIT}"),
+            ));
+        }
+        Ok(())
+    }
+}
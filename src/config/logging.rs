@@ -0,0 +1,54 @@
+use serde::{Deserialize, Serialize};
+use tracing_subscriber::EnvFilter;
+
+use super::ConfigError;
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct LoggingConfig {
+    /// Filter in `RUST_LOG` syntax, e.g. `info` or `heimdall=debug,sqlx=warn`.
+    pub level: String,
+    pub format: LogFormat,
+    pub span_events: SpanEvents,
+}
+
+impl Default for LoggingConfig {
+    fn default() -> Self {
+        Self {
+            level: "info".into(),
+            format: LogFormat::default(),
+            span_events: SpanEvents::default(),
+        }
+    }
+}
+
+impl LoggingConfig {
+    pub(super) fn validate(&self) -> Result<(), ConfigError> {
+        EnvFilter::try_new(&self.level)
+            .map(|_| ())
+            .map_err(|e| ConfigError::invalid("logging_config.level", e.to_string()))
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum LogFormat {
+    /// Multi-line, human readable output for local development.
+    #[default]
+    Pretty,
+    /// One JSON object per line, including the fields of every enclosing span.
+    Json,
+}
+
+/// Span lifecycle events that are logged in addition to regular events.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum SpanEvents {
+    #[default]
+    None,
+    New,
+    /// Logs when a span closes, including how long it was busy and idle.
+    Close,
+    Active,
+    Full,
+}
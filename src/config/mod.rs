@@ -1,21 +1,226 @@
-use database::DatabaseConfig;
+use config::{Config, Environment, File};
 use serde::{Deserialize, Serialize};
-use server::ServerConfig;
 
+pub use args::ConfigArgs;
+pub use audit::AuditConfig;
+pub use cache::CacheConfig;
+pub use consistency::ConsistencyConfig;
+pub use database::{ConnectionPoolConfig, DatabaseConfig, DatabaseType, SslMode};
+pub use error::ConfigError;
+pub use evaluation::EvaluationConfig;
+pub use logging::{LogFormat, LoggingConfig, SpanEvents};
+pub use server::ServerConfig;
+
+mod args;
+mod audit;
+mod cache;
+mod consistency;
 mod database;
+mod error;
+mod evaluation;
+mod logging;
 mod server;
 
-#[derive(Debug, Serialize, Deserialize)]
+/// Prefix for environment overrides, e.g. `HEIMDALL__DATABASE_CONFIG__HOST`.
+const ENV_PREFIX: &str = "HEIMDALL";
+const ENV_SEPARATOR: &str = "__";
+/// Base name of the configuration file looked up in the working directory when no explicit
+/// `--config` path is given. Any extension supported by the `config` crate is accepted.
+const DEFAULT_CONFIG_FILE: &str = "heimdall";
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
 pub struct AppConfig {
     pub database_config: DatabaseConfig,
     pub server_config: ServerConfig,
+    pub logging_config: LoggingConfig,
+    pub evaluation_config: EvaluationConfig,
+    pub consistency_config: ConsistencyConfig,
+    pub cache_config: CacheConfig,
+    pub audit_config: AuditConfig,
+}
+
+impl AppConfig {
+    /// Builds the configuration from, in increasing order of precedence: built-in defaults, the
+    /// configuration file (TOML/YAML/JSON), `HEIMDALL__*` environment variables and finally the
+    /// command-line flags. The result is validated before it is returned, except for the
+    /// `zookie_secret` (see [`ConsistencyConfig::validate_zookie_secret`]).
+    pub fn load(args: &ConfigArgs) -> Result<Self, ConfigError> {
+        Self::load_from(args, environment())
+    }
+
+    fn load_from(args: &ConfigArgs, environment: Environment) -> Result<Self, ConfigError> {
+        let file = match &args.config {
+            Some(path) => File::from(path.as_path()).required(true),
+            None => File::with_name(DEFAULT_CONFIG_FILE).required(false),
+        };
+
+        let builder = Config::builder().add_source(file).add_source(environment);
+        let app_config: AppConfig = args.apply_overrides(builder)?.build()?.try_deserialize()?;
+
+        app_config.validate()?;
+        Ok(app_config)
+    }
+
+    pub fn validate(&self) -> Result<(), ConfigError> {
+        self.server_config.validate()?;
+        self.database_config.validate()?;
+        self.logging_config.validate()?;
+        self.evaluation_config.validate()?;
+        self.consistency_config.validate()?;
+        self.cache_config.validate()?;
+        self.audit_config.validate()?;
+        Ok(())
+    }
 }
 
-impl Default for AppConfig {
-    fn default() -> Self {
-        Self {
-            database_config: DatabaseConfig::default(),
-            server_config: ServerConfig::default(),
+/// Source of the `HEIMDALL__*` overrides. Values are kept as strings and only converted to the
+/// type of the setting they are deserialized into, so a password like `0123` is not read as a
+/// number first and stripped of its leading zero.
+fn environment() -> Environment {
+    Environment::with_prefix(ENV_PREFIX)
+        .prefix_separator(ENV_SEPARATOR)
+        .separator(ENV_SEPARATOR)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{fmt, path::PathBuf};
+
+    use config::Map;
+
+    use super::*;
+
+    const SECRET: &str = "an-example-secret-of-32-bytes-or-more";
+
+    /// Loads the configuration from a TOML file holding `file` and the environment `variables`.
+    /// The file is named after `test` so that tests running in parallel do not share it.
+    fn load(
+        test: &str,
+        file: &str,
+        variables: &[(&str, &str)],
+        args: ConfigArgs,
+    ) -> Result<AppConfig, ConfigError> {
+        let path: PathBuf =
+            std::env::temp_dir().join(format!("heimdall-{}-{test}.toml", std::process::id()));
+        std::fs::write(&path, file).unwrap();
+        let variables: Map<String, String> =
+            [("HEIMDALL__CONSISTENCY_CONFIG__ZOOKIE_SECRET", SECRET)]
+                .iter()
+                .chain(variables)
+                .map(|(name, value)| (name.to_string(), value.to_string()))
+                .collect();
+        let args = ConfigArgs {
+            config: Some(path.clone()),
+            ..args
+        };
+        let loaded = AppConfig::load_from(&args, environment().source(Some(variables)));
+        std::fs::remove_file(path).unwrap();
+        loaded
+    }
+
+    fn invalid_key<T: fmt::Debug>(result: Result<T, ConfigError>) -> String {
+        match result {
+            Err(ConfigError::Invalid { key, .. }) => key,
+            other => panic!("expected an invalid value, got {other:?}"),
         }
     }
+
+    #[test]
+    fn environment_overrides_the_file_and_flags_override_both() {
+        let file = r#"
+            [server_config]
+            port = 4000
+
+            [database_config]
+            host = "file-host"
+            username = "file-user"
+        "#;
+        let variables = [
+            ("HEIMDALL__SERVER_CONFIG__PORT", "5000"),
+            ("HEIMDALL__DATABASE_CONFIG__HOST", "env-host"),
+        ];
+        let args = ConfigArgs {
+            port: Some(6000),
+            ..ConfigArgs::default()
+        };
+        let app_config = load("precedence", file, &variables, args).unwrap();
+
+        assert_eq!(app_config.server_config.port, 6000);
+        assert_eq!(app_config.database_config.host, "env-host");
+        assert_eq!(app_config.database_config.username, "file-user");
+        assert_eq!(app_config.database_config.port, 5432);
+    }
+
+    #[test]
+    fn environment_values_keep_the_type_of_their_setting() {
+        let variables = [
+            ("HEIMDALL__DATABASE_CONFIG__PASSWORD", "0123"),
+            ("HEIMDALL__DATABASE_CONFIG__USERNAME", "1e5"),
+            ("HEIMDALL__DATABASE_CONFIG__PORT", "5433"),
+            ("HEIMDALL__DATABASE_CONFIG__AUTO_MIGRATE", "true"),
+            ("HEIMDALL__AUDIT_CONFIG__SAMPLE_RATE", "0.5"),
+        ];
+        let app_config = load("types", "", &variables, ConfigArgs::default()).unwrap();
+
+        let database_config = &app_config.database_config;
+        assert_eq!(database_config.password.as_deref(), Some("0123"));
+        assert_eq!(database_config.username, "1e5");
+        assert_eq!(database_config.port, 5433);
+        assert!(database_config.auto_migrate);
+        assert_eq!(app_config.audit_config.sample_rate, 0.5);
+    }
+
+    #[test]
+    fn invalid_values_are_reported_with_their_key() {
+        let cases = [
+            ("[server_config]\nport = 0", "server_config.port"),
+            (
+                "[database_config]\ndatabase_type = \"postgres\"\nhost = \" \"",
+                "database_config.host",
+            ),
+            (
+                "[database_config.connection_pool_config]\nmin_connections = 5\nmax_connections = 2",
+                "database_config.connection_pool_config.min_connections",
+            ),
+            (
+                "[logging_config]\nlevel = \"heimdall=loud\"",
+                "logging_config.level",
+            ),
+            (
+                "[consistency_config]\nmax_wait_ms = 600000",
+                "consistency_config.max_wait_ms",
+            ),
+        ];
+        for (file, key) in cases {
+            let loaded = load("invalid", file, &[], ConfigArgs::default());
+            assert_eq!(invalid_key(loaded), key, "{file}");
+        }
+    }
+
+    #[test]
+    fn the_zookie_secret_is_only_checked_on_request() {
+        let variables = [("HEIMDALL__CONSISTENCY_CONFIG__ZOOKIE_SECRET", "too short")];
+        let app_config = load("short-secret", "", &variables, ConfigArgs::default()).unwrap();
+        let checked = app_config.consistency_config.validate_zookie_secret();
+        assert_eq!(invalid_key(checked), "consistency_config.zookie_secret");
+
+        let unset = ConsistencyConfig::default().validate_zookie_secret();
+        assert_eq!(invalid_key(unset), "consistency_config.zookie_secret");
+
+        let app_config = load("secret", "", &[], ConfigArgs::default()).unwrap();
+        assert!(
+            app_config
+                .consistency_config
+                .validate_zookie_secret()
+                .is_ok()
+        );
+    }
+
+    #[test]
+    fn unreadable_values_are_source_errors() {
+        let variables = [("HEIMDALL__SERVER_CONFIG__PORT", "eighty")];
+        let loaded = load("unreadable", "", &variables, ConfigArgs::default());
+        assert!(matches!(loaded, Err(ConfigError::Source(_))), "{loaded:?}");
+    }
 }
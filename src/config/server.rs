@@ -2,7 +2,10 @@ use std::net::{IpAddr, Ipv4Addr};
 
 use serde::{Deserialize, Serialize};
 
+use super::ConfigError;
+
 #[derive(Debug, Serialize, Deserialize)]
+#[serde(default)]
 pub struct ServerConfig {
     pub ip: IpAddr,
     pub port: u16,
@@ -16,3 +19,15 @@ impl Default for ServerConfig {
         }
     }
 }
+
+impl ServerConfig {
+    pub(super) fn validate(&self) -> Result<(), ConfigError> {
+        if self.port == 0 {
+            return Err(ConfigError::invalid(
+                "server_config.port",
+                "must be between 1 and 65535",
+            ));
+        }
+        Ok(())
+    }
+}
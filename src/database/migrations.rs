@@ -0,0 +1,114 @@
+use std::collections::HashMap;
+
+use sqlx::migrate::{Migrate, MigrateError, Migrator};
+
+use super::DatabasePool;
+
+// Both migration directories are embedded at compile time, the backend decides which one is used.
+static POSTGRES_MIGRATOR: Migrator = sqlx::migrate!("./migrations/postgres");
+static SQLITE_MIGRATOR: Migrator = sqlx::migrate!("./migrations/sqlite");
+
+#[derive(Debug, Clone)]
+pub struct MigrationStatus {
+    pub version: i64,
+    pub description: String,
+    pub state: MigrationState,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MigrationState {
+    Applied,
+    Pending,
+    /// The migration was applied, but the embedded script has changed since.
+    ChecksumMismatch,
+    /// The migration was applied, but is not embedded in this binary.
+    Unknown,
+}
+
+impl DatabasePool {
+    fn migrator(&self) -> &'static Migrator {
+        match self {
+            Self::Postgres(_) => &POSTGRES_MIGRATOR,
+            Self::Sqlite(_) => &SQLITE_MIGRATOR,
+        }
+    }
+
+    /// Applies every pending migration.
+    pub async fn migrate_up(&self) -> Result<(), MigrateError> {
+        match self {
+            Self::Postgres(pool) => self.migrator().run(pool).await,
+            Self::Sqlite(pool) => self.migrator().run(pool).await,
+        }
+    }
+
+    /// Reverts every applied migration newer than `target`, or only the latest one when no target
+    /// is given. Returns the version the database is at afterwards, `0` meaning an empty schema.
+    pub async fn migrate_down(&self, target: Option<i64>) -> Result<i64, MigrateError> {
+        let target = match target {
+            Some(target) => target,
+            None => {
+                let mut applied = self.applied_versions().await?;
+                applied.sort_unstable();
+                applied.pop();
+                applied.pop().unwrap_or(0)
+            }
+        };
+        match self {
+            Self::Postgres(pool) => self.migrator().undo(pool, target).await?,
+            Self::Sqlite(pool) => self.migrator().undo(pool, target).await?,
+        }
+        Ok(target)
+    }
+
+    pub async fn migration_status(&self) -> Result<Vec<MigrationStatus>, MigrateError> {
+        let mut applied = self.applied_checksums().await?;
+        let mut statuses = Vec::new();
+
+        for migration in self.migrator().iter() {
+            if migration.migration_type.is_down_migration() {
+                continue;
+            }
+            let state = match applied.remove(&migration.version) {
+                Some(checksum) if checksum == *migration.checksum => MigrationState::Applied,
+                Some(_) => MigrationState::ChecksumMismatch,
+                None => MigrationState::Pending,
+            };
+            statuses.push(MigrationStatus {
+                version: migration.version,
+                description: migration.description.to_string(),
+                state,
+            });
+        }
+        statuses.extend(applied.into_keys().map(|version| MigrationStatus {
+            version,
+            description: String::new(),
+            state: MigrationState::Unknown,
+        }));
+        statuses.sort_by_key(|status| status.version);
+
+        Ok(statuses)
+    }
+
+    async fn applied_versions(&self) -> Result<Vec<i64>, MigrateError> {
+        Ok(self.applied_checksums().await?.into_keys().collect())
+    }
+
+    async fn applied_checksums(&self) -> Result<HashMap<i64, Vec<u8>>, MigrateError> {
+        let applied = match self {
+            Self::Postgres(pool) => {
+                let mut conn = pool.acquire().await?;
+                conn.ensure_migrations_table().await?;
+                conn.list_applied_migrations().await?
+            }
+            Self::Sqlite(pool) => {
+                let mut conn = pool.acquire().await?;
+                conn.ensure_migrations_table().await?;
+                conn.list_applied_migrations().await?
+            }
+        };
+        Ok(applied
+            .into_iter()
+            .map(|migration| (migration.version, migration.checksum.into_owned()))
+            .collect())
+    }
+}
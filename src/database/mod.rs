@@ -0,0 +1,168 @@
+use std::time::Duration;
+
+use sqlx::{
+    Database, PgPool, SqlitePool,
+    pool::PoolOptions,
+    postgres::{PgConnectOptions, PgSslMode},
+    sqlite::{SqliteConnectOptions, SqliteJournalMode},
+};
+
+use crate::config::{ConnectionPoolConfig, DatabaseConfig, DatabaseType, SslMode};
+
+pub use migrations::MigrationState;
+
+mod migrations;
+
+/// Runs `$body` with `$pool` bound to the concrete sqlx pool of the backend in use. The body is
+/// compiled once per backend, so every query in it has to be valid SQL for both Postgres and
+/// SQLite; `$1`-style placeholders are understood by both.
+macro_rules! with_pool {
+    ($database_pool:expr, |$pool:ident| $body:expr) => {
+        match $database_pool {
+            $crate::database::DatabasePool::Postgres($pool) => $body,
+            $crate::database::DatabasePool::Sqlite($pool) => $body,
+        }
+    };
+}
+
+pub(crate) use with_pool;
+
+/// Connection pool for whichever backend `DatabaseConfig::database_type` selects.
+#[derive(Debug, Clone)]
+pub enum DatabasePool {
+    Postgres(PgPool),
+    Sqlite(SqlitePool),
+}
+
+impl DatabasePool {
+    /// Opens the pool and establishes the first connection, so an unreachable database is
+    /// reported here rather than on the first request.
+    pub async fn connect(database_config: &DatabaseConfig) -> Result<Self, sqlx::Error> {
+        let pool_config = &database_config.connection_pool_config;
+        match database_config.database_type {
+            DatabaseType::Postgres => {
+                let pool = pool_options(pool_config)
+                    .connect_with(pg_connect_options(database_config))
+                    .await?;
+                Ok(Self::Postgres(pool))
+            }
+            DatabaseType::Sqllite => {
+                let pool = pool_options(pool_config)
+                    .connect_with(sqlite_connect_options(database_config))
+                    .await?;
+                Ok(Self::Sqlite(pool))
+            }
+        }
+    }
+
+    /// Statement that, run first inside a transaction, makes it wait for every other
+    /// transaction that ran it until they finish. Used to hand out gap-free versions, and by
+    /// writes whose checks have to hold until they commit.
+    ///
+    /// Postgres takes a transaction scoped advisory lock keyed by `"heimdall"` read as a
+    /// big-endian `i64`. SQLite only allows one writer, so a
+    /// write that changes nothing is enough to take the database write lock up front instead
+    /// of failing with `SQLITE_BUSY` when a read lock has to be upgraded later.
+    pub fn serialize_writes_statement(&self) -> &'static str {
+        match self {
+            Self::Postgres(_) => "SELECT pg_advisory_xact_lock(7522534671148739692)",
+            Self::Sqlite(_) => "UPDATE zookies SET version = version WHERE 0 = 1",
+        }
+    }
+
+    pub async fn close(&self) {
+        match self {
+            Self::Postgres(pool) => pool.close().await,
+            Self::Sqlite(pool) => pool.close().await,
+        }
+    }
+}
+
+/// Empty in-memory SQLite database with every migration applied, for tests.
+#[cfg(test)]
+pub(crate) async fn memory_pool() -> DatabasePool {
+    use std::str::FromStr;
+
+    let options = SqliteConnectOptions::from_str("sqlite::memory:")
+        .unwrap()
+        .foreign_keys(true);
+    // Every connection opens a database of its own, which is gone once the connection closes.
+    let pool = PoolOptions::new()
+        .max_connections(1)
+        .idle_timeout(None)
+        .max_lifetime(None)
+        .connect_with(options)
+        .await
+        .unwrap();
+    let pool = DatabasePool::Sqlite(pool);
+    pool.migrate_up().await.unwrap();
+    pool
+}
+
+/// Connection string for `database_config` with the password left out, for use in log and error
+/// messages.
+pub fn redacted_dsn(database_config: &DatabaseConfig) -> String {
+    match database_config.database_type {
+        DatabaseType::Postgres => format!(
+            "postgres://{}@{}:{}/{}?sslmode={}",
+            database_config.username,
+            database_config.host,
+            database_config.port,
+            database_config.database_name,
+            database_config.ssl_mode.as_str(),
+        ),
+        DatabaseType::Sqllite => format!("sqlite://{}", database_config.sqlite_path.display()),
+    }
+}
+
+fn pg_connect_options(database_config: &DatabaseConfig) -> PgConnectOptions {
+    let options = PgConnectOptions::new()
+        .host(&database_config.host)
+        .port(database_config.port)
+        .username(&database_config.username)
+        .database(&database_config.database_name)
+        .ssl_mode(pg_ssl_mode(database_config.ssl_mode));
+    match &database_config.password {
+        Some(password) => options.password(password),
+        None => options,
+    }
+}
+
+fn sqlite_connect_options(database_config: &DatabaseConfig) -> SqliteConnectOptions {
+    SqliteConnectOptions::new()
+        .filename(&database_config.sqlite_path)
+        .create_if_missing(true)
+        .journal_mode(SqliteJournalMode::Wal)
+        .foreign_keys(true)
+}
+
+fn pg_ssl_mode(ssl_mode: SslMode) -> PgSslMode {
+    match ssl_mode {
+        SslMode::Disable => PgSslMode::Disable,
+        SslMode::Allow => PgSslMode::Allow,
+        SslMode::Prefer => PgSslMode::Prefer,
+        SslMode::Require => PgSslMode::Require,
+        SslMode::VerifyCa => PgSslMode::VerifyCa,
+        SslMode::VerifyFull => PgSslMode::VerifyFull,
+    }
+}
+
+fn pool_options<DB: Database>(pool_config: &ConnectionPoolConfig) -> PoolOptions<DB> {
+    // Negative values are rejected by `ConnectionPoolConfig::validate`.
+    let seconds = |value: i64| Duration::from_secs(value.max(0) as u64);
+    let millis = |value: i64| Duration::from_millis(value.max(0) as u64);
+
+    let options = PoolOptions::<DB>::new()
+        .min_connections(pool_config.min_connections)
+        .max_connections(pool_config.max_connections)
+        .max_lifetime(seconds(pool_config.max_lifetime_seconds))
+        .acquire_timeout(millis(pool_config.connection_timeout_ms))
+        .idle_timeout(millis(pool_config.idle_timeout_ms))
+        .test_before_acquire(pool_config.test_before_aquire || pool_config.test_on_borrow);
+
+    match pool_config.max_connection_age_seconds.map(seconds) {
+        Some(max_age) => options
+            .before_acquire(move |_conn, meta| Box::pin(async move { Ok(meta.age <= max_age) })),
+        None => options,
+    }
+}
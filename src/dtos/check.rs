@@ -0,0 +1,106 @@
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    error::{ErrorBody, HeimdallResult},
+    models::{Consistency, ObjectRelation, SubjectRef},
+};
+
+/// Asks whether `subject_type:subject_id[#subject_relation]` holds
+/// `namespace:object_id#relation`.
+#[derive(Debug, Deserialize)]
+pub struct CheckRequest {
+    pub namespace: String,
+    pub object_id: String,
+    pub relation: String,
+    pub subject_type: String,
+    pub subject_id: String,
+    pub subject_relation: Option<String>,
+    #[serde(default)]
+    pub consistency: Consistency,
+}
+
+impl CheckRequest {
+    pub fn object(&self) -> ObjectRelation {
+        ObjectRelation {
+            namespace: self.namespace.clone(),
+            object_id: self.object_id.clone(),
+            relation: self.relation.clone(),
+        }
+    }
+
+    pub fn subject(&self) -> SubjectRef {
+        SubjectRef {
+            subject_type: self.subject_type.clone(),
+            subject_id: self.subject_id.clone(),
+            subject_relation: self.subject_relation.clone(),
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct CheckResponse {
+    pub allowed: bool,
+    /// Zookie of the version the check was evaluated at.
+    pub zookie: String,
+}
+
+/// Asks for every check of `items` at once, all evaluated at the same snapshot.
+#[derive(Debug, Deserialize)]
+pub struct BulkCheckRequest {
+    pub items: Vec<BulkCheckItem>,
+    #[serde(default)]
+    pub consistency: Consistency,
+}
+
+/// One check of a [`BulkCheckRequest`], the fields of a [`CheckRequest`] but `consistency`.
+#[derive(Debug, Deserialize)]
+pub struct BulkCheckItem {
+    pub namespace: String,
+    pub object_id: String,
+    pub relation: String,
+    pub subject_type: String,
+    pub subject_id: String,
+    pub subject_relation: Option<String>,
+}
+
+impl From<BulkCheckItem> for (ObjectRelation, SubjectRef) {
+    fn from(value: BulkCheckItem) -> Self {
+        let object = ObjectRelation {
+            namespace: value.namespace,
+            object_id: value.object_id,
+            relation: value.relation,
+        };
+        let subject = SubjectRef {
+            subject_type: value.subject_type,
+            subject_id: value.subject_id,
+            subject_relation: value.subject_relation,
+        };
+        (object, subject)
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct BulkCheckResponse {
+    /// Result of every item, in the order of the request.
+    pub results: Vec<BulkCheckResult>,
+    /// Zookie of the version the checks were evaluated at.
+    pub zookie: String,
+}
+
+/// `{"allowed": ...}`, or the error of an item that could not be checked in the body `/check`
+/// would have failed with.
+#[derive(Debug, Serialize)]
+#[serde(untagged)]
+pub enum BulkCheckResult {
+    Checked { allowed: bool },
+    Failed(ErrorBody),
+}
+
+impl From<HeimdallResult<bool>> for BulkCheckResult {
+    fn from(value: HeimdallResult<bool>) -> Self {
+        match value {
+            Ok(allowed) => Self::Checked { allowed },
+            Err(error) => Self::Failed(error.body()),
+        }
+    }
+}
@@ -0,0 +1,29 @@
+use serde::{Deserialize, Serialize};
+
+use crate::models::{Consistency, ObjectRelation, UsersetTree};
+
+#[derive(Debug, Deserialize)]
+pub struct ExpandRequest {
+    pub namespace: String,
+    pub object_id: String,
+    pub relation: String,
+    #[serde(default)]
+    pub consistency: Consistency,
+}
+
+impl ExpandRequest {
+    pub fn object(&self) -> ObjectRelation {
+        ObjectRelation {
+            namespace: self.namespace.clone(),
+            object_id: self.object_id.clone(),
+            relation: self.relation.clone(),
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct ExpandResponse {
+    pub tree: UsersetTree,
+    /// Zookie of the version the tree was expanded at.
+    pub zookie: String,
+}
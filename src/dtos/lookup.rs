@@ -0,0 +1,92 @@
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    models::{Consistency, ObjectRelation, Snapshot, SubjectRef, SubjectSet},
+    services::lookup::ResourcePage,
+};
+
+/// Asks for the objects in `namespace` on which the subject holds `relation`.
+#[derive(Debug, Deserialize)]
+pub struct LookupResourcesRequest {
+    pub namespace: String,
+    pub relation: String,
+    pub subject_type: String,
+    pub subject_id: String,
+    pub subject_relation: Option<String>,
+    /// `next_cursor` of the previous page.
+    pub cursor: Option<String>,
+    pub limit: Option<usize>,
+    #[serde(default)]
+    pub consistency: Consistency,
+}
+
+impl LookupResourcesRequest {
+    pub fn subject(&self) -> SubjectRef {
+        SubjectRef {
+            subject_type: self.subject_type.clone(),
+            subject_id: self.subject_id.clone(),
+            subject_relation: self.subject_relation.clone(),
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct LookupResourcesResponse {
+    pub object_ids: Vec<String>,
+    pub next_cursor: Option<String>,
+    /// Zookie of the version the page was looked up at.
+    pub zookie: String,
+}
+
+impl From<(ResourcePage, Snapshot)> for LookupResourcesResponse {
+    fn from((page, snapshot): (ResourcePage, Snapshot)) -> Self {
+        Self {
+            object_ids: page.object_ids,
+            next_cursor: page.next_cursor,
+            zookie: snapshot.zookie,
+        }
+    }
+}
+
+/// Asks for the subjects of `subject_type` holding `relation` on an object.
+#[derive(Debug, Deserialize)]
+pub struct LookupSubjectsRequest {
+    pub namespace: String,
+    pub object_id: String,
+    pub relation: String,
+    pub subject_type: String,
+    #[serde(default)]
+    pub consistency: Consistency,
+}
+
+impl LookupSubjectsRequest {
+    pub fn object(&self) -> ObjectRelation {
+        ObjectRelation {
+            namespace: self.namespace.clone(),
+            object_id: self.object_id.clone(),
+            relation: self.relation.clone(),
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct LookupSubjectsResponse {
+    pub subject_ids: Vec<String>,
+    /// Whether every subject of the type holds the relation, save `excluded_subject_ids`.
+    pub wildcard: bool,
+    /// Subjects denied by an exclusion or left out of the wildcard.
+    pub excluded_subject_ids: Vec<String>,
+    /// Zookie of the version the subjects were looked up at.
+    pub zookie: String,
+}
+
+impl From<(SubjectSet, Snapshot)> for LookupSubjectsResponse {
+    fn from((subjects, snapshot): (SubjectSet, Snapshot)) -> Self {
+        Self {
+            excluded_subject_ids: subjects.excluded_subject_ids().into_iter().collect(),
+            wildcard: subjects.wildcard_exclusions.is_some(),
+            subject_ids: subjects.subject_ids.into_iter().collect(),
+            zookie: snapshot.zookie,
+        }
+    }
+}
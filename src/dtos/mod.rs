@@ -1 +1,8 @@
-
+pub mod check;
+pub mod expand;
+pub mod lookup;
+pub mod namespace;
+pub mod relation;
+pub mod relationship;
+pub mod schema;
+pub mod watch;
@@ -0,0 +1,59 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::entities::Relation;
+
+#[derive(Debug, Deserialize)]
+pub struct CreateRelationRequest {
+    pub name: String,
+    pub description: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct UpdateRelationRequest {
+    pub description: Option<String>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+pub struct ListRelationsQuery {
+    /// Also return soft-deleted relations.
+    #[serde(default)]
+    pub include_deleted: bool,
+}
+
+#[derive(Debug, Serialize)]
+pub struct RelationResponse {
+    pub id: Uuid,
+    pub namespace_id: String,
+    pub name: String,
+    pub description: Option<String>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub deleted_at: Option<DateTime<Utc>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub subject_types: Option<Vec<String>>,
+}
+
+/// Returned by a relation delete.
+#[derive(Debug, Serialize)]
+pub struct DeleteRelationResponse {
+    /// Names the version the relation was deleted at, for reads that must not see it any more.
+    pub zookie: String,
+}
+
+impl From<Relation> for RelationResponse {
+    fn from(value: Relation) -> Self {
+        Self {
+            id: value.id,
+            namespace_id: value.namespace_id,
+            name: value.name,
+            description: value.description,
+            created_at: value.created_at,
+            updated_at: value.updated_at,
+            deleted_at: value.deleted_at,
+            subject_types: value.subject_types.map(|types| types.0),
+        }
+    }
+}
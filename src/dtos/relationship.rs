@@ -0,0 +1,84 @@
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    entities::Zookie,
+    models::{Consistency, Precondition, Snapshot, TupleFilter, TupleKey, TupleUpdate},
+    services::relationship::RelationshipPage,
+};
+
+#[derive(Debug, Deserialize)]
+pub struct WriteRelationshipsRequest {
+    pub updates: Vec<TupleUpdate>,
+    #[serde(default)]
+    pub preconditions: Vec<Precondition>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct WriteRelationshipsResponse {
+    /// Consistency token of the commit.
+    pub zookie: String,
+}
+
+impl From<Zookie> for WriteRelationshipsResponse {
+    fn from(value: Zookie) -> Self {
+        Self {
+            zookie: value.token,
+        }
+    }
+}
+
+/// Query string of `GET /relationships`, every filter field is optional.
+#[derive(Debug, Deserialize)]
+pub struct ReadRelationshipsQuery {
+    pub namespace: Option<String>,
+    pub object_id: Option<String>,
+    pub relation: Option<String>,
+    pub subject_type: Option<String>,
+    pub subject_id: Option<String>,
+    pub subject_relation: Option<String>,
+    /// `next_cursor` of the previous page.
+    pub cursor: Option<String>,
+    pub limit: Option<usize>,
+    /// Read at least as fresh as this zookie, only used for the first page.
+    pub zookie: Option<String>,
+}
+
+impl ReadRelationshipsQuery {
+    pub fn filter(&self) -> TupleFilter {
+        TupleFilter {
+            namespace: self.namespace.clone(),
+            object_id: self.object_id.clone(),
+            relation: self.relation.clone(),
+            subject_type: self.subject_type.clone(),
+            subject_id: self.subject_id.clone(),
+            subject_relation: self.subject_relation.clone(),
+        }
+    }
+
+    pub fn consistency(&self) -> Consistency {
+        match &self.zookie {
+            Some(zookie) => Consistency::AtLeastAsFresh {
+                zookie: zookie.clone(),
+            },
+            None => Consistency::default(),
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct ReadRelationshipsResponse {
+    pub relationships: Vec<TupleKey>,
+    pub next_cursor: Option<String>,
+    /// Zookie of the snapshot every page of this read is taken from.
+    pub zookie: String,
+}
+
+impl From<(RelationshipPage, Snapshot)> for ReadRelationshipsResponse {
+    fn from((page, snapshot): (RelationshipPage, Snapshot)) -> Self {
+        Self {
+            relationships: page.relationships,
+            next_cursor: page.next_cursor,
+            zookie: snapshot.zookie,
+        }
+    }
+}
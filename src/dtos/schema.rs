@@ -0,0 +1,87 @@
+use std::collections::BTreeMap;
+
+use serde::{Deserialize, Serialize};
+
+use crate::models::{OrphanedTuples, SchemaChanges};
+
+#[derive(Debug, Deserialize)]
+pub struct WriteSchemaRequest {
+    /// The complete schema in the schema language.
+    pub schema: String,
+    /// Delete the tuples of removed relations and namespaces that `migrate` does not move.
+    #[serde(default)]
+    pub force: bool,
+    /// Removed relations, `namespace#relation`, mapped to the relation of the same namespace
+    /// their tuples move to.
+    #[serde(default)]
+    pub migrate: BTreeMap<String, String>,
+    /// Report what the write would change without changing anything.
+    #[serde(default)]
+    pub dry_run: bool,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ReadSchemaResponse {
+    pub schema: String,
+}
+
+/// What the write changed, relations are named `namespace#relation`.
+#[derive(Debug, Serialize)]
+pub struct WriteSchemaResponse {
+    pub created_namespaces: Vec<String>,
+    pub deleted_namespaces: Vec<String>,
+    pub created_relations: Vec<String>,
+    pub updated_relations: Vec<String>,
+    pub deleted_relations: Vec<String>,
+    pub orphaned_tuples: Vec<OrphanedTuplesResponse>,
+    pub deleted_tuples: u64,
+    pub migrated_tuples: u64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub zookie: Option<String>,
+    /// Likely mistakes in the schema, each naming its line and column.
+    pub warnings: Vec<String>,
+}
+
+/// Tuples of a removed relation, or of a removed namespace used as subject type.
+#[derive(Debug, Serialize)]
+pub struct OrphanedTuplesResponse {
+    /// `namespace#relation`, or the namespace alone.
+    pub name: String,
+    pub tuple_count: i64,
+    pub subject_tuple_count: i64,
+    /// Permissions still referring to the relation, `namespace#relation`, which keep it from
+    /// being removed.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub referenced_by: Vec<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub migrated_to: Option<String>,
+}
+
+impl From<SchemaChanges> for WriteSchemaResponse {
+    fn from(value: SchemaChanges) -> Self {
+        Self {
+            created_namespaces: value.created_namespaces,
+            deleted_namespaces: value.deleted_namespaces,
+            created_relations: value.created_relations,
+            updated_relations: value.updated_relations,
+            deleted_relations: value.deleted_relations,
+            orphaned_tuples: value.orphaned.into_iter().map(Into::into).collect(),
+            deleted_tuples: value.deleted_tuples,
+            migrated_tuples: value.migrated_tuples,
+            zookie: value.zookie,
+            warnings: value.warnings.iter().map(ToString::to_string).collect(),
+        }
+    }
+}
+
+impl From<OrphanedTuples> for OrphanedTuplesResponse {
+    fn from(value: OrphanedTuples) -> Self {
+        Self {
+            name: value.name(),
+            tuple_count: value.tuple_count,
+            subject_tuple_count: value.subject_tuple_count,
+            referenced_by: value.referenced_by,
+            migrated_to: value.migrated_to,
+        }
+    }
+}
@@ -0,0 +1,39 @@
+use serde::{Deserialize, Serialize};
+
+use crate::models::{ChangeSet, RelationshipChange};
+
+#[derive(Debug, Deserialize)]
+pub struct WatchQuery {
+    /// Comma separated namespaces to watch, all of them if absent.
+    pub namespaces: Option<String>,
+    /// Start after the write this zookie was returned for, instead of after the latest one.
+    pub zookie: Option<String>,
+}
+
+impl WatchQuery {
+    pub fn namespaces(&self) -> Vec<String> {
+        self.namespaces
+            .iter()
+            .flat_map(|namespaces| namespaces.split(','))
+            .map(str::trim)
+            .filter(|namespace| !namespace.is_empty())
+            .map(str::to_string)
+            .collect()
+    }
+}
+
+/// Data of one `change` event, its id is the same zookie.
+#[derive(Debug, Serialize)]
+pub struct ChangeEvent {
+    pub zookie: String,
+    pub changes: Vec<RelationshipChange>,
+}
+
+impl From<ChangeSet> for ChangeEvent {
+    fn from(value: ChangeSet) -> Self {
+        Self {
+            zookie: value.zookie,
+            changes: value.changes,
+        }
+    }
+}
@@ -0,0 +1,19 @@
+use chrono::{DateTime, Utc};
+use serde_json::Value;
+use sqlx::{FromRow, types::Json};
+use uuid::Uuid;
+
+#[derive(Debug, Clone, FromRow)]
+pub struct AuditLogEntry {
+    pub id: Uuid,
+    pub timestamp: DateTime<Utc>,
+    pub actor: String,
+    pub action: String,
+    pub resource_type: String,
+    pub resource_id: String,
+    pub details: Json<Value>,
+    pub trace_id: Option<Uuid>,
+    /// `INET` on Postgres, so queries must select it as `client_ip::TEXT` there.
+    pub client_ip: Option<String>,
+    pub client_info: Option<Json<Value>>,
+}
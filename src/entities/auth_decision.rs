@@ -0,0 +1,23 @@
+use chrono::{DateTime, Utc};
+use serde_json::Value;
+use sqlx::{FromRow, types::Json};
+use uuid::Uuid;
+
+#[derive(Debug, Clone, FromRow)]
+pub struct AuthDecision {
+    pub id: Uuid,
+    pub timestamp: DateTime<Utc>,
+    pub request_id: Uuid,
+    pub subject_type: String,
+    pub subject_id: String,
+    pub namespace_id: String,
+    pub object_id: String,
+    pub relation: String,
+    pub permitted: bool,
+    pub cached: bool,
+    pub latency_ms: i32,
+    pub evaluation_path: Json<Value>,
+    pub zookie_token: Option<String>,
+    pub waited_for_consistency: bool,
+    pub consistency_wait_ms: Option<i32>,
+}
@@ -1 +1,27 @@
+//! Row types for every table of the initial schema.
+//!
+//! All entities decode from both Postgres and SQLite rows. UUIDs are `uuid::Uuid` (UUID on
+//! Postgres, 16 byte BLOB on SQLite), timestamps are `DateTime<Utc>` and JSON columns are
+//! `sqlx::types::Json`.
 
+mod audit_log;
+mod auth_decision;
+mod namespace;
+mod permissions_cache;
+mod relation;
+mod relation_rule;
+mod relationship_tuple;
+mod replication_status;
+mod transaction_log;
+mod zookie;
+
+pub use audit_log::AuditLogEntry;
+pub use auth_decision::AuthDecision;
+pub use namespace::Namespace;
+pub use permissions_cache::PermissionsCacheEntry;
+pub use relation::Relation;
+pub use relation_rule::{RelationRule, RuleType};
+pub use relationship_tuple::RelationshipTuple;
+pub use replication_status::{NodeStatus, ReplicationStatus};
+pub use transaction_log::{OperationType, TransactionLogEntry, TransactionStatus};
+pub use zookie::Zookie;
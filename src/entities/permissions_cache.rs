@@ -0,0 +1,18 @@
+use chrono::{DateTime, Utc};
+use sqlx::FromRow;
+use uuid::Uuid;
+
+#[derive(Debug, Clone, FromRow)]
+pub struct PermissionsCacheEntry {
+    pub id: Uuid,
+    pub namespace_id: String,
+    pub object_id: String,
+    pub relation: String,
+    pub subject_type: String,
+    pub subject_id: String,
+    pub permitted: bool,
+    pub computed_at: DateTime<Utc>,
+    pub valid_until: DateTime<Utc>,
+    pub max_zookie_version: i64,
+    pub cache_key: String,
+}
@@ -0,0 +1,16 @@
+use chrono::{DateTime, Utc};
+use sqlx::{FromRow, types::Json};
+use uuid::Uuid;
+
+#[derive(Debug, Clone, FromRow)]
+pub struct Relation {
+    pub id: Uuid,
+    pub namespace_id: String,
+    pub name: String,
+    pub description: Option<String>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+    pub deleted_at: Option<DateTime<Utc>>,
+    /// Allowed subjects declared in the schema, e.g. `user`, `user:*` or `group#member`.
+    pub subject_types: Option<Json<Vec<String>>>,
+}
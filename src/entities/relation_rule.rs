@@ -0,0 +1,52 @@
+use std::fmt;
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::{FromRow, types::Json};
+use uuid::Uuid;
+
+#[derive(Debug, Clone, FromRow)]
+pub struct RelationRule {
+    pub id: Uuid,
+    pub namespace_id: String,
+    pub relation_name: String,
+    pub rule_type: RuleType,
+    pub ttu_object_namespace: Option<String>,
+    pub ttu_relation: Option<String>,
+    pub child_relations: Option<Json<Vec<String>>>,
+    pub expression: Option<String>,
+    pub priority: i32,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+    pub deleted_at: Option<DateTime<Utc>>,
+}
+
+/// Postgres `rule_type` enum, a CHECK constrained TEXT column on SQLite.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize, sqlx::Type)]
+#[sqlx(type_name = "rule_type", rename_all = "kebab-case")]
+#[serde(rename_all = "kebab-case")]
+pub enum RuleType {
+    Direct,
+    Union,
+    Intersection,
+    Exclusion,
+    TupleToUserset,
+}
+
+impl RuleType {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Direct => "direct",
+            Self::Union => "union",
+            Self::Intersection => "intersection",
+            Self::Exclusion => "exclusion",
+            Self::TupleToUserset => "tuple-to-userset",
+        }
+    }
+}
+
+impl fmt::Display for RuleType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
@@ -0,0 +1,24 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+
+#[derive(Debug, Clone, FromRow)]
+pub struct ReplicationStatus {
+    pub node_id: String,
+    pub last_applied_version: i64,
+    pub last_applied_timestamp: DateTime<Utc>,
+    pub heartbeat_at: DateTime<Utc>,
+    pub is_primary: bool,
+    pub status: NodeStatus,
+    pub sync_lag_ms: Option<i32>,
+}
+
+/// `replication_status.status`, a CHECK constrained VARCHAR on both backends.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize, sqlx::Type)]
+#[sqlx(type_name = "varchar", rename_all = "UPPERCASE")]
+#[serde(rename_all = "UPPERCASE")]
+pub enum NodeStatus {
+    Active,
+    Inactive,
+    Degraded,
+}
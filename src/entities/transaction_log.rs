@@ -0,0 +1,62 @@
+use std::fmt;
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use sqlx::{FromRow, types::Json};
+use uuid::Uuid;
+
+#[derive(Debug, Clone, FromRow)]
+pub struct TransactionLogEntry {
+    pub id: Uuid,
+    pub timestamp: DateTime<Utc>,
+    pub version_number: i64,
+    pub operation: OperationType,
+    pub namespace_id: String,
+    pub object_id: String,
+    pub relation: String,
+    pub subject_type: String,
+    pub subject_id: String,
+    pub userset_namespace: Option<String>,
+    pub userset_relation: Option<String>,
+    pub zookie_token: String,
+    pub payload: Json<Value>,
+    pub status: TransactionStatus,
+}
+
+/// Postgres `operation_type` enum, a CHECK constrained TEXT column on SQLite.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize, sqlx::Type)]
+#[sqlx(type_name = "operation_type", rename_all = "lowercase")]
+#[serde(rename_all = "lowercase")]
+pub enum OperationType {
+    Create,
+    Update,
+    Delete,
+}
+
+impl OperationType {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Create => "create",
+            Self::Update => "update",
+            Self::Delete => "delete",
+        }
+    }
+}
+
+impl fmt::Display for OperationType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// `transaction_log.status`, a CHECK constrained VARCHAR on both backends.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize, sqlx::Type)]
+#[sqlx(type_name = "varchar", rename_all = "UPPERCASE")]
+#[serde(rename_all = "UPPERCASE")]
+pub enum TransactionStatus {
+    Pending,
+    Committed,
+    Failed,
+    Replicated,
+}
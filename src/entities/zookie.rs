@@ -0,0 +1,14 @@
+use chrono::{DateTime, Utc};
+use sqlx::FromRow;
+use uuid::Uuid;
+
+#[derive(Debug, Clone, FromRow)]
+pub struct Zookie {
+    pub token: String,
+    pub timestamp: DateTime<Utc>,
+    pub version: i64,
+    pub transaction_id: Uuid,
+    pub shard_id: i32,
+    pub created_at: DateTime<Utc>,
+    pub expired_at: DateTime<Utc>,
+}
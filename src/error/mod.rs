@@ -1 +1,299 @@
+use std::{fmt, io, net::SocketAddr};
 
+use axum::{
+    Json,
+    extract::rejection::{JsonRejection, QueryRejection},
+    http::StatusCode,
+    response::{IntoResponse, Response},
+};
+use serde::Serialize;
+use sqlx::migrate::MigrateError;
+
+use crate::{config::ConfigError, dtos::schema::OrphanedTuplesResponse, models::OrphanedTuples};
+
+pub type HeimdallResult<T> = Result<T, HeimdallError>;
+
+/// Extended result code SQLite uses for constraints enforced by foreign key actions.
+const SQLITE_CONSTRAINT_TRIGGER: &str = "1811";
+
+/// Every failure Heimdall can report, both while starting up and while serving requests.
+///
+/// Clients branch on [`HeimdallError::code`], so existing codes must never change meaning or be
+/// renamed; add a new variant instead.
+#[derive(Debug)]
+pub enum HeimdallError {
+    Config(ConfigError),
+    DatabaseConnection {
+        target: String,
+        source: sqlx::Error,
+    },
+    Database(sqlx::Error),
+    Migration(MigrateError),
+    Bind {
+        addr: SocketAddr,
+        source: io::Error,
+    },
+    Server(io::Error),
+    InvalidArgument(String),
+    /// A request body over the limit of its route.
+    PayloadTooLarge(String),
+    /// A JSON body sent without `Content-Type: application/json`.
+    UnsupportedMediaType(String),
+    NamespaceNotFound(String),
+    RelationNotFound {
+        namespace: String,
+        relation: String,
+    },
+    Conflict(String),
+    PreconditionFailed(String),
+    SchemaValidation(String),
+    /// A schema write removing what tuples or permissions still use, which is reported to the
+    /// client as a [`HeimdallError::SchemaValidation`] listing the `orphaned` tuples.
+    SchemaInUse {
+        message: String,
+        orphaned: Vec<OrphanedTuples>,
+    },
+    InvalidConsistencyToken(String),
+    ConsistencyTimeout(String),
+    RuleEvaluation(String),
+}
+
+/// Canonical gRPC status codes, for transports other than HTTP.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(i32)]
+pub enum GrpcCode {
+    InvalidArgument = 3,
+    NotFound = 5,
+    AlreadyExists = 6,
+    ResourceExhausted = 8,
+    FailedPrecondition = 9,
+    Internal = 13,
+    Unavailable = 14,
+}
+
+impl HeimdallError {
+    /// Stable, machine-readable identifier of the error kind.
+    pub fn code(&self) -> &'static str {
+        match self {
+            Self::Config(_) => "config_invalid",
+            Self::DatabaseConnection { .. } => "database_unavailable",
+            Self::Database(_) => "database_error",
+            Self::Migration(_) => "migration_failed",
+            Self::Bind { .. } | Self::Server(_) => "server_error",
+            Self::InvalidArgument(_) => "invalid_argument",
+            Self::PayloadTooLarge(_) => "payload_too_large",
+            Self::UnsupportedMediaType(_) => "unsupported_media_type",
+            Self::NamespaceNotFound(_) => "namespace_not_found",
+            Self::RelationNotFound { .. } => "relation_not_found",
+            Self::Conflict(_) => "conflict",
+            Self::PreconditionFailed(_) => "precondition_failed",
+            Self::SchemaValidation(_) | Self::SchemaInUse { .. } => "schema_validation_failed",
+            Self::InvalidConsistencyToken(_) => "invalid_consistency_token",
+            Self::ConsistencyTimeout(_) => "consistency_timeout",
+            Self::RuleEvaluation(_) => "rule_evaluation_failed",
+        }
+    }
+
+    /// Turns unique and foreign key violations into a [`HeimdallError::Conflict`] carrying
+    /// `message`, any other error is a plain database error.
+    pub(crate) fn conflict_on_constraint(
+        error: sqlx::Error,
+        message: impl FnOnce() -> String,
+    ) -> Self {
+        match &error {
+            sqlx::Error::Database(e)
+                if e.is_unique_violation()
+                    || e.is_foreign_key_violation()
+                    // SQLite reports `ON DELETE RESTRICT` as SQLITE_CONSTRAINT_TRIGGER, which
+                    // sqlx does not classify as a foreign key violation.
+                    || e.code().as_deref() == Some(SQLITE_CONSTRAINT_TRIGGER) =>
+            {
+                Self::Conflict(message())
+            }
+            _ => Self::Database(error),
+        }
+    }
+
+    pub fn status_code(&self) -> StatusCode {
+        match self {
+            Self::InvalidArgument(_) | Self::InvalidConsistencyToken(_) => StatusCode::BAD_REQUEST,
+            Self::PayloadTooLarge(_) => StatusCode::PAYLOAD_TOO_LARGE,
+            Self::UnsupportedMediaType(_) => StatusCode::UNSUPPORTED_MEDIA_TYPE,
+            Self::NamespaceNotFound(_) | Self::RelationNotFound { .. } => StatusCode::NOT_FOUND,
+            Self::Conflict(_) => StatusCode::CONFLICT,
+            Self::PreconditionFailed(_) => StatusCode::PRECONDITION_FAILED,
+            Self::SchemaValidation(_) | Self::SchemaInUse { .. } | Self::RuleEvaluation(_) => {
+                StatusCode::UNPROCESSABLE_ENTITY
+            }
+            Self::DatabaseConnection { .. } | Self::ConsistencyTimeout(_) => {
+                StatusCode::SERVICE_UNAVAILABLE
+            }
+            Self::Config(_)
+            | Self::Database(_)
+            | Self::Migration(_)
+            | Self::Bind { .. }
+            | Self::Server(_) => StatusCode::INTERNAL_SERVER_ERROR,
+        }
+    }
+
+    pub fn grpc_code(&self) -> GrpcCode {
+        match self {
+            Self::InvalidArgument(_)
+            | Self::UnsupportedMediaType(_)
+            | Self::InvalidConsistencyToken(_) => GrpcCode::InvalidArgument,
+            Self::PayloadTooLarge(_) => GrpcCode::ResourceExhausted,
+            Self::NamespaceNotFound(_) | Self::RelationNotFound { .. } => GrpcCode::NotFound,
+            Self::Conflict(_) => GrpcCode::AlreadyExists,
+            Self::PreconditionFailed(_)
+            | Self::SchemaValidation(_)
+            | Self::SchemaInUse { .. }
+            | Self::RuleEvaluation(_) => GrpcCode::FailedPrecondition,
+            Self::DatabaseConnection { .. } | Self::ConsistencyTimeout(_) => GrpcCode::Unavailable,
+            Self::Config(_)
+            | Self::Database(_)
+            | Self::Migration(_)
+            | Self::Bind { .. }
+            | Self::Server(_) => GrpcCode::Internal,
+        }
+    }
+}
+
+impl fmt::Display for HeimdallError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Config(e) => write!(f, "{e}"),
+            Self::DatabaseConnection { target, source } => {
+                write!(f, "failed to connect to the database at {target}: {source}")
+            }
+            Self::Database(e) => write!(f, "database error: {e}"),
+            Self::Migration(e) => write!(f, "failed to apply migrations: {e}"),
+            Self::Bind { addr, source } => write!(f, "failed to bind to {addr}: {source}"),
+            Self::Server(e) => write!(f, "server error: {e}"),
+            Self::InvalidArgument(message) => write!(f, "invalid argument: {message}"),
+            Self::PayloadTooLarge(message) => write!(f, "payload too large: {message}"),
+            Self::UnsupportedMediaType(message) => write!(f, "unsupported media type: {message}"),
+            Self::NamespaceNotFound(namespace) => write!(f, "namespace `{namespace}` not found"),
+            Self::RelationNotFound {
+                namespace,
+                relation,
+            } => write!(
+                f,
+                "relation `{relation}` not found in namespace `{namespace}`"
+            ),
+            Self::Conflict(message) => write!(f, "{message}"),
+            Self::PreconditionFailed(message) => write!(f, "precondition failed: {message}"),
+            Self::SchemaValidation(message) | Self::SchemaInUse { message, .. } => {
+                write!(f, "schema validation failed: {message}")
+            }
+            Self::InvalidConsistencyToken(message) => {
+                write!(f, "invalid consistency token: {message}")
+            }
+            Self::ConsistencyTimeout(message) => write!(f, "consistency timeout: {message}"),
+            Self::RuleEvaluation(message) => write!(f, "rule evaluation failed: {message}"),
+        }
+    }
+}
+
+impl std::error::Error for HeimdallError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::Config(e) => Some(e),
+            Self::DatabaseConnection { source, .. } => Some(source),
+            Self::Database(e) => Some(e),
+            Self::Migration(e) => Some(e),
+            Self::Bind { source, .. } => Some(source),
+            Self::Server(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<ConfigError> for HeimdallError {
+    fn from(value: ConfigError) -> Self {
+        Self::Config(value)
+    }
+}
+
+impl From<sqlx::Error> for HeimdallError {
+    fn from(value: sqlx::Error) -> Self {
+        Self::Database(value)
+    }
+}
+
+impl From<MigrateError> for HeimdallError {
+    fn from(value: MigrateError) -> Self {
+        Self::Migration(value)
+    }
+}
+
+/// Bodies over the limit and bodies that are not declared JSON keep the status axum gives them,
+/// any other rejection is an invalid argument.
+impl From<JsonRejection> for HeimdallError {
+    fn from(value: JsonRejection) -> Self {
+        match value.status() {
+            StatusCode::PAYLOAD_TOO_LARGE => Self::PayloadTooLarge(value.body_text()),
+            StatusCode::UNSUPPORTED_MEDIA_TYPE => Self::UnsupportedMediaType(value.body_text()),
+            _ => Self::InvalidArgument(value.body_text()),
+        }
+    }
+}
+
+impl From<QueryRejection> for HeimdallError {
+    fn from(value: QueryRejection) -> Self {
+        Self::InvalidArgument(value.body_text())
+    }
+}
+
+/// JSON body of an error, `{"error": {"code", "message"}}`, with the `orphaned_tuples` of a
+/// [`HeimdallError::SchemaInUse`].
+#[derive(Debug, Serialize)]
+pub(crate) struct ErrorBody {
+    error: ErrorDetails,
+}
+
+#[derive(Debug, Serialize)]
+struct ErrorDetails {
+    code: &'static str,
+    message: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    orphaned_tuples: Option<Vec<OrphanedTuplesResponse>>,
+}
+
+impl HeimdallError {
+    /// What the client gets to see of the error.
+    pub(crate) fn body(&self) -> ErrorBody {
+        // Internal errors are logged in full, but only their kind is exposed to the client. A
+        // consistency timeout reveals nothing internal and tells the client what to retry.
+        let status = self.status_code();
+        let internal = status.is_server_error() && !matches!(self, Self::ConsistencyTimeout(_));
+        let message = if internal {
+            tracing::error!(error = %self, code = self.code(), "request failed");
+            status
+                .canonical_reason()
+                .unwrap_or("internal error")
+                .to_string()
+        } else {
+            self.to_string()
+        };
+
+        let orphaned_tuples = match self {
+            Self::SchemaInUse { orphaned, .. } => {
+                Some(orphaned.iter().cloned().map(Into::into).collect())
+            }
+            _ => None,
+        };
+        ErrorBody {
+            error: ErrorDetails {
+                code: self.code(),
+                message,
+                orphaned_tuples,
+            },
+        }
+    }
+}
+
+impl IntoResponse for HeimdallError {
+    fn into_response(self) -> Response {
+        (self.status_code(), Json(self.body())).into_response()
+    }
+}
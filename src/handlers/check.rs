@@ -0,0 +1,47 @@
+use axum::{Json, extract::State};
+
+use crate::{
+    dtos::check::{BulkCheckRequest, BulkCheckResponse, CheckRequest, CheckResponse},
+    error::HeimdallResult,
+    models::{ObjectRelation, SubjectRef},
+    state::AppState,
+};
+
+use super::extract::{ApiJson, RequestUuid};
+
+pub async fn check(
+    State(app_state): State<AppState>,
+    RequestUuid(request_id): RequestUuid,
+    ApiJson(request): ApiJson<CheckRequest>,
+) -> HeimdallResult<Json<CheckResponse>> {
+    let (allowed, snapshot) = app_state
+        .check_service
+        .check(
+            &request.object(),
+            &request.subject(),
+            &request.consistency,
+            request_id,
+        )
+        .await?;
+    Ok(Json(CheckResponse {
+        allowed,
+        zookie: snapshot.zookie,
+    }))
+}
+
+pub async fn check_bulk(
+    State(app_state): State<AppState>,
+    RequestUuid(request_id): RequestUuid,
+    ApiJson(request): ApiJson<BulkCheckRequest>,
+) -> HeimdallResult<Json<BulkCheckResponse>> {
+    let items: Vec<(ObjectRelation, SubjectRef)> =
+        request.items.into_iter().map(Into::into).collect();
+    let (results, snapshot) = app_state
+        .check_service
+        .check_bulk(&items, &request.consistency, request_id)
+        .await?;
+    Ok(Json(BulkCheckResponse {
+        results: results.into_iter().map(Into::into).collect(),
+        zookie: snapshot.zookie,
+    }))
+}
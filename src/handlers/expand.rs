@@ -0,0 +1,23 @@
+use axum::{Json, extract::State};
+
+use crate::{
+    dtos::expand::{ExpandRequest, ExpandResponse},
+    error::HeimdallResult,
+    state::AppState,
+};
+
+use super::extract::ApiJson;
+
+pub async fn expand(
+    State(app_state): State<AppState>,
+    ApiJson(request): ApiJson<ExpandRequest>,
+) -> HeimdallResult<Json<ExpandResponse>> {
+    let (tree, snapshot) = app_state
+        .expand_service
+        .expand(&request.object(), &request.consistency)
+        .await?;
+    Ok(Json(ExpandResponse {
+        tree,
+        zookie: snapshot.zookie,
+    }))
+}
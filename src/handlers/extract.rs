@@ -0,0 +1,92 @@
+use std::convert::Infallible;
+
+use axum::{
+    extract::{FromRequest, FromRequestParts},
+    http::request::Parts,
+};
+use uuid::Uuid;
+
+use crate::{error::HeimdallError, middlewares::trace::REQUEST_ID_HEADER};
+
+/// [`axum::Json`] whose rejections are reported in Heimdall's error format.
+#[derive(Debug, FromRequest)]
+#[from_request(via(axum::Json), rejection(HeimdallError))]
+pub struct ApiJson<T>(pub T);
+
+/// [`axum::extract::Query`] whose rejections are reported in Heimdall's error format.
+#[derive(Debug, FromRequestParts)]
+#[from_request(via(axum::extract::Query), rejection(HeimdallError))]
+pub struct ApiQuery<T>(pub T);
+
+/// Id of the request as a UUID, the `x-request-id` the request span logs and the response
+/// echoes. [`normalize_request_id`] and `SetRequestIdLayer` make sure it is one, a fresh id is
+/// only made up for requests that did not pass through them.
+///
+/// [`normalize_request_id`]: crate::middlewares::request_id::normalize_request_id
+#[derive(Debug, Clone, Copy)]
+pub struct RequestUuid(pub Uuid);
+
+impl<S: Send + Sync> FromRequestParts<S> for RequestUuid {
+    type Rejection = Infallible;
+
+    async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
+        let request_id = parts
+            .headers
+            .get(REQUEST_ID_HEADER)
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| Uuid::parse_str(value).ok())
+            .unwrap_or_else(Uuid::new_v4);
+        Ok(Self(request_id))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::{body::Body, http::Request};
+    use serde_json::Value;
+
+    use super::*;
+
+    async fn extract(content_type: Option<&str>, body: impl Into<Body>) -> HeimdallError {
+        let mut request = Request::post("/check");
+        if let Some(content_type) = content_type {
+            request = request.header("content-type", content_type);
+        }
+        let request = request.body(body.into()).unwrap();
+        match ApiJson::<Value>::from_request(request, &()).await {
+            Ok(ApiJson(value)) => panic!("expected a rejection, got {value}"),
+            Err(error) => error,
+        }
+    }
+
+    #[tokio::test]
+    async fn malformed_bodies_are_invalid_arguments() {
+        let error = extract(Some("application/json"), "{\"object\":").await;
+        assert_eq!(
+            (error.status_code().as_u16(), error.code()),
+            (400, "invalid_argument")
+        );
+    }
+
+    #[tokio::test]
+    async fn bodies_over_the_limit_are_too_large() {
+        // Beyond axum's default limit of 2 MB.
+        let body = format!("\"{}\"", "a".repeat(3 * 1024 * 1024));
+        let error = extract(Some("application/json"), body).await;
+        assert_eq!(
+            (error.status_code().as_u16(), error.code()),
+            (413, "payload_too_large")
+        );
+    }
+
+    #[tokio::test]
+    async fn bodies_not_declared_json_are_unsupported() {
+        for content_type in [None, Some("text/plain")] {
+            let error = extract(content_type, "{}").await;
+            assert_eq!(
+                (error.status_code().as_u16(), error.code()),
+                (415, "unsupported_media_type")
+            );
+        }
+    }
+}
@@ -0,0 +1,11 @@
+use axum::{Json, extract::State};
+use serde_json::{Value, json};
+
+use crate::state::AppState;
+
+pub async fn health_check(State(app_state): State<AppState>) -> Json<Value> {
+    Json(json!({
+        "status": "ok",
+        "check_cache": app_state.check_cache.stats(),
+    }))
+}
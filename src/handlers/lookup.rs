@@ -0,0 +1,45 @@
+use axum::{Json, extract::State};
+
+use crate::{
+    dtos::lookup::{
+        LookupResourcesRequest, LookupResourcesResponse, LookupSubjectsRequest,
+        LookupSubjectsResponse,
+    },
+    error::HeimdallResult,
+    state::AppState,
+};
+
+use super::extract::ApiJson;
+
+pub async fn lookup_resources(
+    State(app_state): State<AppState>,
+    ApiJson(request): ApiJson<LookupResourcesRequest>,
+) -> HeimdallResult<Json<LookupResourcesResponse>> {
+    let page = app_state
+        .lookup_service
+        .lookup_resources(
+            &request.namespace,
+            &request.relation,
+            &request.subject(),
+            request.cursor.as_deref(),
+            request.limit,
+            &request.consistency,
+        )
+        .await?;
+    Ok(Json(page.into()))
+}
+
+pub async fn lookup_subjects(
+    State(app_state): State<AppState>,
+    ApiJson(request): ApiJson<LookupSubjectsRequest>,
+) -> HeimdallResult<Json<LookupSubjectsResponse>> {
+    let subjects = app_state
+        .lookup_service
+        .lookup_subjects(
+            &request.object(),
+            &request.subject_type,
+            &request.consistency,
+        )
+        .await?;
+    Ok(Json(subjects.into()))
+}
@@ -1 +1,10 @@
-
+pub mod check;
+pub mod expand;
+pub mod extract;
+pub mod health;
+pub mod lookup;
+pub mod namespace;
+pub mod relation;
+pub mod relationship;
+pub mod schema;
+pub mod watch;
@@ -0,0 +1,65 @@
+use axum::{
+    Json,
+    extract::{Path, State},
+    http::StatusCode,
+};
+
+use crate::{
+    dtos::namespace::{CreateNamespaceRequest, NamespaceResponse, UpdateNamespaceRequest},
+    error::HeimdallResult,
+    state::AppState,
+};
+
+use super::extract::ApiJson;
+
+pub async fn list_namespaces(
+    State(app_state): State<AppState>,
+) -> HeimdallResult<Json<Vec<NamespaceResponse>>> {
+    let namespaces = app_state.namespace_service.list_namespaces().await?;
+    Ok(Json(namespaces.into_iter().map(Into::into).collect()))
+}
+
+pub async fn get_namespace(
+    State(app_state): State<AppState>,
+    Path(namespace_id): Path<String>,
+) -> HeimdallResult<Json<NamespaceResponse>> {
+    let namespace = app_state
+        .namespace_service
+        .get_namespace(&namespace_id)
+        .await?;
+    Ok(Json(namespace.into()))
+}
+
+pub async fn create_namespace(
+    State(app_state): State<AppState>,
+    ApiJson(request): ApiJson<CreateNamespaceRequest>,
+) -> HeimdallResult<(StatusCode, Json<NamespaceResponse>)> {
+    let namespace = app_state
+        .namespace_service
+        .create_namespace(request)
+        .await?;
+    Ok((StatusCode::CREATED, Json(namespace.into())))
+}
+
+pub async fn update_namespace(
+    State(app_state): State<AppState>,
+    Path(namespace_id): Path<String>,
+    ApiJson(request): ApiJson<UpdateNamespaceRequest>,
+) -> HeimdallResult<Json<NamespaceResponse>> {
+    let namespace = app_state
+        .namespace_service
+        .update_namespace(&namespace_id, request)
+        .await?;
+    Ok(Json(namespace.into()))
+}
+
+pub async fn delete_namespace(
+    State(app_state): State<AppState>,
+    Path(namespace_id): Path<String>,
+) -> HeimdallResult<StatusCode> {
+    app_state
+        .namespace_service
+        .delete_namespace(&namespace_id)
+        .await?;
+    Ok(StatusCode::NO_CONTENT)
+}
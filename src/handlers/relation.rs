@@ -0,0 +1,74 @@
+use axum::{
+    Json,
+    extract::{Path, State},
+    http::StatusCode,
+};
+
+use crate::{
+    dtos::relation::{
+        CreateRelationRequest, DeleteRelationResponse, ListRelationsQuery, RelationResponse,
+        UpdateRelationRequest,
+    },
+    error::HeimdallResult,
+    state::AppState,
+};
+
+use super::extract::{ApiJson, ApiQuery};
+
+pub async fn list_relations(
+    State(app_state): State<AppState>,
+    Path(namespace_id): Path<String>,
+    ApiQuery(query): ApiQuery<ListRelationsQuery>,
+) -> HeimdallResult<Json<Vec<RelationResponse>>> {
+    let relations = app_state
+        .namespace_service
+        .list_relations(&namespace_id, query.include_deleted)
+        .await?;
+    Ok(Json(relations.into_iter().map(Into::into).collect()))
+}
+
+pub async fn get_relation(
+    State(app_state): State<AppState>,
+    Path((namespace_id, relation_name)): Path<(String, String)>,
+) -> HeimdallResult<Json<RelationResponse>> {
+    let relation = app_state
+        .namespace_service
+        .get_relation(&namespace_id, &relation_name)
+        .await?;
+    Ok(Json(relation.into()))
+}
+
+pub async fn create_relation(
+    State(app_state): State<AppState>,
+    Path(namespace_id): Path<String>,
+    ApiJson(request): ApiJson<CreateRelationRequest>,
+) -> HeimdallResult<(StatusCode, Json<RelationResponse>)> {
+    let relation = app_state
+        .namespace_service
+        .create_relation(&namespace_id, request)
+        .await?;
+    Ok((StatusCode::CREATED, Json(relation.into())))
+}
+
+pub async fn update_relation(
+    State(app_state): State<AppState>,
+    Path((namespace_id, relation_name)): Path<(String, String)>,
+    ApiJson(request): ApiJson<UpdateRelationRequest>,
+) -> HeimdallResult<Json<RelationResponse>> {
+    let relation = app_state
+        .namespace_service
+        .update_relation(&namespace_id, &relation_name, request)
+        .await?;
+    Ok(Json(relation.into()))
+}
+
+pub async fn delete_relation(
+    State(app_state): State<AppState>,
+    Path((namespace_id, relation_name)): Path<(String, String)>,
+) -> HeimdallResult<Json<DeleteRelationResponse>> {
+    let zookie = app_state
+        .namespace_service
+        .delete_relation(&namespace_id, &relation_name)
+        .await?;
+    Ok(Json(DeleteRelationResponse { zookie }))
+}
@@ -0,0 +1,39 @@
+use axum::{Json, extract::State};
+
+use crate::{
+    dtos::relationship::{
+        ReadRelationshipsQuery, ReadRelationshipsResponse, WriteRelationshipsRequest,
+        WriteRelationshipsResponse,
+    },
+    error::HeimdallResult,
+    state::AppState,
+};
+
+use super::extract::{ApiJson, ApiQuery};
+
+pub async fn write_relationships(
+    State(app_state): State<AppState>,
+    ApiJson(request): ApiJson<WriteRelationshipsRequest>,
+) -> HeimdallResult<Json<WriteRelationshipsResponse>> {
+    let zookie = app_state
+        .relationship_service
+        .write(&request.updates, &request.preconditions)
+        .await?;
+    Ok(Json(zookie.into()))
+}
+
+pub async fn read_relationships(
+    State(app_state): State<AppState>,
+    ApiQuery(query): ApiQuery<ReadRelationshipsQuery>,
+) -> HeimdallResult<Json<ReadRelationshipsResponse>> {
+    let page = app_state
+        .relationship_service
+        .read(
+            &query.filter(),
+            query.cursor.as_deref(),
+            query.limit,
+            &query.consistency(),
+        )
+        .await?;
+    Ok(Json(page.into()))
+}
@@ -0,0 +1,39 @@
+use axum::{Json, extract::State};
+
+use crate::{
+    dtos::schema::{ReadSchemaResponse, WriteSchemaRequest, WriteSchemaResponse},
+    error::HeimdallResult,
+    models::{RelationMigration, SchemaWriteOptions},
+    state::AppState,
+};
+
+use super::extract::ApiJson;
+
+pub async fn read_schema(
+    State(app_state): State<AppState>,
+) -> HeimdallResult<Json<ReadSchemaResponse>> {
+    let schema = app_state.schema_service.read_schema().await?;
+    Ok(Json(ReadSchemaResponse {
+        schema: schema.to_string(),
+    }))
+}
+
+pub async fn write_schema(
+    State(app_state): State<AppState>,
+    ApiJson(request): ApiJson<WriteSchemaRequest>,
+) -> HeimdallResult<Json<WriteSchemaResponse>> {
+    let options = SchemaWriteOptions {
+        force: request.force,
+        migrations: request
+            .migrate
+            .iter()
+            .map(|(from, to)| RelationMigration::parse(from, to))
+            .collect::<HeimdallResult<_>>()?,
+        dry_run: request.dry_run,
+    };
+    let changes = app_state
+        .schema_service
+        .write_schema(&request.schema, &options)
+        .await?;
+    Ok(Json(changes.into()))
+}
@@ -0,0 +1,47 @@
+use std::convert::Infallible;
+
+use axum::{
+    extract::State,
+    http::HeaderMap,
+    response::sse::{Event, KeepAlive, Sse},
+};
+use futures_util::{Stream, StreamExt};
+
+use crate::{
+    dtos::watch::{ChangeEvent, WatchQuery},
+    error::HeimdallResult,
+    state::AppState,
+};
+
+use super::extract::ApiQuery;
+
+/// Header an `EventSource` sends on reconnect, carrying the id of the last event it received.
+const LAST_EVENT_ID_HEADER: &str = "last-event-id";
+
+pub async fn watch(
+    State(app_state): State<AppState>,
+    headers: HeaderMap,
+    ApiQuery(query): ApiQuery<WatchQuery>,
+) -> HeimdallResult<Sse<impl Stream<Item = Result<Event, Infallible>>>> {
+    // A reconnecting client resumes after the last event it saw, not where it first started.
+    let zookie = headers
+        .get(LAST_EVENT_ID_HEADER)
+        .and_then(|value| value.to_str().ok())
+        .or(query.zookie.as_deref());
+    let change_sets = app_state
+        .watch_service
+        .watch(query.namespaces(), zookie)
+        .await?;
+
+    let events = change_sets.map(|change_set| {
+        let event = match change_set {
+            Ok(change_set) => Event::default()
+                .event("change")
+                .id(change_set.zookie.clone())
+                .json_data(ChangeEvent::from(change_set)),
+            Err(error) => Event::default().event("error").json_data(error.body()),
+        };
+        Ok(event.expect("events serialize to JSON"))
+    });
+    Ok(Sse::new(events).keep_alive(KeepAlive::default()))
+}
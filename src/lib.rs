@@ -1,18 +1,244 @@
+pub mod cli;
 pub mod config;
+mod database;
 mod dtos;
-mod entities;
-mod error;
+pub mod entities;
+pub mod error;
 mod handlers;
 mod middlewares;
+mod models;
 mod repositories;
 mod routes;
 mod services;
 mod state;
+pub mod telemetry;
 
+use std::{io::Read, net::SocketAddr, path::Path};
+
+use cli::{MigrateCommand, SchemaCommand};
 use config::AppConfig;
+use database::MigrationState;
+use error::HeimdallError;
+use models::{RelationMigration, SchemaChanges, SchemaWarning, SchemaWriteOptions};
+use services::{CheckCache, SchemaService, consistency::ZookieCodec, schema::parse_schema};
 use state::AppState;
+use tokio::net::TcpListener;
+
+pub async fn start_service(app_config: AppConfig) -> Result<(), HeimdallError> {
+    app_config.consistency_config.validate_zookie_secret()?;
+    let app_state = AppState::new(&app_config).await?;
+    if app_config.database_config.auto_migrate {
+        app_state.pool.migrate_up().await?;
+    }
+
+    let addr = SocketAddr::new(app_config.server_config.ip, app_config.server_config.port);
+    let listener = TcpListener::bind(addr)
+        .await
+        .map_err(|source| HeimdallError::Bind { addr, source })?;
+    tracing::info!(%addr, "listening");
+
+    // In-flight requests are drained by axum before `serve` returns, everything the state holds
+    // is released only afterwards so those requests can still complete.
+    let shutdown = {
+        let app_state = app_state.clone();
+        async move {
+            shutdown_signal().await;
+            // Watch streams never end on their own and would hold the drain open.
+            app_state.watch_service.close();
+        }
+    };
+    let served = axum::serve(listener, routes::create_router(app_state.clone()))
+        .with_graceful_shutdown(shutdown)
+        .await
+        .map_err(HeimdallError::Server);
+    app_state.shutdown().await;
+    tracing::info!("shutdown complete");
+
+    served
+}
+
+pub async fn run_migrations(
+    app_config: AppConfig,
+    command: MigrateCommand,
+) -> Result<(), HeimdallError> {
+    let pool = state::connect(&app_config).await?;
+
+    let result = match command {
+        MigrateCommand::Up => pool.migrate_up().await.map(|_| {
+            println!("All migrations applied");
+        }),
+        MigrateCommand::Down { target } => pool.migrate_down(target).await.map(|version| {
+            println!("Reverted migrations newer than version {version}");
+        }),
+        MigrateCommand::Status => pool.migration_status().await.map(|statuses| {
+            for status in statuses {
+                let state = match status.state {
+                    MigrationState::Applied => "applied",
+                    MigrationState::Pending => "pending",
+                    MigrationState::ChecksumMismatch => "applied (checksum mismatch)",
+                    MigrationState::Unknown => "applied (unknown migration)",
+                };
+                println!(
+                    "{:<16} {:<28} {}",
+                    status.version, state, status.description
+                );
+            }
+        }),
+    };
+    pool.close().await;
+
+    Ok(result?)
+}
+
+pub async fn run_schema(
+    app_config: AppConfig,
+    command: SchemaCommand,
+) -> Result<(), HeimdallError> {
+    let (source, options) = match command {
+        SchemaCommand::Format { file } => {
+            let (schema, warnings) = parse_schema(&read_schema_file(&file)?)?;
+            print!("{schema}");
+            print_schema_warnings(&warnings);
+            return Ok(());
+        }
+        SchemaCommand::Apply {
+            file,
+            force,
+            migrate,
+            dry_run,
+        } => {
+            let migrations = migrate
+                .iter()
+                .map(|migration| match migration.split_once('=') {
+                    Some((from, to)) => RelationMigration::parse(from, to),
+                    None => Err(HeimdallError::InvalidArgument(format!(
+                        "cannot migrate `{migration}`: expected `namespace#relation=target`"
+                    ))),
+                })
+                .collect::<Result<_, _>>()?;
+            let options = SchemaWriteOptions {
+                force,
+                migrations,
+                dry_run,
+            };
+            (Some(read_schema_file(&file)?), options)
+        }
+        SchemaCommand::Export => (None, SchemaWriteOptions::default()),
+    };
+    app_config.consistency_config.validate_zookie_secret()?;
+    let pool = state::connect(&app_config).await?;
+    let zookies = ZookieCodec::new(&app_config.consistency_config);
+    // Nothing in this process serves checks, the cache is only there to be cleared.
+    let check_cache = CheckCache::new(&app_config.cache_config);
+    let schema_service = SchemaService::new(pool.clone(), zookies, check_cache);
+
+    let result = match source {
+        Some(source) => schema_service
+            .write_schema(&source, &options)
+            .await
+            .map(|changes| print_schema_changes(changes, options.dry_run)),
+        None => schema_service
+            .read_schema()
+            .await
+            .map(|schema| print!("{schema}")),
+    };
+    pool.close().await;
+
+    result
+}
+
+/// Reads a schema file, `-` being standard input.
+fn read_schema_file(path: &Path) -> Result<String, HeimdallError> {
+    let mut source = String::new();
+    let read = if path == Path::new("-") {
+        std::io::stdin().read_to_string(&mut source).map(|_| ())
+    } else {
+        std::fs::read_to_string(path).map(|contents| source = contents)
+    };
+    read.map_err(|e| {
+        HeimdallError::InvalidArgument(format!("failed to read `{}`: {e}", path.display()))
+    })?;
+    Ok(source)
+}
+
+/// Warnings go to standard error, keeping standard output a valid schema.
+fn print_schema_warnings(warnings: &[SchemaWarning]) {
+    for warning in warnings {
+        eprintln!("warning: {warning}");
+    }
+}
+
+fn print_schema_changes(changes: SchemaChanges, dry_run: bool) {
+    print_schema_warnings(&changes.warnings);
+    if changes.is_empty() {
+        println!("Schema is up to date");
+        return;
+    }
+    for orphaned in &changes.orphaned {
+        let mut tuples = format!("{} tuple(s)", orphaned.tuple_count);
+        if orphaned.subject_tuple_count > 0 {
+            tuples.push_str(&format!(
+                ", {} with it as subject",
+                orphaned.subject_tuple_count
+            ));
+        }
+        match &orphaned.migrated_to {
+            Some(target) => println!(
+                "{:<18} {} -> {target} ({tuples})",
+                "migrated tuples",
+                orphaned.name()
+            ),
+            None => println!("{:<18} {} ({tuples})", "deleted tuples", orphaned.name()),
+        }
+    }
+    let zookie = changes.zookie;
+    let changes = [
+        ("created namespace", changes.created_namespaces),
+        ("deleted namespace", changes.deleted_namespaces),
+        ("created relation", changes.created_relations),
+        ("updated relation", changes.updated_relations),
+        ("deleted relation", changes.deleted_relations),
+    ];
+    for (change, names) in changes {
+        for name in names {
+            println!("{change:<18} {name}");
+        }
+    }
+    if let Some(zookie) = zookie {
+        println!("{:<18} {zookie}", "zookie");
+    }
+    if dry_run {
+        println!("Dry run, nothing was changed");
+    }
+}
+
+/// Resolves on SIGINT (Ctrl+C) or, on unix, SIGTERM.
+async fn shutdown_signal() {
+    let ctrl_c = async {
+        if let Err(e) = tokio::signal::ctrl_c().await {
+            tracing::error!(error = %e, "failed to listen for SIGINT");
+            std::future::pending::<()>().await;
+        }
+    };
+
+    #[cfg(unix)]
+    let terminate = async {
+        match tokio::signal::unix::signal(tokio::signal::unix::SignalKind::terminate()) {
+            Ok(mut signal) => {
+                signal.recv().await;
+            }
+            Err(e) => {
+                tracing::error!(error = %e, "failed to listen for SIGTERM");
+                std::future::pending::<()>().await;
+            }
+        }
+    };
+    #[cfg(not(unix))]
+    let terminate = std::future::pending::<()>();
 
-pub async fn start_service(app_config: AppConfig) -> Result<(), String> {
-    let _app_state = AppState::new(app_config);
-    Ok(())
+    tokio::select! {
+        _ = ctrl_c => {},
+        _ = terminate => {},
+    }
+    tracing::info!("shutdown signal received, draining in-flight requests");
 }
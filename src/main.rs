@@ -1,13 +1,27 @@
-use heimdall::config::AppConfig;
+use clap::Parser;
+use heimdall::{
+    cli::{Cli, Command},
+    config::AppConfig,
+    error::HeimdallError,
+};
 
 #[tokio::main]
 async fn main() {
+    let cli = Cli::parse();
+    if let Err(e) = run(cli).await {
+        eprintln!("heimdall: {e}");
+        std::process::exit(1);
+    }
+}
+
+async fn run(cli: Cli) -> Result<(), HeimdallError> {
     // NOTE: Initializing Application Configuration right at the start, so that I can use the
     // configuration values for setting up tracing if needed.
-    let app_config = AppConfig::default();
-    // TODO: Initialize Tracing
-    if let Err(_e) = heimdall::start_service(app_config).await {
-        // TODO: handle error for better context
-        todo!()
+    let app_config = AppConfig::load(&cli.config_args)?;
+    heimdall::telemetry::init_tracing(&app_config.logging_config);
+    match cli.command.unwrap_or(Command::Serve) {
+        Command::Serve => heimdall::start_service(app_config).await,
+        Command::Migrate(command) => heimdall::run_migrations(app_config, command).await,
+        Command::Schema(command) => heimdall::run_schema(app_config, command).await,
     }
 }
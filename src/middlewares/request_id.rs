@@ -0,0 +1,57 @@
+use axum::{extract::Request, http::HeaderValue, middleware::Next, response::Response};
+use uuid::Uuid;
+
+use super::trace::REQUEST_ID_HEADER;
+
+/// Makes the `x-request-id` of a request a UUID in its hyphenated form before
+/// `SetRequestIdLayer` runs. A client id that is not a UUID is dropped, so the layer assigns a
+/// fresh one, and the same id is logged, echoed and stored with the decisions of the request.
+pub async fn normalize_request_id(mut request: Request, next: Next) -> Response {
+    let headers = request.headers_mut();
+    match headers.get(REQUEST_ID_HEADER).map(normalized) {
+        Some(Some(request_id)) => {
+            headers.insert(REQUEST_ID_HEADER, request_id);
+        }
+        Some(None) => {
+            headers.remove(REQUEST_ID_HEADER);
+        }
+        None => {}
+    }
+    next.run(request).await
+}
+
+/// `request_id` in hyphenated form, `None` if it is not a UUID.
+fn normalized(request_id: &HeaderValue) -> Option<HeaderValue> {
+    let request_id = Uuid::try_parse_ascii(request_id.as_bytes()).ok()?;
+    HeaderValue::from_str(&request_id.hyphenated().to_string()).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn normalize(request_id: &str) -> Option<String> {
+        let normalized = normalized(&HeaderValue::from_str(request_id).unwrap())?;
+        Some(normalized.to_str().unwrap().to_string())
+    }
+
+    #[test]
+    fn uuids_are_kept_in_hyphenated_form() {
+        let hyphenated = "67e55044-10b1-426f-9247-bb680e5fe0c8";
+        assert_eq!(normalize(hyphenated).as_deref(), Some(hyphenated));
+        assert_eq!(
+            normalize("67E5504410B1426F9247BB680E5FE0C8").as_deref(),
+            Some(hyphenated)
+        );
+        assert_eq!(
+            normalize("{67e55044-10b1-426f-9247-bb680e5fe0c8}").as_deref(),
+            Some(hyphenated)
+        );
+    }
+
+    #[test]
+    fn other_ids_are_dropped() {
+        assert_eq!(normalize("checkout-42"), None);
+        assert_eq!(normalize(""), None);
+    }
+}
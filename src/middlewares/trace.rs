@@ -0,0 +1,34 @@
+use std::time::Duration;
+
+use axum::{
+    body::Body,
+    http::{Request, Response},
+};
+use tracing::{Span, field};
+
+pub const REQUEST_ID_HEADER: &str = "x-request-id";
+
+/// Opens the span every HTTP request runs in. The request id is normalized and, if missing,
+/// set by `SetRequestIdLayer` before this runs, so it is always a UUID.
+pub fn make_span(request: &Request<Body>) -> Span {
+    let request_id = request
+        .headers()
+        .get(REQUEST_ID_HEADER)
+        .and_then(|value| value.to_str().ok())
+        .unwrap_or_default();
+
+    tracing::info_span!(
+        "http_request",
+        method = %request.method(),
+        uri = %request.uri(),
+        request_id,
+        status = field::Empty,
+        latency_ms = field::Empty,
+    )
+}
+
+pub fn on_response(response: &Response<Body>, latency: Duration, span: &Span) {
+    span.record("status", response.status().as_u16());
+    span.record("latency_ms", latency.as_secs_f64() * 1000.0);
+    tracing::info!("request completed");
+}
@@ -0,0 +1,26 @@
+use serde::Serialize;
+
+use super::TupleKey;
+
+/// What happened to a tuple. Refreshing an existing tuple changes nothing and is not reported.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ChangeOperation {
+    Create,
+    Delete,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct RelationshipChange {
+    pub operation: ChangeOperation,
+    pub relationship: TupleKey,
+}
+
+/// Every change one committed write made, in key order.
+#[derive(Debug, Clone)]
+pub struct ChangeSet {
+    pub version: i64,
+    /// Zookie of `version`, from which watching resumes after this change set.
+    pub zookie: String,
+    pub changes: Vec<RelationshipChange>,
+}
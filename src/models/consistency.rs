@@ -0,0 +1,24 @@
+use serde::Deserialize;
+
+/// How fresh the data a read is evaluated against has to be.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize)]
+#[serde(tag = "mode", rename_all = "snake_case")]
+pub enum Consistency {
+    /// Whatever is fastest to read, possibly missing the latest writes.
+    #[default]
+    MinimizeLatency,
+    /// At least every write up to and including the one `zookie` was returned for.
+    AtLeastAsFresh { zookie: String },
+    /// Every write committed before the read started.
+    FullyConsistent,
+}
+
+/// The version a read was evaluated at, and what it took to get there.
+#[derive(Debug, Clone)]
+pub struct Snapshot {
+    pub version: i64,
+    /// Zookie of `version`, returned to the caller to chain further reads.
+    pub zookie: String,
+    pub waited_for_consistency: bool,
+    pub consistency_wait_ms: Option<i32>,
+}
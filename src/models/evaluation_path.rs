@@ -0,0 +1,24 @@
+use serde::Serialize;
+
+/// One step of the evaluation of a check, as recorded in `auth_decisions.evaluation_path`.
+/// Relations are listed once their result is known, so a relation follows the ones it was
+/// computed from.
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum EvaluationStep {
+    /// `object` computed from its `rule`, in rule expression syntax.
+    Rule {
+        object: String,
+        rule: String,
+        result: bool,
+    },
+    /// `object` answered from the in-process cache or `permissions_cache`.
+    Cached { object: String, result: bool },
+    /// `object` already answered earlier in the same request.
+    Reused { object: String, result: bool },
+    /// `subject` stored in `object` itself, exactly or through a `*` wildcard of its type.
+    Direct { object: String, subject: String },
+    /// A tuple whose userset or tupleset subject led to the subject being granted the relation
+    /// the tuple is stored in.
+    Tuple { tuple: String },
+}
@@ -0,0 +1,27 @@
+//! Domain types shared by the services and repositories that are neither table rows nor
+//! request/response bodies.
+
+pub mod change;
+pub mod consistency;
+pub mod evaluation_path;
+pub mod rewrite;
+pub mod schema;
+pub mod subject_set;
+pub mod tuple;
+pub mod userset_tree;
+
+pub use change::{ChangeOperation, ChangeSet, RelationshipChange};
+pub use consistency::{Consistency, Snapshot};
+pub use evaluation_path::EvaluationStep;
+pub use rewrite::Rewrite;
+pub use schema::{
+    Definition, Expression, ExpressionKind, OrphanedTuples, Position, RelationDefinition,
+    RelationKind, RelationMigration, Schema, SchemaChanges, SchemaWarning, SchemaWriteOptions,
+    SubjectType,
+};
+pub use subject_set::SubjectSet;
+pub use tuple::{
+    ObjectRelation, Precondition, PreconditionOperation, SubjectRef, TupleFilter, TupleKey,
+    TupleUpdate, UpdateOperation, WILDCARD_SUBJECT_ID,
+};
+pub use userset_tree::{UsersetNode, UsersetTree};
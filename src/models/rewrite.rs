@@ -0,0 +1,279 @@
+use std::fmt;
+
+use crate::{
+    entities::{RelationRule, RuleType},
+    error::{HeimdallError, HeimdallResult},
+};
+
+use super::schema::Expression;
+
+/// How the subjects of a relation are computed, Zanzibar's userset rewrite.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Rewrite {
+    /// Subjects stored in tuples of the relation itself.
+    This,
+    /// Subjects of another relation of the same object.
+    ComputedUserset(String),
+    /// For every tuple of `tupleset` on the object, the subjects of `computed_relation` on the
+    /// tuple's subject. With a `target_namespace` only subjects of that type are followed.
+    TupleToUserset {
+        tupleset: String,
+        computed_relation: String,
+        target_namespace: Option<String>,
+    },
+    Union(Vec<Rewrite>),
+    Intersection(Vec<Rewrite>),
+    Exclusion {
+        base: Box<Rewrite>,
+        subtract: Box<Rewrite>,
+    },
+}
+
+impl Rewrite {
+    /// Builds the rewrite of a relation from its live rules, which are OR'ed in the order they
+    /// are given, i.e. by priority. A relation without rules holds just its own tuples.
+    ///
+    /// A rule with an `expression` is defined by it, the other columns then only summarize it.
+    pub fn from_rules(rules: &[RelationRule]) -> HeimdallResult<Self> {
+        let mut rewrites = rules
+            .iter()
+            .map(Self::from_rule)
+            .collect::<HeimdallResult<Vec<_>>>()?;
+        Ok(match rewrites.len() {
+            0 => Self::This,
+            1 => rewrites.remove(0),
+            _ => Self::Union(rewrites),
+        })
+    }
+
+    fn from_rule(rule: &RelationRule) -> HeimdallResult<Self> {
+        let children = rule
+            .child_relations
+            .as_ref()
+            .map(|children| children.0.as_slice())
+            .unwrap_or_default();
+        let computed = |children: &[String]| {
+            children
+                .iter()
+                .map(|child| Self::ComputedUserset(child.clone()))
+                .collect::<Vec<_>>()
+        };
+        let malformed = |reason: &str| {
+            HeimdallError::RuleEvaluation(format!(
+                "{} rule {} of `{}#{}` {reason}",
+                rule.rule_type, rule.id, rule.namespace_id, rule.relation_name
+            ))
+        };
+
+        if let Some(expression) = &rule.expression {
+            return Expression::parse(expression)
+                .map(|expression| expression.to_rewrite())
+                .map_err(|e| malformed(&format!("has an invalid expression: {e}")));
+        }
+
+        match rule.rule_type {
+            RuleType::Direct => Ok(Self::This),
+            RuleType::Union if !children.is_empty() => Ok(Self::Union(computed(children))),
+            RuleType::Intersection if !children.is_empty() => {
+                Ok(Self::Intersection(computed(children)))
+            }
+            RuleType::Union | RuleType::Intersection => {
+                Err(malformed("needs at least one child relation"))
+            }
+            RuleType::Exclusion => match children {
+                [base, subtract] => Ok(Self::Exclusion {
+                    base: Box::new(Self::ComputedUserset(base.clone())),
+                    subtract: Box::new(Self::ComputedUserset(subtract.clone())),
+                }),
+                [base, subtract @ ..] if !subtract.is_empty() => Ok(Self::Exclusion {
+                    base: Box::new(Self::ComputedUserset(base.clone())),
+                    subtract: Box::new(Self::Union(computed(subtract))),
+                }),
+                _ => Err(malformed(
+                    "needs a base relation followed by the relations to exclude",
+                )),
+            },
+            RuleType::TupleToUserset => match (children, &rule.ttu_relation) {
+                ([tupleset], Some(computed_relation)) => Ok(Self::TupleToUserset {
+                    tupleset: tupleset.clone(),
+                    computed_relation: computed_relation.clone(),
+                    target_namespace: rule.ttu_object_namespace.clone(),
+                }),
+                _ => Err(malformed(
+                    "needs exactly one tupleset relation in `child_relations` and a `ttu_relation`",
+                )),
+            },
+        }
+    }
+}
+
+/// Renders the rewrite in rule expression syntax, e.g. `(viewer + parent->view) - banned`.
+impl fmt::Display for Rewrite {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let join = |f: &mut fmt::Formatter<'_>, rewrites: &[Rewrite], operator: &str| {
+            f.write_str("(")?;
+            for (index, rewrite) in rewrites.iter().enumerate() {
+                if index > 0 {
+                    write!(f, " {operator} ")?;
+                }
+                write!(f, "{rewrite}")?;
+            }
+            f.write_str(")")
+        };
+        match self {
+            Self::This => f.write_str("this"),
+            Self::ComputedUserset(relation) => f.write_str(relation),
+            Self::TupleToUserset {
+                tupleset,
+                computed_relation,
+                ..
+            } => write!(f, "{tupleset}->{computed_relation}"),
+            Self::Union(rewrites) => join(f, rewrites, "+"),
+            Self::Intersection(rewrites) => join(f, rewrites, "&"),
+            Self::Exclusion { base, subtract } => write!(f, "({base} - {subtract})"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::DateTime;
+    use sqlx::types::Json;
+    use uuid::Uuid;
+
+    use super::*;
+
+    fn rule(rule_type: RuleType, children: &[&str]) -> RelationRule {
+        RelationRule {
+            id: Uuid::nil(),
+            namespace_id: "document".to_string(),
+            relation_name: "view".to_string(),
+            rule_type,
+            ttu_object_namespace: None,
+            ttu_relation: None,
+            child_relations: (!children.is_empty())
+                .then(|| Json(children.iter().map(ToString::to_string).collect())),
+            expression: None,
+            priority: 100,
+            created_at: DateTime::UNIX_EPOCH,
+            updated_at: DateTime::UNIX_EPOCH,
+            deleted_at: None,
+        }
+    }
+
+    fn computed(relation: &str) -> Rewrite {
+        Rewrite::ComputedUserset(relation.to_string())
+    }
+
+    fn error(rules: &[RelationRule]) -> String {
+        match Rewrite::from_rules(rules) {
+            Err(HeimdallError::RuleEvaluation(message)) => message,
+            other => panic!("expected a malformed rule, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn every_rule_type_builds_its_rewrite() {
+        let ttu = RelationRule {
+            ttu_relation: Some("view".to_string()),
+            ttu_object_namespace: Some("folder".to_string()),
+            ..rule(RuleType::TupleToUserset, &["parent"])
+        };
+        let cases = [
+            (rule(RuleType::Direct, &[]), Rewrite::This),
+            (
+                rule(RuleType::Union, &["viewer", "editor"]),
+                Rewrite::Union(vec![computed("viewer"), computed("editor")]),
+            ),
+            (
+                rule(RuleType::Intersection, &["viewer", "member"]),
+                Rewrite::Intersection(vec![computed("viewer"), computed("member")]),
+            ),
+            (
+                rule(RuleType::Exclusion, &["viewer", "banned"]),
+                Rewrite::Exclusion {
+                    base: Box::new(computed("viewer")),
+                    subtract: Box::new(computed("banned")),
+                },
+            ),
+            (
+                rule(RuleType::Exclusion, &["viewer", "banned", "suspended"]),
+                Rewrite::Exclusion {
+                    base: Box::new(computed("viewer")),
+                    subtract: Box::new(Rewrite::Union(vec![
+                        computed("banned"),
+                        computed("suspended"),
+                    ])),
+                },
+            ),
+            (
+                ttu,
+                Rewrite::TupleToUserset {
+                    tupleset: "parent".to_string(),
+                    computed_relation: "view".to_string(),
+                    target_namespace: Some("folder".to_string()),
+                },
+            ),
+        ];
+        for (rule, rewrite) in cases {
+            assert_eq!(Rewrite::from_rules(&[rule]).unwrap(), rewrite);
+        }
+    }
+
+    #[test]
+    fn rules_are_ored_in_the_order_given() {
+        assert_eq!(Rewrite::from_rules(&[]).unwrap(), Rewrite::This);
+        let rules = [
+            rule(RuleType::Union, &["editor"]),
+            rule(RuleType::Direct, &[]),
+        ];
+        assert_eq!(
+            Rewrite::from_rules(&rules).unwrap(),
+            Rewrite::Union(vec![
+                Rewrite::Union(vec![computed("editor")]),
+                Rewrite::This
+            ])
+        );
+    }
+
+    #[test]
+    fn an_expression_takes_precedence_over_the_columns() {
+        let rule = RelationRule {
+            expression: Some("this + editor - banned".to_string()),
+            ..rule(RuleType::Union, &["unrelated"])
+        };
+        assert_eq!(
+            Rewrite::from_rules(&[rule]).unwrap().to_string(),
+            "((this + editor) - banned)"
+        );
+    }
+
+    #[test]
+    fn malformed_rules_name_the_rule_and_the_problem() {
+        let message = error(&[rule(RuleType::Union, &[])]);
+        assert_eq!(
+            message,
+            format!(
+                "union rule {} of `document#view` needs at least one child relation",
+                Uuid::nil()
+            )
+        );
+        assert!(error(&[rule(RuleType::Intersection, &[])]).contains("at least one child"));
+        assert!(error(&[rule(RuleType::Exclusion, &["viewer"])]).contains("base relation"));
+        assert!(error(&[rule(RuleType::TupleToUserset, &["parent"])]).contains("ttu_relation"));
+        let two_tuplesets = RelationRule {
+            ttu_relation: Some("view".to_string()),
+            ..rule(RuleType::TupleToUserset, &["parent", "owner"])
+        };
+        assert!(error(&[two_tuplesets]).contains("exactly one tupleset"));
+        let invalid = RelationRule {
+            expression: Some("viewer +".to_string()),
+            ..rule(RuleType::Direct, &[])
+        };
+        assert!(error(&[invalid]).contains("has an invalid expression"));
+        // One malformed rule fails the whole relation.
+        assert!(
+            error(&[rule(RuleType::Direct, &[]), rule(RuleType::Union, &[])]).contains("union")
+        );
+    }
+}
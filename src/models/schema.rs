@@ -0,0 +1,551 @@
+//! The schema language, a readable form of the `namespaces`, `relations` and `relation_rules`
+//! tables:
+//!
+//! ```text
+//! definition user {}
+//!
+//! definition document {
+//!     relation owner: user
+//!     relation viewer: user | user:* | group#member
+//!     relation parent: folder
+//!     permission view = owner + viewer + parent->view - banned
+//! }
+//! ```
+//!
+//! A `relation` holds the subjects stored in its tuples, a `permission` is computed from the
+//! expression. `+`, `&` and `-` are union, intersection and exclusion, they bind equally
+//! strong and group from the left, so the example excludes `banned` from the whole union.
+//! `tupleset->relation` follows the tuples of `tupleset` to `relation` on their subjects and
+//! `this` stands for the permission's own tuples. Statements may end with a `;`, `//` and
+//! `/* */` comments are ignored.
+
+mod parser;
+
+use std::fmt;
+
+use crate::{
+    entities::RuleType,
+    error::{HeimdallError, HeimdallResult},
+};
+
+use super::Rewrite;
+
+/// Where a schema element starts in the source text, both counting from 1.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Position {
+    pub line: usize,
+    pub column: usize,
+}
+
+impl fmt::Display for Position {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "line {}, column {}", self.line, self.column)
+    }
+}
+
+/// A parsed schema, or one read back from the database, in which case no element has a
+/// position.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Schema {
+    pub definitions: Vec<Definition>,
+}
+
+impl Schema {
+    /// Parses schema source text, failing with [`HeimdallError::SchemaValidation`] that names
+    /// the line and column of the first syntax error.
+    ///
+    /// [`HeimdallError::SchemaValidation`]: crate::error::HeimdallError::SchemaValidation
+    pub fn parse(source: &str) -> HeimdallResult<Self> {
+        parser::parse_schema(source)
+    }
+}
+
+/// `definition name { ... }`, one namespace.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Definition {
+    pub name: String,
+    pub relations: Vec<RelationDefinition>,
+    pub position: Option<Position>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RelationDefinition {
+    pub name: String,
+    pub kind: RelationKind,
+    pub position: Option<Position>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RelationKind {
+    /// `relation name: type | ...`, subjects are stored in tuples. The allowed subject types
+    /// are informational, an empty list leaves them open.
+    Relation { subject_types: Vec<SubjectType> },
+    /// `permission name = expression`.
+    Permission(Expression),
+}
+
+/// Allowed subject of a relation: `user`, every user `user:*`, or the userset `group#member`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SubjectType {
+    pub namespace: String,
+    pub relation: Option<String>,
+    pub wildcard: bool,
+    pub position: Option<Position>,
+}
+
+impl SubjectType {
+    /// Reads back the stored form written by [`SubjectType`]'s `Display`.
+    pub fn from_stored(value: &str) -> Self {
+        let (namespace, relation, wildcard) = if let Some(namespace) = value.strip_suffix(":*") {
+            (namespace, None, true)
+        } else if let Some((namespace, relation)) = value.split_once('#') {
+            (namespace, Some(relation.to_string()), false)
+        } else {
+            (value, None, false)
+        };
+        Self {
+            namespace: namespace.to_string(),
+            relation,
+            wildcard,
+            position: None,
+        }
+    }
+}
+
+impl fmt::Display for SubjectType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.namespace)?;
+        if self.wildcard {
+            f.write_str(":*")?;
+        }
+        if let Some(relation) = &self.relation {
+            write!(f, "#{relation}")?;
+        }
+        Ok(())
+    }
+}
+
+/// Right hand side of a permission, the source form of a [`Rewrite`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Expression {
+    pub kind: ExpressionKind,
+    pub position: Option<Position>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ExpressionKind {
+    This,
+    Relation(String),
+    /// `tupleset->computed_relation`.
+    Arrow {
+        tupleset: String,
+        computed_relation: String,
+    },
+    Union(Vec<Expression>),
+    Intersection(Vec<Expression>),
+    Exclusion {
+        base: Box<Expression>,
+        subtract: Box<Expression>,
+    },
+}
+
+impl Expression {
+    /// Parses a lone expression, as stored in `relation_rules.expression`.
+    pub fn parse(source: &str) -> HeimdallResult<Self> {
+        parser::parse_expression(source)
+    }
+
+    pub fn to_rewrite(&self) -> Rewrite {
+        let all = |expressions: &[Expression]| expressions.iter().map(Self::to_rewrite).collect();
+        match &self.kind {
+            ExpressionKind::This => Rewrite::This,
+            ExpressionKind::Relation(relation) => Rewrite::ComputedUserset(relation.clone()),
+            ExpressionKind::Arrow {
+                tupleset,
+                computed_relation,
+            } => Rewrite::TupleToUserset {
+                tupleset: tupleset.clone(),
+                computed_relation: computed_relation.clone(),
+                target_namespace: None,
+            },
+            ExpressionKind::Union(children) => Rewrite::Union(all(children)),
+            ExpressionKind::Intersection(children) => Rewrite::Intersection(all(children)),
+            ExpressionKind::Exclusion { base, subtract } => Rewrite::Exclusion {
+                base: Box::new(base.to_rewrite()),
+                subtract: Box::new(subtract.to_rewrite()),
+            },
+        }
+    }
+
+    /// The expression of a stored rewrite. The schema language cannot restrict a
+    /// tuple-to-userset to one target namespace, so that restriction is dropped.
+    pub fn from_rewrite(rewrite: &Rewrite) -> Self {
+        let all = |rewrites: &[Rewrite]| rewrites.iter().map(Self::from_rewrite).collect();
+        let kind = match rewrite {
+            Rewrite::This => ExpressionKind::This,
+            Rewrite::ComputedUserset(relation) => ExpressionKind::Relation(relation.clone()),
+            Rewrite::TupleToUserset {
+                tupleset,
+                computed_relation,
+                ..
+            } => ExpressionKind::Arrow {
+                tupleset: tupleset.clone(),
+                computed_relation: computed_relation.clone(),
+            },
+            Rewrite::Union(children) => ExpressionKind::Union(all(children)),
+            Rewrite::Intersection(children) => ExpressionKind::Intersection(all(children)),
+            Rewrite::Exclusion { base, subtract } => ExpressionKind::Exclusion {
+                base: Box::new(Self::from_rewrite(base)),
+                subtract: Box::new(Self::from_rewrite(subtract)),
+            },
+        };
+        Self {
+            kind,
+            position: None,
+        }
+    }
+
+    /// `rule_type` of a rule storing this expression, after its outermost operation.
+    pub fn rule_type(&self) -> RuleType {
+        match &self.kind {
+            ExpressionKind::This => RuleType::Direct,
+            ExpressionKind::Relation(_) | ExpressionKind::Union(_) => RuleType::Union,
+            ExpressionKind::Arrow { .. } => RuleType::TupleToUserset,
+            ExpressionKind::Intersection(_) => RuleType::Intersection,
+            ExpressionKind::Exclusion { .. } => RuleType::Exclusion,
+        }
+    }
+
+    /// Relations of the permission's own namespace the expression refers to, computed ones and
+    /// tuplesets alike, in order of first appearance.
+    pub fn relations(&self) -> Vec<&str> {
+        let mut relations = Vec::new();
+        self.collect_relations(&mut relations);
+        relations
+    }
+
+    /// Every `tupleset->computed_relation` of the expression, as `(tupleset, computed_relation)`.
+    pub fn arrows(&self) -> Vec<(&str, &str)> {
+        match &self.kind {
+            ExpressionKind::This | ExpressionKind::Relation(_) => Vec::new(),
+            ExpressionKind::Arrow {
+                tupleset,
+                computed_relation,
+            } => vec![(tupleset.as_str(), computed_relation.as_str())],
+            ExpressionKind::Union(children) | ExpressionKind::Intersection(children) => {
+                children.iter().flat_map(Self::arrows).collect()
+            }
+            ExpressionKind::Exclusion { base, subtract } => {
+                let mut arrows = base.arrows();
+                arrows.extend(subtract.arrows());
+                arrows
+            }
+        }
+    }
+
+    fn collect_relations<'a>(&'a self, relations: &mut Vec<&'a str>) {
+        let mut add = |relation: &'a str| {
+            if !relations.contains(&relation) {
+                relations.push(relation);
+            }
+        };
+        match &self.kind {
+            ExpressionKind::This => {}
+            ExpressionKind::Relation(relation) => add(relation),
+            ExpressionKind::Arrow { tupleset, .. } => add(tupleset),
+            ExpressionKind::Union(children) | ExpressionKind::Intersection(children) => {
+                for child in children {
+                    child.collect_relations(relations);
+                }
+            }
+            ExpressionKind::Exclusion { base, subtract } => {
+                base.collect_relations(relations);
+                subtract.collect_relations(relations);
+            }
+        }
+    }
+
+    fn is_compound(&self) -> bool {
+        matches!(
+            self.kind,
+            ExpressionKind::Union(_)
+                | ExpressionKind::Intersection(_)
+                | ExpressionKind::Exclusion { .. }
+        )
+    }
+
+    /// Writes an operand that follows an operator. Operators group from the left, so only
+    /// compound right hand operands need parentheses.
+    fn fmt_right_operand(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.is_compound() {
+            write!(f, "({self})")
+        } else {
+            write!(f, "{self}")
+        }
+    }
+}
+
+/// Renders the expression with as few parentheses as it can be parsed back from.
+impl fmt::Display for Expression {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let join = |f: &mut fmt::Formatter<'_>, children: &[Expression], operator: &str| {
+            for (index, child) in children.iter().enumerate() {
+                if index == 0 {
+                    write!(f, "{child}")?;
+                } else {
+                    write!(f, " {operator} ")?;
+                    child.fmt_right_operand(f)?;
+                }
+            }
+            Ok(())
+        };
+        match &self.kind {
+            ExpressionKind::This => f.write_str("this"),
+            ExpressionKind::Relation(relation) => f.write_str(relation),
+            ExpressionKind::Arrow {
+                tupleset,
+                computed_relation,
+            } => write!(f, "{tupleset}->{computed_relation}"),
+            ExpressionKind::Union(children) => join(f, children, "+"),
+            ExpressionKind::Intersection(children) => join(f, children, "&"),
+            ExpressionKind::Exclusion { base, subtract } => {
+                write!(f, "{base} - ")?;
+                subtract.fmt_right_operand(f)
+            }
+        }
+    }
+}
+
+/// Pretty-prints the schema in canonical layout, one statement per line.
+impl fmt::Display for Schema {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (index, definition) in self.definitions.iter().enumerate() {
+            if index > 0 {
+                writeln!(f)?;
+            }
+            write!(f, "{definition}")?;
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for Definition {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.relations.is_empty() {
+            return writeln!(f, "definition {} {{}}", self.name);
+        }
+        writeln!(f, "definition {} {{", self.name)?;
+        for relation in &self.relations {
+            writeln!(f, "    {relation}")?;
+        }
+        writeln!(f, "}}")
+    }
+}
+
+impl fmt::Display for RelationDefinition {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.kind {
+            RelationKind::Relation { subject_types } => {
+                write!(f, "relation {}", self.name)?;
+                for (index, subject_type) in subject_types.iter().enumerate() {
+                    f.write_str(if index == 0 { ": " } else { " | " })?;
+                    write!(f, "{subject_type}")?;
+                }
+                Ok(())
+            }
+            RelationKind::Permission(expression) => {
+                write!(f, "permission {} = {expression}", self.name)
+            }
+        }
+    }
+}
+
+/// What applying a schema changed, relations are named `namespace#relation`.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SchemaChanges {
+    pub created_namespaces: Vec<String>,
+    pub deleted_namespaces: Vec<String>,
+    pub created_relations: Vec<String>,
+    pub updated_relations: Vec<String>,
+    pub deleted_relations: Vec<String>,
+    /// Removed relations and namespaces that tuples still used, and what happened to them.
+    pub orphaned: Vec<OrphanedTuples>,
+    pub deleted_tuples: u64,
+    pub migrated_tuples: u64,
+    /// Zookie of the version the changes were committed at, if there were any.
+    pub zookie: Option<String>,
+    /// Likely mistakes found in the schema, which do not count as changes.
+    pub warnings: Vec<SchemaWarning>,
+}
+
+impl SchemaChanges {
+    pub fn is_empty(&self) -> bool {
+        Self {
+            warnings: Vec::new(),
+            ..self.clone()
+        } == Self::default()
+    }
+}
+
+/// A likely mistake in a schema that does not keep it from being applied.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SchemaWarning {
+    pub message: String,
+    pub position: Option<Position>,
+}
+
+impl fmt::Display for SchemaWarning {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.message)?;
+        if let Some(position) = self.position {
+            write!(f, " at {position}")?;
+        }
+        Ok(())
+    }
+}
+
+/// How a schema write treats tuples left behind by the relations and namespaces it removes.
+#[derive(Debug, Clone, Default)]
+pub struct SchemaWriteOptions {
+    /// Delete tuples of removed relations and namespaces that are not migrated.
+    pub force: bool,
+    pub migrations: Vec<RelationMigration>,
+    /// Plan and check the write, then roll it back.
+    pub dry_run: bool,
+}
+
+/// Moves the tuples of a removed relation to another relation of the same namespace, along
+/// with tuples having its usersets as subject.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RelationMigration {
+    pub namespace: String,
+    pub from: String,
+    pub to: String,
+}
+
+impl RelationMigration {
+    /// Migration of `from`, written `namespace#relation`, to the relation `to` of the same
+    /// namespace.
+    pub fn parse(from: &str, to: &str) -> HeimdallResult<Self> {
+        match from.split_once('#') {
+            Some((namespace, relation)) if !namespace.is_empty() && !relation.is_empty() => {
+                Ok(Self {
+                    namespace: namespace.to_string(),
+                    from: relation.to_string(),
+                    to: to.to_string(),
+                })
+            }
+            _ => Err(HeimdallError::InvalidArgument(format!(
+                "cannot migrate `{from}`: expected `namespace#relation`"
+            ))),
+        }
+    }
+}
+
+/// Use of a relation, or with no `relation` of a namespace as subject type, that a schema
+/// removes.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OrphanedTuples {
+    pub namespace: String,
+    pub relation: Option<String>,
+    /// Tuples of the relation.
+    pub tuple_count: i64,
+    /// Tuples whose subject is a userset of the relation, or a subject of the namespace.
+    pub subject_tuple_count: i64,
+    /// Permissions of the schema still referring to the relation, `namespace#relation`. These
+    /// cannot be forced.
+    pub referenced_by: Vec<String>,
+    /// Relation the tuples are moved to.
+    pub migrated_to: Option<String>,
+}
+
+impl OrphanedTuples {
+    /// `namespace#relation`, or the namespace alone.
+    pub fn name(&self) -> String {
+        match &self.relation {
+            Some(relation) => format!("{}#{relation}", self.namespace),
+            None => self.namespace.clone(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Prints the canonical form of `source` and checks that parsing it back prints the same.
+    fn canonical(source: &str) -> String {
+        let printed = Schema::parse(source).unwrap().to_string();
+        assert_eq!(Schema::parse(&printed).unwrap().to_string(), printed);
+        printed
+    }
+
+    #[test]
+    fn prints_schemas_in_canonical_layout() {
+        let source = "definition user{}  definition group { relation member : user|group#member; }\n\
+                      // documents\n\
+                      definition document {\n\
+                      relation viewer: user | user:*\n\
+                      relation parent: group\n\
+                      permission view = (this + viewer) + parent->member - (viewer & parent->member)\n\
+                      }";
+        assert_eq!(
+            canonical(source),
+            "definition user {}\n\
+             \n\
+             definition group {\n    \
+                 relation member: user | group#member\n\
+             }\n\
+             \n\
+             definition document {\n    \
+                 relation viewer: user | user:*\n    \
+                 relation parent: group\n    \
+                 permission view = this + viewer + parent->member - (viewer & parent->member)\n\
+             }\n"
+        );
+    }
+
+    #[test]
+    fn expressions_print_with_the_parentheses_they_need() {
+        for (source, printed) in [
+            ("a - (b - c)", "a - (b - c)"),
+            ("(a - b) - c", "a - b - c"),
+            ("(a + b) & c", "a + b & c"),
+            ("a + (b + c)", "a + (b + c)"),
+            ("a & (b + c->d)", "a & (b + c->d)"),
+            ("((this))", "this"),
+        ] {
+            let expression = Expression::parse(source).unwrap();
+            assert_eq!(expression.to_string(), printed, "{source}");
+            let reparsed = Expression::parse(printed).unwrap();
+            assert_eq!(reparsed.to_rewrite(), expression.to_rewrite(), "{source}");
+        }
+    }
+
+    #[test]
+    fn stored_rewrites_survive_printing() {
+        let rewrite = Rewrite::Exclusion {
+            base: Box::new(Rewrite::Union(vec![
+                Rewrite::This,
+                Rewrite::Intersection(vec![
+                    Rewrite::ComputedUserset("editor".to_string()),
+                    Rewrite::TupleToUserset {
+                        tupleset: "parent".to_string(),
+                        computed_relation: "view".to_string(),
+                        target_namespace: None,
+                    },
+                ]),
+            ])),
+            subtract: Box::new(Rewrite::Exclusion {
+                base: Box::new(Rewrite::ComputedUserset("banned".to_string())),
+                subtract: Box::new(Rewrite::ComputedUserset("pardoned".to_string())),
+            }),
+        };
+        let printed = Expression::from_rewrite(&rewrite).to_string();
+        assert_eq!(
+            printed,
+            "this + (editor & parent->view) - (banned - pardoned)"
+        );
+        assert_eq!(Expression::parse(&printed).unwrap().to_rewrite(), rewrite);
+    }
+}
@@ -0,0 +1,652 @@
+//! Hand-written lexer and recursive descent parser of the schema language.
+
+use std::{fmt, iter::Peekable, str::CharIndices};
+
+use crate::error::{HeimdallError, HeimdallResult};
+
+use super::{
+    Definition, Expression, ExpressionKind, Position, RelationDefinition, RelationKind, Schema,
+    SubjectType,
+};
+
+/// Deepest an expression may nest, counting parentheses and changes of operator. Everything
+/// walking expressions and rewrites recurses, so this keeps hostile input from exhausting the
+/// stack, while staying far beyond what any readable permission needs.
+const MAX_EXPRESSION_DEPTH: usize = 100;
+
+pub(super) fn parse_schema(source: &str) -> HeimdallResult<Schema> {
+    let mut parser = Parser::new(source)?;
+    let mut definitions = Vec::new();
+    while parser.peek() != &Token::Eof {
+        definitions.push(parser.definition()?);
+    }
+    Ok(Schema { definitions })
+}
+
+pub(super) fn parse_expression(source: &str) -> HeimdallResult<Expression> {
+    let mut parser = Parser::new(source)?;
+    let expression = parser.expression()?;
+    parser.expect(&Token::Eof)?;
+    Ok(expression)
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Token {
+    Identifier(String),
+    LeftBrace,
+    RightBrace,
+    LeftParen,
+    RightParen,
+    Colon,
+    Semicolon,
+    Pipe,
+    Hash,
+    Star,
+    Equals,
+    Plus,
+    Ampersand,
+    Minus,
+    Arrow,
+    Eof,
+}
+
+impl fmt::Display for Token {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let symbol = match self {
+            Self::Identifier(name) => return write!(f, "`{name}`"),
+            Self::Eof => return f.write_str("end of input"),
+            Self::LeftBrace => "{",
+            Self::RightBrace => "}",
+            Self::LeftParen => "(",
+            Self::RightParen => ")",
+            Self::Colon => ":",
+            Self::Semicolon => ";",
+            Self::Pipe => "|",
+            Self::Hash => "#",
+            Self::Star => "*",
+            Self::Equals => "=",
+            Self::Plus => "+",
+            Self::Ampersand => "&",
+            Self::Minus => "-",
+            Self::Arrow => "->",
+        };
+        write!(f, "`{symbol}`")
+    }
+}
+
+fn syntax_error(position: Position, message: impl fmt::Display) -> HeimdallError {
+    HeimdallError::SchemaValidation(format!("{message} at {position}"))
+}
+
+fn tokenize(source: &str) -> HeimdallResult<Vec<(Token, Position)>> {
+    let mut lexer = Lexer {
+        chars: source.char_indices().peekable(),
+        source,
+        line: 1,
+        column: 1,
+    };
+    let mut tokens = Vec::new();
+    loop {
+        let (token, position) = lexer.next_token()?;
+        let done = token == Token::Eof;
+        tokens.push((token, position));
+        if done {
+            return Ok(tokens);
+        }
+    }
+}
+
+struct Lexer<'a> {
+    chars: Peekable<CharIndices<'a>>,
+    source: &'a str,
+    line: usize,
+    column: usize,
+}
+
+impl Lexer<'_> {
+    fn position(&self) -> Position {
+        Position {
+            line: self.line,
+            column: self.column,
+        }
+    }
+
+    fn bump(&mut self) -> Option<char> {
+        let (_, c) = self.chars.next()?;
+        if c == '\n' {
+            self.line += 1;
+            self.column = 1;
+        } else {
+            self.column += 1;
+        }
+        Some(c)
+    }
+
+    fn peek_char(&mut self) -> Option<char> {
+        self.chars.peek().map(|&(_, c)| c)
+    }
+
+    /// Skips whitespace and comments.
+    fn skip_trivia(&mut self) -> HeimdallResult<()> {
+        loop {
+            match self.peek_char() {
+                Some(c) if c.is_whitespace() => {
+                    self.bump();
+                }
+                Some('/') => {
+                    let start = self.position();
+                    let mut lookahead = self.chars.clone();
+                    lookahead.next();
+                    match lookahead.next().map(|(_, c)| c) {
+                        Some('/') => {
+                            while self.peek_char().is_some_and(|c| c != '\n') {
+                                self.bump();
+                            }
+                        }
+                        Some('*') => {
+                            self.bump();
+                            self.bump();
+                            let mut previous = None;
+                            loop {
+                                match self.bump() {
+                                    Some('/') if previous == Some('*') => break,
+                                    Some(c) => previous = Some(c),
+                                    None => {
+                                        return Err(syntax_error(start, "unterminated comment"));
+                                    }
+                                }
+                            }
+                        }
+                        _ => return Ok(()),
+                    }
+                }
+                _ => return Ok(()),
+            }
+        }
+    }
+
+    fn next_token(&mut self) -> HeimdallResult<(Token, Position)> {
+        self.skip_trivia()?;
+        let position = self.position();
+        let Some(&(start, c)) = self.chars.peek() else {
+            return Ok((Token::Eof, position));
+        };
+        if c.is_ascii_alphabetic() || c == '_' {
+            let mut end = start;
+            while let Some(&(index, c)) = self.chars.peek() {
+                if !(c.is_ascii_alphanumeric() || c == '_') {
+                    break;
+                }
+                end = index + c.len_utf8();
+                self.bump();
+            }
+            return Ok((
+                Token::Identifier(self.source[start..end].to_string()),
+                position,
+            ));
+        }
+
+        self.bump();
+        let token = match c {
+            '{' => Token::LeftBrace,
+            '}' => Token::RightBrace,
+            '(' => Token::LeftParen,
+            ')' => Token::RightParen,
+            ':' => Token::Colon,
+            ';' => Token::Semicolon,
+            '|' => Token::Pipe,
+            '#' => Token::Hash,
+            '*' => Token::Star,
+            '=' => Token::Equals,
+            '+' => Token::Plus,
+            '&' => Token::Ampersand,
+            '-' if self.peek_char() == Some('>') => {
+                self.bump();
+                Token::Arrow
+            }
+            '-' => Token::Minus,
+            _ => {
+                return Err(syntax_error(
+                    position,
+                    format!("unexpected character `{c}`"),
+                ));
+            }
+        };
+        Ok((token, position))
+    }
+}
+
+struct Parser {
+    tokens: Vec<(Token, Position)>,
+    next: usize,
+    /// Nesting of the expression being parsed, see [`MAX_EXPRESSION_DEPTH`].
+    depth: usize,
+}
+
+impl Parser {
+    fn new(source: &str) -> HeimdallResult<Self> {
+        Ok(Self {
+            tokens: tokenize(source)?,
+            next: 0,
+            depth: 0,
+        })
+    }
+
+    fn peek(&self) -> &Token {
+        &self.tokens[self.next].0
+    }
+
+    fn position(&self) -> Position {
+        self.tokens[self.next].1
+    }
+
+    fn advance(&mut self) -> Token {
+        let token = self.tokens[self.next].0.clone();
+        // The trailing `Eof` is never consumed.
+        if token != Token::Eof {
+            self.next += 1;
+        }
+        token
+    }
+
+    fn eat(&mut self, token: &Token) -> bool {
+        if self.peek() == token {
+            self.advance();
+            true
+        } else {
+            false
+        }
+    }
+
+    fn unexpected(&self, expected: &str) -> HeimdallError {
+        syntax_error(
+            self.position(),
+            format!("expected {expected}, found {}", self.peek()),
+        )
+    }
+
+    fn expect(&mut self, token: &Token) -> HeimdallResult<()> {
+        if self.eat(token) {
+            Ok(())
+        } else {
+            Err(self.unexpected(&token.to_string()))
+        }
+    }
+
+    /// Enters one more level of expression nesting, failing at `position` past
+    /// [`MAX_EXPRESSION_DEPTH`].
+    fn nest(&mut self, position: Position) -> HeimdallResult<()> {
+        self.depth += 1;
+        if self.depth > MAX_EXPRESSION_DEPTH {
+            return Err(syntax_error(
+                position,
+                format!("expression nested deeper than {MAX_EXPRESSION_DEPTH} levels"),
+            ));
+        }
+        Ok(())
+    }
+
+    fn keyword(&mut self, keyword: &str) -> bool {
+        if matches!(self.peek(), Token::Identifier(name) if name == keyword) {
+            self.advance();
+            true
+        } else {
+            false
+        }
+    }
+
+    /// A name, which must not be one of the keywords.
+    fn identifier(&mut self, what: &str) -> HeimdallResult<(String, Position)> {
+        let position = self.position();
+        match self.peek() {
+            Token::Identifier(name) if is_keyword(name) => Err(syntax_error(
+                position,
+                format!("`{name}` is a keyword and cannot be used as {what}"),
+            )),
+            Token::Identifier(_) => match self.advance() {
+                Token::Identifier(name) => Ok((name, position)),
+                _ => unreachable!(),
+            },
+            _ => Err(self.unexpected(what)),
+        }
+    }
+
+    fn definition(&mut self) -> HeimdallResult<Definition> {
+        let position = self.position();
+        if !self.keyword("definition") {
+            return Err(self.unexpected("`definition`"));
+        }
+        let (name, _) = self.identifier("a definition name")?;
+        self.expect(&Token::LeftBrace)?;
+
+        let mut relations = Vec::new();
+        while !self.eat(&Token::RightBrace) {
+            relations.push(self.relation()?);
+            while self.eat(&Token::Semicolon) {}
+        }
+        Ok(Definition {
+            name,
+            relations,
+            position: Some(position),
+        })
+    }
+
+    fn relation(&mut self) -> HeimdallResult<RelationDefinition> {
+        let position = self.position();
+        let (name, kind) = if self.keyword("relation") {
+            let (name, _) = self.identifier("a relation name")?;
+            let mut subject_types = Vec::new();
+            if self.eat(&Token::Colon) {
+                subject_types.push(self.subject_type()?);
+                while self.eat(&Token::Pipe) {
+                    subject_types.push(self.subject_type()?);
+                }
+            }
+            (name, RelationKind::Relation { subject_types })
+        } else if self.keyword("permission") {
+            let (name, _) = self.identifier("a permission name")?;
+            self.expect(&Token::Equals)?;
+            (name, RelationKind::Permission(self.expression()?))
+        } else {
+            return Err(self.unexpected("`relation`, `permission` or `}`"));
+        };
+        Ok(RelationDefinition {
+            name,
+            kind,
+            position: Some(position),
+        })
+    }
+
+    fn subject_type(&mut self) -> HeimdallResult<SubjectType> {
+        let (namespace, position) = self.identifier("a subject type")?;
+        let mut subject_type = SubjectType {
+            namespace,
+            relation: None,
+            wildcard: false,
+            position: Some(position),
+        };
+        if self.eat(&Token::Colon) {
+            self.expect(&Token::Star)?;
+            subject_type.wildcard = true;
+        } else if self.eat(&Token::Hash) {
+            subject_type.relation = Some(self.identifier("a relation name")?.0);
+        }
+        Ok(subject_type)
+    }
+
+    /// Terms joined by `+`, `&` and `-`, which bind equally strong and group from the left.
+    /// Runs of the same set operation are collected into one node, unless parenthesized.
+    fn expression(&mut self) -> HeimdallResult<Expression> {
+        let depth = self.depth;
+        let mut left = self.term()?;
+        let mut collecting = false;
+        loop {
+            let operator = self.peek().clone();
+            if !matches!(operator, Token::Plus | Token::Ampersand | Token::Minus) {
+                self.depth = depth;
+                return Ok(left);
+            }
+            let operator_position = self.position();
+            self.advance();
+            let right = self.term()?;
+
+            let position = left.position;
+            left = match (operator, left.kind) {
+                (Token::Plus, ExpressionKind::Union(mut children)) if collecting => {
+                    children.push(right);
+                    Expression {
+                        kind: ExpressionKind::Union(children),
+                        position,
+                    }
+                }
+                (Token::Ampersand, ExpressionKind::Intersection(mut children)) if collecting => {
+                    children.push(right);
+                    Expression {
+                        kind: ExpressionKind::Intersection(children),
+                        position,
+                    }
+                }
+                (operator, kind) => {
+                    // Every operator that does not extend a run wraps the expression so far.
+                    self.nest(operator_position)?;
+                    let left = Expression { kind, position };
+                    let kind = match operator {
+                        Token::Plus => ExpressionKind::Union(vec![left, right]),
+                        Token::Ampersand => ExpressionKind::Intersection(vec![left, right]),
+                        _ => ExpressionKind::Exclusion {
+                            base: Box::new(left),
+                            subtract: Box::new(right),
+                        },
+                    };
+                    Expression { kind, position }
+                }
+            };
+            collecting = true;
+        }
+    }
+
+    fn term(&mut self) -> HeimdallResult<Expression> {
+        let position = self.position();
+        if self.eat(&Token::LeftParen) {
+            self.nest(position)?;
+            let expression = self.expression()?;
+            self.expect(&Token::RightParen)?;
+            self.depth -= 1;
+            return Ok(expression);
+        }
+        if self.keyword("this") {
+            return Ok(Expression {
+                kind: ExpressionKind::This,
+                position: Some(position),
+            });
+        }
+
+        let (relation, _) = self.identifier("a relation name, `this` or `(`")?;
+        let kind = if self.eat(&Token::Arrow) {
+            ExpressionKind::Arrow {
+                tupleset: relation,
+                computed_relation: self.identifier("a relation name")?.0,
+            }
+        } else {
+            ExpressionKind::Relation(relation)
+        };
+        Ok(Expression {
+            kind,
+            position: Some(position),
+        })
+    }
+}
+
+fn is_keyword(name: &str) -> bool {
+    matches!(name, "definition" | "relation" | "permission" | "this")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn relation(name: &str) -> ExpressionKind {
+        ExpressionKind::Relation(name.to_string())
+    }
+
+    fn kinds(expressions: &[Expression]) -> Vec<ExpressionKind> {
+        expressions.iter().map(|e| e.kind.clone()).collect()
+    }
+
+    fn syntax_error_message(result: HeimdallResult<impl fmt::Debug>) -> String {
+        match result {
+            Err(HeimdallError::SchemaValidation(message)) => message,
+            other => panic!("expected a schema validation error, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn parses_definitions_relations_and_subject_types() {
+        let schema = parse_schema(
+            "// users\ndefinition user {}\n\ndefinition document {\n    relation viewer: user | \
+             user:* | group#member;\n    /* computed */ permission view = viewer\n}\n",
+        )
+        .unwrap();
+
+        assert_eq!(schema.definitions.len(), 2);
+        let user = &schema.definitions[0];
+        assert_eq!(user.name, "user");
+        assert!(user.relations.is_empty());
+        assert_eq!(user.position, Some(Position { line: 2, column: 1 }));
+
+        let document = &schema.definitions[1];
+        let viewer = &document.relations[0];
+        assert_eq!(viewer.position, Some(Position { line: 5, column: 5 }));
+        let RelationKind::Relation { subject_types } = &viewer.kind else {
+            panic!("expected a relation, got {:?}", viewer.kind);
+        };
+        let subject_types: Vec<String> = subject_types.iter().map(|t| t.to_string()).collect();
+        assert_eq!(subject_types, ["user", "user:*", "group#member"]);
+
+        let view = &document.relations[1];
+        assert_eq!(view.name, "view");
+        assert_eq!(
+            view.position,
+            Some(Position {
+                line: 6,
+                column: 20
+            })
+        );
+        assert!(matches!(&view.kind, RelationKind::Permission(e) if e.kind == relation("viewer")));
+    }
+
+    #[test]
+    fn operators_group_from_the_left_and_collect_runs() {
+        let expression = parse_expression("owner + viewer + parent->view - banned").unwrap();
+        let ExpressionKind::Exclusion { base, subtract } = expression.kind else {
+            panic!("expected an exclusion, got {:?}", expression.kind);
+        };
+        assert_eq!(subtract.kind, relation("banned"));
+        let ExpressionKind::Union(children) = base.kind else {
+            panic!("expected a union, got {:?}", base.kind);
+        };
+        assert_eq!(
+            kinds(&children),
+            [
+                relation("owner"),
+                relation("viewer"),
+                ExpressionKind::Arrow {
+                    tupleset: "parent".to_string(),
+                    computed_relation: "view".to_string(),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn parentheses_keep_runs_apart() {
+        let expression = parse_expression("(a + b) + c & this").unwrap();
+        let ExpressionKind::Intersection(children) = expression.kind else {
+            panic!("expected an intersection, got {:?}", expression.kind);
+        };
+        assert_eq!(children[1].kind, ExpressionKind::This);
+        let ExpressionKind::Union(union) = &children[0].kind else {
+            panic!("expected a union, got {:?}", children[0].kind);
+        };
+        assert_eq!(union[1].kind, relation("c"));
+        assert_eq!(
+            union[0].kind,
+            ExpressionKind::Union(vec![
+                Expression {
+                    kind: relation("a"),
+                    position: Some(Position { line: 1, column: 2 }),
+                },
+                Expression {
+                    kind: relation("b"),
+                    position: Some(Position { line: 1, column: 6 }),
+                },
+            ])
+        );
+    }
+
+    #[test]
+    fn syntax_errors_name_line_and_column() {
+        let message =
+            syntax_error_message(parse_schema("definition doc {\n    relation : user\n}"));
+        assert_eq!(
+            message,
+            "expected a relation name, found `:` at line 2, column 14"
+        );
+
+        let message = syntax_error_message(parse_schema("definition doc { permission p = a % b }"));
+        assert_eq!(message, "unexpected character `%` at line 1, column 35");
+
+        let message = syntax_error_message(parse_schema("definition this {}"));
+        assert_eq!(
+            message,
+            "`this` is a keyword and cannot be used as a definition name at line 1, column 12"
+        );
+
+        let message = syntax_error_message(parse_schema("definition doc {} /* open"));
+        assert_eq!(message, "unterminated comment at line 1, column 19");
+
+        let message = syntax_error_message(parse_expression("(a + b"));
+        assert_eq!(
+            message,
+            "expected `)`, found end of input at line 1, column 7"
+        );
+    }
+
+    #[test]
+    fn rejects_deeply_nested_parentheses() {
+        let source = format!("{}a{}", "(".repeat(2000), ")".repeat(2000));
+        let message = syntax_error_message(parse_expression(&source));
+        assert_eq!(
+            message,
+            format!(
+                "expression nested deeper than {MAX_EXPRESSION_DEPTH} levels at line 1, column {}",
+                MAX_EXPRESSION_DEPTH + 1
+            )
+        );
+
+        let source = format!(
+            "{}a{}",
+            "(".repeat(MAX_EXPRESSION_DEPTH),
+            ")".repeat(MAX_EXPRESSION_DEPTH)
+        );
+        assert_eq!(parse_expression(&source).unwrap().kind, relation("a"));
+    }
+
+    #[test]
+    fn rejects_long_chains_of_alternating_operators() {
+        let source = format!("a{}", " - b + c".repeat(2000));
+        let message = syntax_error_message(parse_expression(&source));
+        assert!(
+            message.starts_with(&format!(
+                "expression nested deeper than {MAX_EXPRESSION_DEPTH} levels"
+            )),
+            "{message}"
+        );
+
+        // A run of the same operation is one node, however long.
+        let source = format!("a{}", " + b".repeat(2000));
+        let ExpressionKind::Union(children) = parse_expression(&source).unwrap().kind else {
+            panic!("expected a union");
+        };
+        assert_eq!(children.len(), 2001);
+    }
+
+    #[test]
+    fn depth_is_counted_per_permission() {
+        let permission = format!(
+            "permission p{{}} = {}a{}",
+            "(".repeat(MAX_EXPRESSION_DEPTH),
+            ")".repeat(MAX_EXPRESSION_DEPTH)
+        );
+        let relations: Vec<String> = (0..3)
+            .map(|i| permission.replace("{}", &i.to_string()))
+            .collect();
+        let source = format!("definition doc {{\n{}\n}}", relations.join("\n"));
+        assert_eq!(
+            parse_schema(&source).unwrap().definitions[0]
+                .relations
+                .len(),
+            3
+        );
+    }
+}
@@ -0,0 +1,202 @@
+use std::collections::BTreeSet;
+
+/// Subjects of one type holding a relation, closed under the set operations of rewrites.
+///
+/// Members are the explicitly named `subject_ids`, plus every subject not in
+/// `wildcard_exclusions` if a `*` wildcard applies. Wildcard exclusions never name an explicit
+/// member.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SubjectSet {
+    pub subject_ids: BTreeSet<String>,
+    /// Set when a `*` wildcard applies, holding the subjects it does not cover.
+    pub wildcard_exclusions: Option<BTreeSet<String>>,
+    /// Subjects an exclusion removed on the way, reported alongside the members.
+    pub excluded: BTreeSet<String>,
+}
+
+impl SubjectSet {
+    pub fn single(subject_id: String) -> Self {
+        Self {
+            subject_ids: BTreeSet::from([subject_id]),
+            ..Self::default()
+        }
+    }
+
+    pub fn wildcard() -> Self {
+        Self {
+            wildcard_exclusions: Some(BTreeSet::new()),
+            ..Self::default()
+        }
+    }
+
+    pub fn contains(&self, subject_id: &str) -> bool {
+        self.subject_ids.contains(subject_id)
+            || self
+                .wildcard_exclusions
+                .as_ref()
+                .is_some_and(|exclusions| !exclusions.contains(subject_id))
+    }
+
+    /// Subjects an exclusion removed or the wildcard does not cover, that are not members.
+    pub fn excluded_subject_ids(&self) -> BTreeSet<String> {
+        self.excluded
+            .iter()
+            .chain(self.wildcard_exclusions.iter().flatten())
+            .filter(|subject_id| !self.contains(subject_id))
+            .cloned()
+            .collect()
+    }
+
+    pub fn union(mut self, other: Self) -> Self {
+        let wildcard_exclusions = match (self.wildcard_exclusions.take(), other.wildcard_exclusions)
+        {
+            (Some(left), Some(right)) => Some(left.intersection(&right).cloned().collect()),
+            (Some(exclusions), None) | (None, Some(exclusions)) => Some(exclusions),
+            (None, None) => None,
+        };
+        self.subject_ids.extend(other.subject_ids);
+        self.excluded.extend(other.excluded);
+        Self {
+            wildcard_exclusions,
+            ..self
+        }
+        .normalized()
+    }
+
+    pub fn intersection(self, other: Self) -> Self {
+        let subject_ids = self
+            .subject_ids
+            .iter()
+            .chain(&other.subject_ids)
+            .filter(|subject_id| self.contains(subject_id) && other.contains(subject_id))
+            .cloned()
+            .collect();
+        let wildcard_exclusions = match (&self.wildcard_exclusions, &other.wildcard_exclusions) {
+            (Some(left), Some(right)) => Some(left.union(right).cloned().collect()),
+            _ => None,
+        };
+        Self {
+            subject_ids,
+            wildcard_exclusions,
+            excluded: self.excluded.union(&other.excluded).cloned().collect(),
+        }
+        .normalized()
+    }
+
+    /// Members of `self` that are not members of `subtract`.
+    pub fn exclusion(self, subtract: Self) -> Self {
+        let (mut subject_ids, mut excluded): (BTreeSet<_>, BTreeSet<_>) = self
+            .subject_ids
+            .iter()
+            .cloned()
+            .partition(|subject_id| !subtract.contains(subject_id));
+        excluded.extend(self.excluded);
+
+        let wildcard_exclusions = match (self.wildcard_exclusions, subtract.wildcard_exclusions) {
+            // Everyone outside `left` minus everyone outside `right` is `right` without `left`.
+            (Some(left), Some(right)) => {
+                subject_ids.extend(right.difference(&left).cloned());
+                None
+            }
+            (Some(mut exclusions), None) => {
+                excluded.extend(subtract.subject_ids.difference(&exclusions).cloned());
+                exclusions.extend(subtract.subject_ids);
+                Some(exclusions)
+            }
+            (None, _) => None,
+        };
+        Self {
+            subject_ids,
+            wildcard_exclusions,
+            excluded,
+        }
+        .normalized()
+    }
+
+    fn normalized(mut self) -> Self {
+        if let Some(exclusions) = &mut self.wildcard_exclusions {
+            exclusions.retain(|subject_id| !self.subject_ids.contains(subject_id));
+        }
+        self
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ids(subject_ids: &[&str]) -> BTreeSet<String> {
+        subject_ids.iter().map(ToString::to_string).collect()
+    }
+
+    fn explicit(subject_ids: &[&str]) -> SubjectSet {
+        SubjectSet {
+            subject_ids: ids(subject_ids),
+            ..SubjectSet::default()
+        }
+    }
+
+    fn wildcard_except(subject_ids: &[&str]) -> SubjectSet {
+        SubjectSet::wildcard().exclusion(explicit(subject_ids))
+    }
+
+    #[test]
+    fn wildcard_minus_explicit_excludes_the_explicit_subjects() {
+        let subjects = wildcard_except(&["bob"]);
+        assert!(subjects.contains("alice"));
+        assert!(!subjects.contains("bob"));
+        assert_eq!(subjects.wildcard_exclusions, Some(ids(&["bob"])));
+        assert_eq!(subjects.excluded_subject_ids(), ids(&["bob"]));
+    }
+
+    #[test]
+    fn wildcard_minus_wildcard_leaves_what_only_the_subtrahend_excludes() {
+        assert_eq!(
+            SubjectSet::wildcard().exclusion(SubjectSet::wildcard()),
+            SubjectSet::default()
+        );
+
+        let subjects = wildcard_except(&["bob"]).exclusion(wildcard_except(&["bob", "carol"]));
+        assert_eq!(subjects.subject_ids, ids(&["carol"]));
+        assert_eq!(subjects.wildcard_exclusions, None);
+        assert!(!subjects.contains("alice"));
+        assert_eq!(subjects.excluded_subject_ids(), ids(&["bob"]));
+    }
+
+    #[test]
+    fn explicit_intersected_with_wildcard_keeps_the_covered_subjects() {
+        let subjects = explicit(&["alice", "bob"]).intersection(wildcard_except(&["bob"]));
+        assert_eq!(subjects.subject_ids, ids(&["alice"]));
+        assert_eq!(subjects.wildcard_exclusions, None);
+        assert_eq!(subjects.excluded_subject_ids(), ids(&["bob"]));
+
+        let subjects = wildcard_except(&["bob"]).intersection(wildcard_except(&["carol"]));
+        assert_eq!(subjects.wildcard_exclusions, Some(ids(&["bob", "carol"])));
+        assert!(subjects.contains("alice"));
+    }
+
+    #[test]
+    fn union_of_wildcards_excludes_only_what_both_exclude() {
+        let subjects = wildcard_except(&["bob", "carol"]).union(wildcard_except(&["carol"]));
+        assert_eq!(subjects.wildcard_exclusions, Some(ids(&["carol"])));
+        assert!(subjects.contains("bob"));
+        assert_eq!(subjects.excluded_subject_ids(), ids(&["carol"]));
+    }
+
+    #[test]
+    fn excluded_subject_ids_leave_out_members() {
+        let subjects = explicit(&["alice", "bob"]).exclusion(explicit(&["bob"]));
+        assert_eq!(subjects.subject_ids, ids(&["alice"]));
+        assert_eq!(subjects.excluded_subject_ids(), ids(&["bob"]));
+
+        // Granted again on another branch, `bob` is a member and no longer reported.
+        let subjects = subjects.union(explicit(&["bob"]));
+        assert!(subjects.contains("bob"));
+        assert!(subjects.excluded_subject_ids().is_empty());
+
+        // Explicit members are never wildcard exclusions.
+        let subjects = wildcard_except(&["bob"]).union(explicit(&["bob"]));
+        assert_eq!(subjects.wildcard_exclusions, Some(BTreeSet::new()));
+        assert!(subjects.excluded_subject_ids().is_empty());
+    }
+}
@@ -0,0 +1,175 @@
+use std::fmt;
+
+use serde::{Deserialize, Serialize};
+
+use crate::entities::{RelationshipTuple, TransactionLogEntry};
+
+/// Subject id that stands for every subject of its type.
+pub const WILDCARD_SUBJECT_ID: &str = "*";
+
+/// Identity of a relationship tuple, `namespace:object_id#relation@subject`.
+///
+/// A subject with a `subject_relation` is a userset, e.g. `group:eng#member`, and is stored with
+/// `userset_namespace = subject_type` and `userset_relation = subject_relation`.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct TupleKey {
+    pub namespace: String,
+    pub object_id: String,
+    pub relation: String,
+    pub subject_type: String,
+    pub subject_id: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub subject_relation: Option<String>,
+}
+
+impl TupleKey {
+    pub fn object(&self) -> ObjectRelation {
+        ObjectRelation {
+            namespace: self.namespace.clone(),
+            object_id: self.object_id.clone(),
+            relation: self.relation.clone(),
+        }
+    }
+
+    pub fn subject(&self) -> SubjectRef {
+        SubjectRef {
+            subject_type: self.subject_type.clone(),
+            subject_id: self.subject_id.clone(),
+            subject_relation: self.subject_relation.clone(),
+        }
+    }
+
+    /// Value of the `userset_namespace` column for this tuple.
+    pub fn userset_namespace(&self) -> Option<&str> {
+        self.subject_relation
+            .as_ref()
+            .map(|_| self.subject_type.as_str())
+    }
+}
+
+impl fmt::Display for TupleKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}@{}", self.object(), self.subject())
+    }
+}
+
+impl From<RelationshipTuple> for TupleKey {
+    fn from(value: RelationshipTuple) -> Self {
+        Self {
+            namespace: value.namespace_id,
+            object_id: value.object_id,
+            relation: value.relation,
+            subject_type: value.subject_type,
+            subject_id: value.subject_id,
+            subject_relation: value.userset_relation,
+        }
+    }
+}
+
+impl From<TransactionLogEntry> for TupleKey {
+    fn from(value: TransactionLogEntry) -> Self {
+        Self {
+            namespace: value.namespace_id,
+            object_id: value.object_id,
+            relation: value.relation,
+            subject_type: value.subject_type,
+            subject_id: value.subject_id,
+            subject_relation: value.userset_relation,
+        }
+    }
+}
+
+/// A relation of one object, `namespace:object_id#relation`.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct ObjectRelation {
+    pub namespace: String,
+    pub object_id: String,
+    pub relation: String,
+}
+
+impl fmt::Display for ObjectRelation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:{}#{}", self.namespace, self.object_id, self.relation)
+    }
+}
+
+/// Subject of a tuple or a check: a single subject such as `user:alice`, or with a
+/// `subject_relation` the userset `group:eng#member`.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct SubjectRef {
+    pub subject_type: String,
+    pub subject_id: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub subject_relation: Option<String>,
+}
+
+impl SubjectRef {
+    /// The userset this subject denotes, if it is one.
+    pub fn as_userset(&self) -> Option<ObjectRelation> {
+        self.subject_relation
+            .as_ref()
+            .map(|subject_relation| ObjectRelation {
+                namespace: self.subject_type.clone(),
+                object_id: self.subject_id.clone(),
+                relation: subject_relation.clone(),
+            })
+    }
+}
+
+impl fmt::Display for SubjectRef {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:{}", self.subject_type, self.subject_id)?;
+        if let Some(subject_relation) = &self.subject_relation {
+            write!(f, "#{subject_relation}")?;
+        }
+        Ok(())
+    }
+}
+
+/// Matches every tuple whose fields equal the ones that are set.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TupleFilter {
+    pub namespace: Option<String>,
+    pub object_id: Option<String>,
+    pub relation: Option<String>,
+    pub subject_type: Option<String>,
+    pub subject_id: Option<String>,
+    pub subject_relation: Option<String>,
+}
+
+impl TupleFilter {
+    pub fn is_empty(&self) -> bool {
+        *self == Self::default()
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum UpdateOperation {
+    /// Writes the tuple, failing if it already exists.
+    Create,
+    /// Writes the tuple, or refreshes it if it already exists.
+    Touch,
+    /// Removes the tuple if it exists.
+    Delete,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TupleUpdate {
+    pub operation: UpdateOperation,
+    pub relationship: TupleKey,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PreconditionOperation {
+    MustExist,
+    MustNotExist,
+}
+
+/// Condition on the stored tuples that has to hold for a write batch to be applied.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Precondition {
+    pub operation: PreconditionOperation,
+    pub filter: TupleFilter,
+}
@@ -0,0 +1,42 @@
+use serde::Serialize;
+
+use super::{ObjectRelation, SubjectRef};
+
+/// Expansion of one `namespace:object_id#relation` into the subjects that hold it.
+#[derive(Debug, Clone, Serialize)]
+pub struct UsersetTree {
+    pub object: ObjectRelation,
+    #[serde(flatten)]
+    pub node: UsersetNode,
+}
+
+/// A node of a [`UsersetTree`], mirroring the [`Rewrite`](super::Rewrite) it was expanded from.
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum UsersetNode {
+    /// Subjects stored in the relation itself. Usersets among them are expanded in `children`.
+    This {
+        subjects: Vec<SubjectRef>,
+        children: Vec<UsersetTree>,
+    },
+    /// Another relation of the same object.
+    ComputedUserset {
+        child: Box<UsersetTree>,
+    },
+    /// The `computed_relation` of every object in `tupleset`, one child per object.
+    TupleToUserset {
+        tupleset: String,
+        computed_relation: String,
+        children: Vec<UsersetTree>,
+    },
+    Union {
+        children: Vec<UsersetNode>,
+    },
+    Intersection {
+        children: Vec<UsersetNode>,
+    },
+    Exclusion {
+        base: Box<UsersetNode>,
+        subtract: Box<UsersetNode>,
+    },
+}
@@ -0,0 +1,52 @@
+use crate::{
+    database::{DatabasePool, with_pool},
+    entities::AuthDecision,
+};
+
+#[derive(Debug, Clone)]
+pub struct AuthDecisionRepository {
+    pool: DatabasePool,
+}
+
+impl AuthDecisionRepository {
+    pub fn new(pool: DatabasePool) -> Self {
+        Self { pool }
+    }
+
+    /// Records `decisions` in one transaction.
+    pub async fn insert_all(&self, decisions: &[AuthDecision]) -> Result<(), sqlx::Error> {
+        if decisions.is_empty() {
+            return Ok(());
+        }
+        with_pool!(&self.pool, |pool| {
+            let mut tx = pool.begin().await?;
+            for decision in decisions {
+                sqlx::query(
+                    "INSERT INTO auth_decisions (id, timestamp, request_id, subject_type, \
+                     subject_id, namespace_id, object_id, relation, permitted, cached, \
+                     latency_ms, evaluation_path, zookie_token, waited_for_consistency, \
+                     consistency_wait_ms) \
+                     VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13, $14, $15)",
+                )
+                .bind(decision.id)
+                .bind(decision.timestamp)
+                .bind(decision.request_id)
+                .bind(&decision.subject_type)
+                .bind(&decision.subject_id)
+                .bind(&decision.namespace_id)
+                .bind(&decision.object_id)
+                .bind(&decision.relation)
+                .bind(decision.permitted)
+                .bind(decision.cached)
+                .bind(decision.latency_ms)
+                .bind(&decision.evaluation_path)
+                .bind(decision.zookie_token.as_deref())
+                .bind(decision.waited_for_consistency)
+                .bind(decision.consistency_wait_ms)
+                .execute(&mut *tx)
+                .await?;
+            }
+            tx.commit().await
+        })
+    }
+}
@@ -1 +1,17 @@
+pub mod auth_decision;
+pub mod namespace;
+pub mod permissions_cache;
+pub mod relation;
+pub mod relation_rule;
+pub mod relationship;
+pub mod schema;
+pub mod transaction_log;
 
+pub use auth_decision::AuthDecisionRepository;
+pub use namespace::NamespaceRepository;
+pub use permissions_cache::PermissionsCacheRepository;
+pub use relation::{RelationDeletion, RelationRepository};
+pub use relation_rule::RelationRuleRepository;
+pub use relationship::{RelationshipRepository, WriteOutcome};
+pub use schema::{NamespaceSchema, RelationSchema, RuleSchema, SchemaApply, SchemaRepository};
+pub use transaction_log::TransactionLogRepository;
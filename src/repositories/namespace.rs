@@ -0,0 +1,99 @@
+use chrono::Utc;
+
+use crate::{
+    database::{DatabasePool, with_pool},
+    entities::Namespace,
+};
+
+#[derive(Debug, Clone)]
+pub struct NamespaceRepository {
+    pool: DatabasePool,
+}
+
+impl NamespaceRepository {
+    pub fn new(pool: DatabasePool) -> Self {
+        Self { pool }
+    }
+
+    pub async fn find_all(&self) -> Result<Vec<Namespace>, sqlx::Error> {
+        with_pool!(&self.pool, |pool| {
+            sqlx::query_as("SELECT * FROM namespaces ORDER BY id")
+                .fetch_all(pool)
+                .await
+        })
+    }
+
+    pub async fn find_by_id(&self, id: &str) -> Result<Option<Namespace>, sqlx::Error> {
+        with_pool!(&self.pool, |pool| {
+            sqlx::query_as("SELECT * FROM namespaces WHERE id = $1")
+                .bind(id)
+                .fetch_optional(pool)
+                .await
+        })
+    }
+
+    pub async fn create(
+        &self,
+        id: &str,
+        name: &str,
+        description: Option<&str>,
+    ) -> Result<Namespace, sqlx::Error> {
+        with_pool!(&self.pool, |pool| {
+            sqlx::query_as(
+                "INSERT INTO namespaces (id, name, description) VALUES ($1, $2, $3) RETURNING *",
+            )
+            .bind(id)
+            .bind(name)
+            .bind(description)
+            .fetch_one(pool)
+            .await
+        })
+    }
+
+    pub async fn update(
+        &self,
+        id: &str,
+        name: &str,
+        description: Option<&str>,
+    ) -> Result<Option<Namespace>, sqlx::Error> {
+        with_pool!(&self.pool, |pool| {
+            sqlx::query_as(
+                "UPDATE namespaces SET name = $2, description = $3, updated_at = $4 \
+                 WHERE id = $1 RETURNING *",
+            )
+            .bind(id)
+            .bind(name)
+            .bind(description)
+            .bind(Utc::now())
+            .fetch_optional(pool)
+            .await
+        })
+    }
+
+    /// Deletes the namespace together with its soft-deleted relations and their rules. Live
+    /// relations, and tuples still referencing a soft-deleted relation, make the delete fail with
+    /// a foreign key violation. Returns `false` if the namespace does not exist.
+    pub async fn delete(&self, id: &str) -> Result<bool, sqlx::Error> {
+        with_pool!(&self.pool, |pool| {
+            let mut tx = pool.begin().await?;
+            sqlx::query(
+                "DELETE FROM relation_rules WHERE namespace_id = $1 AND relation_name IN \
+                 (SELECT name FROM relations WHERE namespace_id = $1 AND deleted_at IS NOT NULL)",
+            )
+            .bind(id)
+            .execute(&mut *tx)
+            .await?;
+            sqlx::query("DELETE FROM relations WHERE namespace_id = $1 AND deleted_at IS NOT NULL")
+                .bind(id)
+                .execute(&mut *tx)
+                .await?;
+            let deleted = sqlx::query("DELETE FROM namespaces WHERE id = $1")
+                .bind(id)
+                .execute(&mut *tx)
+                .await?
+                .rows_affected();
+            tx.commit().await?;
+            Ok(deleted > 0)
+        })
+    }
+}
@@ -0,0 +1,101 @@
+use std::fmt::Write;
+
+use chrono::Utc;
+use sha2::{Digest, Sha256};
+
+use crate::{
+    database::{DatabasePool, with_pool},
+    entities::PermissionsCacheEntry,
+    models::{ObjectRelation, SubjectRef},
+};
+
+/// `cache_key` of the result of checking `subject` against `object`. The fields are hashed, so
+/// long ids fit the column and userset subjects get keys of their own.
+pub fn cache_key(object: &ObjectRelation, subject: &SubjectRef) -> String {
+    let mut hasher = Sha256::new();
+    for field in [
+        &object.namespace,
+        &object.object_id,
+        &object.relation,
+        &subject.subject_type,
+        &subject.subject_id,
+    ] {
+        hasher.update(field.as_bytes());
+        hasher.update([0]);
+    }
+    if let Some(subject_relation) = &subject.subject_relation {
+        hasher.update(subject_relation.as_bytes());
+    }
+    hasher
+        .finalize()
+        .iter()
+        .fold(String::with_capacity(64), |mut key, byte| {
+            let _ = write!(key, "{byte:02x}");
+            key
+        })
+}
+
+#[derive(Debug, Clone)]
+pub struct PermissionsCacheRepository {
+    pool: DatabasePool,
+}
+
+impl PermissionsCacheRepository {
+    pub fn new(pool: DatabasePool) -> Self {
+        Self { pool }
+    }
+
+    /// Cached result under `cache_key` that has not expired and was computed at `min_version`
+    /// or later, along with that version.
+    pub async fn find(
+        &self,
+        cache_key: &str,
+        min_version: i64,
+    ) -> Result<Option<(bool, i64)>, sqlx::Error> {
+        with_pool!(&self.pool, |pool| {
+            sqlx::query_as(
+                "SELECT permitted, max_zookie_version FROM permissions_cache \
+                 WHERE cache_key = $1 AND max_zookie_version >= $2 AND valid_until > $3",
+            )
+            .bind(cache_key)
+            .bind(min_version)
+            .bind(Utc::now())
+            .fetch_optional(pool)
+            .await
+        })
+    }
+
+    /// Stores `entries` in one transaction, replacing entries computed at the same or an older
+    /// version.
+    pub async fn upsert_all(&self, entries: &[PermissionsCacheEntry]) -> Result<(), sqlx::Error> {
+        with_pool!(&self.pool, |pool| {
+            let mut tx = pool.begin().await?;
+            for entry in entries {
+                sqlx::query(
+                    "INSERT INTO permissions_cache (id, namespace_id, object_id, relation, \
+                     subject_type, subject_id, permitted, computed_at, valid_until, \
+                     max_zookie_version, cache_key) \
+                     VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11) \
+                     ON CONFLICT (cache_key) DO UPDATE SET permitted = excluded.permitted, \
+                     computed_at = excluded.computed_at, valid_until = excluded.valid_until, \
+                     max_zookie_version = excluded.max_zookie_version \
+                     WHERE excluded.max_zookie_version >= permissions_cache.max_zookie_version",
+                )
+                .bind(entry.id)
+                .bind(&entry.namespace_id)
+                .bind(&entry.object_id)
+                .bind(&entry.relation)
+                .bind(&entry.subject_type)
+                .bind(&entry.subject_id)
+                .bind(entry.permitted)
+                .bind(entry.computed_at)
+                .bind(entry.valid_until)
+                .bind(entry.max_zookie_version)
+                .bind(&entry.cache_key)
+                .execute(&mut *tx)
+                .await?;
+            }
+            tx.commit().await
+        })
+    }
+}
@@ -0,0 +1,227 @@
+use chrono::Utc;
+use uuid::Uuid;
+
+use crate::{
+    database::{DatabasePool, with_pool},
+    entities::{Relation, RelationRule},
+};
+
+use super::{
+    relationship::{CURRENT_VERSION_QUERY, INSERT_ZOOKIE},
+    schema::{references, stored_schema},
+};
+
+#[derive(Debug, Clone)]
+pub struct RelationRepository {
+    pool: DatabasePool,
+}
+
+/// Outcome of [`RelationRepository::soft_delete`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RelationDeletion {
+    /// The relation was deleted at a new version, named by `zookie`.
+    Deleted {
+        zookie: String,
+    },
+    NotFound,
+    /// Nothing was changed because relationship tuples still use the relation.
+    InUse {
+        tuple_count: i64,
+    },
+    /// Nothing was changed because rules of other relations refer to the relation, named
+    /// `namespace#relation`.
+    Referenced {
+        referenced_by: Vec<String>,
+    },
+}
+
+impl RelationRepository {
+    pub fn new(pool: DatabasePool) -> Self {
+        Self { pool }
+    }
+
+    pub async fn find_by_namespace(
+        &self,
+        namespace_id: &str,
+        include_deleted: bool,
+    ) -> Result<Vec<Relation>, sqlx::Error> {
+        with_pool!(&self.pool, |pool| {
+            sqlx::query_as(
+                "SELECT * FROM relations WHERE namespace_id = $1 \
+                 AND ($2 OR deleted_at IS NULL) ORDER BY name",
+            )
+            .bind(namespace_id)
+            .bind(include_deleted)
+            .fetch_all(pool)
+            .await
+        })
+    }
+
+    /// Relations of every namespace that have not been soft-deleted.
+    pub async fn find_all(&self) -> Result<Vec<Relation>, sqlx::Error> {
+        with_pool!(&self.pool, |pool| {
+            sqlx::query_as(
+                "SELECT * FROM relations WHERE deleted_at IS NULL ORDER BY namespace_id, name",
+            )
+            .fetch_all(pool)
+            .await
+        })
+    }
+
+    /// Finds a relation that has not been soft-deleted.
+    pub async fn find(
+        &self,
+        namespace_id: &str,
+        name: &str,
+    ) -> Result<Option<Relation>, sqlx::Error> {
+        with_pool!(&self.pool, |pool| {
+            sqlx::query_as(
+                "SELECT * FROM relations \
+                 WHERE namespace_id = $1 AND name = $2 AND deleted_at IS NULL",
+            )
+            .bind(namespace_id)
+            .bind(name)
+            .fetch_optional(pool)
+            .await
+        })
+    }
+
+    /// Creates the relation, or restores it if it was soft-deleted. Returns `None` if a live
+    /// relation with the same name already exists.
+    pub async fn create(
+        &self,
+        namespace_id: &str,
+        name: &str,
+        description: Option<&str>,
+    ) -> Result<Option<Relation>, sqlx::Error> {
+        with_pool!(&self.pool, |pool| {
+            sqlx::query_as(
+                "INSERT INTO relations (id, namespace_id, name, description) \
+                 VALUES ($1, $2, $3, $4) \
+                 ON CONFLICT (namespace_id, name) DO UPDATE \
+                 SET description = excluded.description, deleted_at = NULL, updated_at = $5 \
+                 WHERE relations.deleted_at IS NOT NULL \
+                 RETURNING *",
+            )
+            .bind(Uuid::new_v4())
+            .bind(namespace_id)
+            .bind(name)
+            .bind(description)
+            .bind(Utc::now())
+            .fetch_optional(pool)
+            .await
+        })
+    }
+
+    pub async fn update(
+        &self,
+        namespace_id: &str,
+        name: &str,
+        description: Option<&str>,
+    ) -> Result<Option<Relation>, sqlx::Error> {
+        with_pool!(&self.pool, |pool| {
+            sqlx::query_as(
+                "UPDATE relations SET description = $3, updated_at = $4 \
+                 WHERE namespace_id = $1 AND name = $2 AND deleted_at IS NULL RETURNING *",
+            )
+            .bind(namespace_id)
+            .bind(name)
+            .bind(description)
+            .bind(Utc::now())
+            .fetch_optional(pool)
+            .await
+        })
+    }
+
+    /// Soft-deletes the relation along with the rules defining it. Mirrors the `ON DELETE
+    /// RESTRICT` foreign key of `relationship_tuples`, which a soft delete would not trigger,
+    /// and refuses like [`SchemaRepository::apply`](super::SchemaRepository::apply) does while
+    /// the rules of other relations refer to it. Serialized with relationship and schema writes,
+    /// so no tuple or rule can start using the relation between the checks and the delete.
+    ///
+    /// Like a schema change, the delete is committed as a new version under the token
+    /// `mint_token` returns and drops `permissions_cache`, whose results may have been computed
+    /// through the relation.
+    pub async fn soft_delete(
+        &self,
+        namespace_id: &str,
+        name: &str,
+        mint_token: &(dyn Fn(i64) -> String + Sync),
+    ) -> Result<RelationDeletion, sqlx::Error> {
+        let serialize_writes = self.pool.serialize_writes_statement();
+        with_pool!(&self.pool, |pool| {
+            let mut tx = pool.begin().await?;
+            sqlx::query(serialize_writes).execute(&mut *tx).await?;
+            let tuple_count: i64 = sqlx::query_scalar(
+                "SELECT COUNT(*) FROM relationship_tuples WHERE namespace_id = $1 AND relation = $2",
+            )
+            .bind(namespace_id)
+            .bind(name)
+            .fetch_one(&mut *tx)
+            .await?;
+            if tuple_count > 0 {
+                return Ok(RelationDeletion::InUse { tuple_count });
+            }
+
+            let relations: Vec<Relation> = sqlx::query_as(
+                "SELECT * FROM relations WHERE deleted_at IS NULL \
+                 AND NOT (namespace_id = $1 AND name = $2)",
+            )
+            .bind(namespace_id)
+            .bind(name)
+            .fetch_all(&mut *tx)
+            .await?;
+            let rules: Vec<RelationRule> =
+                sqlx::query_as("SELECT * FROM relation_rules WHERE deleted_at IS NULL")
+                    .fetch_all(&mut *tx)
+                    .await?;
+            let referenced_by = references(&stored_schema(&relations, &rules), namespace_id, name);
+            if !referenced_by.is_empty() {
+                return Ok(RelationDeletion::Referenced { referenced_by });
+            }
+
+            let now = Utc::now();
+            let deleted = sqlx::query(
+                "UPDATE relations SET deleted_at = $3, updated_at = $3 \
+                 WHERE namespace_id = $1 AND name = $2 AND deleted_at IS NULL",
+            )
+            .bind(namespace_id)
+            .bind(name)
+            .bind(now)
+            .execute(&mut *tx)
+            .await?
+            .rows_affected();
+            if deleted == 0 {
+                return Ok(RelationDeletion::NotFound);
+            }
+            sqlx::query(
+                "UPDATE relation_rules SET deleted_at = $3, updated_at = $3 \
+                 WHERE namespace_id = $1 AND relation_name = $2 AND deleted_at IS NULL",
+            )
+            .bind(namespace_id)
+            .bind(name)
+            .bind(now)
+            .execute(&mut *tx)
+            .await?;
+            sqlx::query("DELETE FROM permissions_cache")
+                .execute(&mut *tx)
+                .await?;
+
+            let version: i64 = sqlx::query_scalar(CURRENT_VERSION_QUERY)
+                .fetch_one(&mut *tx)
+                .await?;
+            let version = version + 1;
+            let zookie = mint_token(version);
+            sqlx::query(INSERT_ZOOKIE)
+                .bind(&zookie)
+                .bind(now)
+                .bind(version)
+                .bind(Uuid::new_v4())
+                .execute(&mut *tx)
+                .await?;
+
+            tx.commit().await?;
+            Ok(RelationDeletion::Deleted { zookie })
+        })
+    }
+}
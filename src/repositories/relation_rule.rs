@@ -0,0 +1,46 @@
+use crate::{
+    database::{DatabasePool, with_pool},
+    entities::RelationRule,
+};
+
+#[derive(Debug, Clone)]
+pub struct RelationRuleRepository {
+    pool: DatabasePool,
+}
+
+impl RelationRuleRepository {
+    pub fn new(pool: DatabasePool) -> Self {
+        Self { pool }
+    }
+
+    /// Live rules defining the relation, in the order they are evaluated.
+    pub async fn find_by_relation(
+        &self,
+        namespace_id: &str,
+        relation_name: &str,
+    ) -> Result<Vec<RelationRule>, sqlx::Error> {
+        with_pool!(&self.pool, |pool| {
+            sqlx::query_as(
+                "SELECT * FROM relation_rules \
+                 WHERE namespace_id = $1 AND relation_name = $2 AND deleted_at IS NULL \
+                 ORDER BY priority",
+            )
+            .bind(namespace_id)
+            .bind(relation_name)
+            .fetch_all(pool)
+            .await
+        })
+    }
+
+    /// Live rules of every relation, grouped by relation and in evaluation order.
+    pub async fn find_all(&self) -> Result<Vec<RelationRule>, sqlx::Error> {
+        with_pool!(&self.pool, |pool| {
+            sqlx::query_as(
+                "SELECT * FROM relation_rules WHERE deleted_at IS NULL \
+                 ORDER BY namespace_id, relation_name, priority",
+            )
+            .fetch_all(pool)
+            .await
+        })
+    }
+}
@@ -0,0 +1,438 @@
+use std::collections::BTreeSet;
+
+use chrono::Utc;
+use sqlx::types::Json;
+use uuid::Uuid;
+
+use crate::{
+    database::{DatabasePool, with_pool},
+    entities::{OperationType, RelationshipTuple, Zookie},
+    models::{
+        ObjectRelation, Precondition, PreconditionOperation, SubjectRef, TupleFilter, TupleKey,
+        TupleUpdate, UpdateOperation, WILDCARD_SUBJECT_ID,
+    },
+};
+
+/// Matches the tuple identified by `$1..=$7`, using the same `COALESCE` as `idx_tuples_unique`.
+pub(super) const TUPLE_KEY_CONDITION: &str = "namespace_id = $1 AND object_id = $2 AND relation = $3 \
+     AND subject_type = $4 AND subject_id = $5 \
+     AND COALESCE(userset_namespace, '') = COALESCE($6, '') \
+     AND COALESCE(userset_relation, '') = COALESCE($7, '')";
+
+/// Inserts a tuple from `$1..=$10`: id, the key columns, the write time and the zookie token.
+pub(super) const INSERT_TUPLE: &str = "INSERT INTO relationship_tuples (id, namespace_id, \
+     object_id, relation, subject_type, subject_id, userset_namespace, userset_relation, \
+     created_at, updated_at, zookie_token) \
+     VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $9, $10) ON CONFLICT DO NOTHING";
+
+/// Records a changed tuple in `transaction_log` from `$1..=$13`.
+pub(super) const INSERT_TRANSACTION_LOG: &str = "INSERT INTO transaction_log (id, timestamp, \
+     version_number, operation, namespace_id, object_id, relation, subject_type, subject_id, \
+     userset_namespace, userset_relation, zookie_token, payload) \
+     VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13)";
+
+/// Drops the cached results of the object relation `$1..=$3` a changed tuple belongs to. Results
+/// depending on it through other relations are not found this way, those fall behind the version
+/// of the write instead.
+pub(super) const INVALIDATE_PERMISSIONS_CACHE: &str = "DELETE FROM permissions_cache \
+     WHERE namespace_id = $1 AND object_id = $2 AND relation = $3";
+
+/// Records a commit from `$1..=$4`: token, time, version and transaction id.
+pub(super) const INSERT_ZOOKIE: &str = "INSERT INTO zookies (token, timestamp, version, \
+     transaction_id, shard_id, created_at) VALUES ($1, $2, $3, $4, 0, $2) RETURNING *";
+
+/// Matches the tuples selected by a [`TupleFilter`] bound to `$1..=$6`.
+const TUPLE_FILTER_CONDITION: &str = "($1 IS NULL OR namespace_id = $1) \
+     AND ($2 IS NULL OR object_id = $2) \
+     AND ($3 IS NULL OR relation = $3) \
+     AND ($4 IS NULL OR subject_type = $4) \
+     AND ($5 IS NULL OR subject_id = $5) \
+     AND ($6 IS NULL OR userset_relation = $6)";
+
+/// Matches `transaction_log` entries `l` about the same tuple as the row `t`.
+const SAME_TUPLE_AS_T: &str = "l.namespace_id = t.namespace_id AND l.object_id = t.object_id \
+     AND l.relation = t.relation AND l.subject_type = t.subject_type \
+     AND l.subject_id = t.subject_id \
+     AND COALESCE(l.userset_relation, '') = COALESCE(t.userset_relation, '')";
+
+/// Key columns of a tuple, the subject relation last.
+pub(super) type TupleKeyRow = (String, String, String, String, String, Option<String>);
+
+pub(super) fn tuple_key(row: TupleKeyRow) -> TupleKey {
+    let (namespace, object_id, relation, subject_type, subject_id, subject_relation) = row;
+    TupleKey {
+        namespace,
+        object_id,
+        relation,
+        subject_type,
+        subject_id,
+        subject_relation,
+    }
+}
+
+/// Highest version handed out so far, whether or not its zookie has expired since.
+pub(super) const CURRENT_VERSION_QUERY: &str = "SELECT COALESCE(MAX(version), 0) FROM (\
+     SELECT MAX(version) AS version FROM zookies \
+     UNION ALL SELECT MAX(version_number) FROM transaction_log) AS versions";
+
+#[derive(Debug, Clone)]
+pub struct RelationshipRepository {
+    pool: DatabasePool,
+}
+
+/// Outcome of [`RelationshipRepository::write`]. Nothing is written unless it is `Committed`.
+#[derive(Debug, Clone)]
+pub enum WriteOutcome {
+    Committed(Zookie),
+    /// The precondition at this index did not hold.
+    PreconditionFailed(usize),
+    /// The `create` update at this index targets a tuple that already exists.
+    AlreadyExists(usize),
+    NamespaceNotFound(String),
+    RelationNotFound {
+        namespace: String,
+        relation: String,
+    },
+}
+
+impl RelationshipRepository {
+    pub fn new(pool: DatabasePool) -> Self {
+        Self { pool }
+    }
+
+    /// Applies `updates` in a single transaction once every precondition holds and every
+    /// namespace and relation they reference exists. The commit gets the next version, one
+    /// zookie carrying the token `mint_token` returns for that version, and one
+    /// `transaction_log` entry per tuple that actually changed. Cached results of the relations
+    /// of changed tuples are dropped.
+    pub async fn write(
+        &self,
+        updates: &[TupleUpdate],
+        preconditions: &[Precondition],
+        mint_token: &(dyn Fn(i64) -> String + Sync),
+    ) -> Result<WriteOutcome, sqlx::Error> {
+        let serialize_writes = self.pool.serialize_writes_statement();
+        with_pool!(&self.pool, |pool| {
+            let mut tx = pool.begin().await?;
+            sqlx::query(serialize_writes).execute(&mut *tx).await?;
+
+            for (index, precondition) in preconditions.iter().enumerate() {
+                let filter = &precondition.filter;
+                let exists: bool = sqlx::query_scalar(&format!(
+                    "SELECT EXISTS (SELECT 1 FROM relationship_tuples \
+                     WHERE {TUPLE_FILTER_CONDITION})"
+                ))
+                .bind(filter.namespace.as_deref())
+                .bind(filter.object_id.as_deref())
+                .bind(filter.relation.as_deref())
+                .bind(filter.subject_type.as_deref())
+                .bind(filter.subject_id.as_deref())
+                .bind(filter.subject_relation.as_deref())
+                .fetch_one(&mut *tx)
+                .await?;
+                let holds = match precondition.operation {
+                    PreconditionOperation::MustExist => exists,
+                    PreconditionOperation::MustNotExist => !exists,
+                };
+                if !holds {
+                    return Ok(WriteOutcome::PreconditionFailed(index));
+                }
+            }
+
+            // Deletes are checked as well, so a typo is reported instead of silently matching
+            // nothing.
+            let mut relations = BTreeSet::new();
+            let mut subject_namespaces = BTreeSet::new();
+            for update in updates {
+                let tuple = &update.relationship;
+                relations.insert((tuple.namespace.as_str(), tuple.relation.as_str()));
+                match &tuple.subject_relation {
+                    Some(subject_relation) => {
+                        relations.insert((tuple.subject_type.as_str(), subject_relation.as_str()))
+                    }
+                    None => subject_namespaces.insert(tuple.subject_type.as_str()),
+                };
+            }
+            for (namespace, relation) in relations {
+                let exists: bool = sqlx::query_scalar(
+                    "SELECT EXISTS (SELECT 1 FROM relations \
+                     WHERE namespace_id = $1 AND name = $2 AND deleted_at IS NULL)",
+                )
+                .bind(namespace)
+                .bind(relation)
+                .fetch_one(&mut *tx)
+                .await?;
+                if !exists {
+                    return Ok(WriteOutcome::RelationNotFound {
+                        namespace: namespace.to_string(),
+                        relation: relation.to_string(),
+                    });
+                }
+            }
+            for namespace in subject_namespaces {
+                let exists: bool =
+                    sqlx::query_scalar("SELECT EXISTS (SELECT 1 FROM namespaces WHERE id = $1)")
+                        .bind(namespace)
+                        .fetch_one(&mut *tx)
+                        .await?;
+                if !exists {
+                    return Ok(WriteOutcome::NamespaceNotFound(namespace.to_string()));
+                }
+            }
+
+            let version: i64 = sqlx::query_scalar(CURRENT_VERSION_QUERY)
+                .fetch_one(&mut *tx)
+                .await?;
+            let version = version + 1;
+            let token = mint_token(version);
+            let now = Utc::now();
+
+            for (index, update) in updates.iter().enumerate() {
+                let tuple = &update.relationship;
+                let operation = match update.operation {
+                    UpdateOperation::Create | UpdateOperation::Touch => {
+                        let inserted = sqlx::query(INSERT_TUPLE)
+                            .bind(Uuid::new_v4())
+                            .bind(&tuple.namespace)
+                            .bind(&tuple.object_id)
+                            .bind(&tuple.relation)
+                            .bind(&tuple.subject_type)
+                            .bind(&tuple.subject_id)
+                            .bind(tuple.userset_namespace())
+                            .bind(tuple.subject_relation.as_deref())
+                            .bind(now)
+                            .bind(&token)
+                            .execute(&mut *tx)
+                            .await?
+                            .rows_affected();
+                        if inserted > 0 {
+                            Some(OperationType::Create)
+                        } else if update.operation == UpdateOperation::Create {
+                            return Ok(WriteOutcome::AlreadyExists(index));
+                        } else {
+                            sqlx::query(&format!(
+                                "UPDATE relationship_tuples SET updated_at = $8, \
+                                 zookie_token = $9 WHERE {TUPLE_KEY_CONDITION}"
+                            ))
+                            .bind(&tuple.namespace)
+                            .bind(&tuple.object_id)
+                            .bind(&tuple.relation)
+                            .bind(&tuple.subject_type)
+                            .bind(&tuple.subject_id)
+                            .bind(tuple.userset_namespace())
+                            .bind(tuple.subject_relation.as_deref())
+                            .bind(now)
+                            .bind(&token)
+                            .execute(&mut *tx)
+                            .await?;
+                            Some(OperationType::Update)
+                        }
+                    }
+                    UpdateOperation::Delete => {
+                        let deleted = sqlx::query(&format!(
+                            "DELETE FROM relationship_tuples WHERE {TUPLE_KEY_CONDITION}"
+                        ))
+                        .bind(&tuple.namespace)
+                        .bind(&tuple.object_id)
+                        .bind(&tuple.relation)
+                        .bind(&tuple.subject_type)
+                        .bind(&tuple.subject_id)
+                        .bind(tuple.userset_namespace())
+                        .bind(tuple.subject_relation.as_deref())
+                        .execute(&mut *tx)
+                        .await?
+                        .rows_affected();
+                        (deleted > 0).then_some(OperationType::Delete)
+                    }
+                };
+
+                if let Some(operation) = operation {
+                    sqlx::query(INSERT_TRANSACTION_LOG)
+                        .bind(Uuid::new_v4())
+                        .bind(now)
+                        .bind(version)
+                        .bind(operation)
+                        .bind(&tuple.namespace)
+                        .bind(&tuple.object_id)
+                        .bind(&tuple.relation)
+                        .bind(&tuple.subject_type)
+                        .bind(&tuple.subject_id)
+                        .bind(tuple.userset_namespace())
+                        .bind(tuple.subject_relation.as_deref())
+                        .bind(&token)
+                        .bind(Json(tuple))
+                        .execute(&mut *tx)
+                        .await?;
+                    sqlx::query(INVALIDATE_PERMISSIONS_CACHE)
+                        .bind(&tuple.namespace)
+                        .bind(&tuple.object_id)
+                        .bind(&tuple.relation)
+                        .execute(&mut *tx)
+                        .await?;
+                }
+            }
+
+            let zookie: Zookie = sqlx::query_as(INSERT_ZOOKIE)
+                .bind(&token)
+                .bind(now)
+                .bind(version)
+                .bind(Uuid::new_v4())
+                .fetch_one(&mut *tx)
+                .await?;
+
+            tx.commit().await?;
+            Ok(WriteOutcome::Committed(zookie))
+        })
+    }
+
+    /// Tuples matching `filter` as they were right after the write of `version`, ordered by
+    /// key and starting after `after`.
+    ///
+    /// Tuples written since are reconstructed from the earliest later `transaction_log` entry of
+    /// their key: a tuple created since did not exist yet, one updated or deleted since did.
+    pub async fn find_at_version(
+        &self,
+        filter: &TupleFilter,
+        version: i64,
+        after: Option<&TupleKey>,
+        limit: i64,
+    ) -> Result<Vec<TupleKey>, sqlx::Error> {
+        let rows: Vec<TupleKeyRow> = with_pool!(&self.pool, |pool| {
+            sqlx::query_as(&format!(
+                "SELECT namespace_id, object_id, relation, subject_type, subject_id, \
+                 userset_relation FROM (\
+                 SELECT namespace_id, object_id, relation, subject_type, subject_id, \
+                 userset_relation FROM relationship_tuples t WHERE NOT EXISTS (\
+                 SELECT 1 FROM transaction_log l WHERE l.version_number > $7 \
+                 AND {SAME_TUPLE_AS_T}) \
+                 UNION ALL \
+                 SELECT namespace_id, object_id, relation, subject_type, subject_id, \
+                 userset_relation FROM transaction_log t \
+                 WHERE t.version_number > $7 AND t.operation <> 'create' AND NOT EXISTS (\
+                 SELECT 1 FROM transaction_log l WHERE l.version_number > $7 \
+                 AND l.version_number < t.version_number AND {SAME_TUPLE_AS_T})\
+                 ) AS tuples \
+                 WHERE {TUPLE_FILTER_CONDITION} AND ($8 IS NULL OR (namespace_id, object_id, \
+                 relation, subject_type, subject_id, COALESCE(userset_relation, '')) \
+                 > ($8, $9, $10, $11, $12, COALESCE($13, ''))) \
+                 ORDER BY namespace_id, object_id, relation, subject_type, subject_id, \
+                 COALESCE(userset_relation, '') \
+                 LIMIT $14"
+            ))
+            .bind(filter.namespace.as_deref())
+            .bind(filter.object_id.as_deref())
+            .bind(filter.relation.as_deref())
+            .bind(filter.subject_type.as_deref())
+            .bind(filter.subject_id.as_deref())
+            .bind(filter.subject_relation.as_deref())
+            .bind(version)
+            .bind(after.map(|key| key.namespace.as_str()))
+            .bind(after.map(|key| key.object_id.as_str()))
+            .bind(after.map(|key| key.relation.as_str()))
+            .bind(after.map(|key| key.subject_type.as_str()))
+            .bind(after.map(|key| key.subject_id.as_str()))
+            .bind(after.and_then(|key| key.subject_relation.as_deref()))
+            .bind(limit)
+            .fetch_all(pool)
+            .await
+        })?;
+        Ok(rows.into_iter().map(tuple_key).collect())
+    }
+
+    /// Version of the latest committed write, 0 before the first one.
+    pub async fn current_version(&self) -> Result<i64, sqlx::Error> {
+        with_pool!(&self.pool, |pool| {
+            sqlx::query_scalar(CURRENT_VERSION_QUERY)
+                .fetch_one(pool)
+                .await
+        })
+    }
+
+    /// Whether `subject` is stored in the relation itself, either exactly or, for a single
+    /// subject, through a `*` wildcard of its type.
+    pub async fn contains(
+        &self,
+        object: &ObjectRelation,
+        subject: &SubjectRef,
+    ) -> Result<bool, sqlx::Error> {
+        with_pool!(&self.pool, |pool| {
+            sqlx::query_scalar(
+                "SELECT EXISTS (SELECT 1 FROM relationship_tuples \
+                 WHERE namespace_id = $1 AND object_id = $2 AND relation = $3 \
+                 AND subject_type = $4 AND (subject_id = $5 OR ($6 IS NULL AND subject_id = $7)) \
+                 AND COALESCE(userset_relation, '') = COALESCE($6, ''))",
+            )
+            .bind(&object.namespace)
+            .bind(&object.object_id)
+            .bind(&object.relation)
+            .bind(&subject.subject_type)
+            .bind(&subject.subject_id)
+            .bind(subject.subject_relation.as_deref())
+            .bind(WILDCARD_SUBJECT_ID)
+            .fetch_one(pool)
+            .await
+        })
+    }
+
+    /// Tuples of the relation, or with `usersets_only` just those whose subject is a userset.
+    pub async fn find_by_object(
+        &self,
+        object: &ObjectRelation,
+        usersets_only: bool,
+    ) -> Result<Vec<RelationshipTuple>, sqlx::Error> {
+        with_pool!(&self.pool, |pool| {
+            sqlx::query_as(
+                "SELECT * FROM relationship_tuples \
+                 WHERE namespace_id = $1 AND object_id = $2 AND relation = $3 \
+                 AND (NOT $4 OR userset_relation IS NOT NULL) \
+                 ORDER BY subject_type, subject_id, userset_relation",
+            )
+            .bind(&object.namespace)
+            .bind(&object.object_id)
+            .bind(&object.relation)
+            .bind(usersets_only)
+            .fetch_all(pool)
+            .await
+        })
+    }
+
+    /// Tuples whose subject is exactly `subject`, served by `idx_tuples_subject`.
+    pub async fn find_by_subject(
+        &self,
+        subject: &SubjectRef,
+    ) -> Result<Vec<RelationshipTuple>, sqlx::Error> {
+        with_pool!(&self.pool, |pool| {
+            sqlx::query_as(
+                "SELECT * FROM relationship_tuples \
+                 WHERE subject_type = $1 AND subject_id = $2 \
+                 AND COALESCE(userset_relation, '') = COALESCE($3, '') \
+                 ORDER BY namespace_id, object_id, relation",
+            )
+            .bind(&subject.subject_type)
+            .bind(&subject.subject_id)
+            .bind(subject.subject_relation.as_deref())
+            .fetch_all(pool)
+            .await
+        })
+    }
+
+    pub async fn find_by_filter(
+        &self,
+        filter: &TupleFilter,
+    ) -> Result<Vec<RelationshipTuple>, sqlx::Error> {
+        with_pool!(&self.pool, |pool| {
+            sqlx::query_as(&format!(
+                "SELECT * FROM relationship_tuples WHERE {TUPLE_FILTER_CONDITION} \
+                 ORDER BY namespace_id, object_id, relation, subject_type, subject_id"
+            ))
+            .bind(filter.namespace.as_deref())
+            .bind(filter.object_id.as_deref())
+            .bind(filter.relation.as_deref())
+            .bind(filter.subject_type.as_deref())
+            .bind(filter.subject_id.as_deref())
+            .bind(filter.subject_relation.as_deref())
+            .fetch_all(pool)
+            .await
+        })
+    }
+}
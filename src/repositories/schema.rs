@@ -0,0 +1,818 @@
+use std::collections::{HashMap, HashSet};
+
+use chrono::Utc;
+use sqlx::types::Json;
+use uuid::Uuid;
+
+use crate::{
+    database::{DatabasePool, with_pool},
+    entities::{Namespace, OperationType, Relation, RelationRule, RuleType},
+    models::{
+        Expression, OrphanedTuples, RelationMigration, SchemaChanges, SchemaWriteOptions, TupleKey,
+    },
+};
+
+use super::relationship::{
+    CURRENT_VERSION_QUERY, INSERT_TRANSACTION_LOG, INSERT_TUPLE, INSERT_ZOOKIE,
+    INVALIDATE_PERMISSIONS_CACHE, TUPLE_KEY_CONDITION, TupleKeyRow, tuple_key,
+};
+
+/// Records a change of `$tuple` in `transaction_log` and drops the cached results it affects, like
+/// [`RelationshipRepository::write`](super::RelationshipRepository::write) does.
+macro_rules! log_tuple {
+    ($tx:ident, $operation:expr, $tuple:expr, $version:expr, $token:expr, $now:expr) => {
+        sqlx::query(INSERT_TRANSACTION_LOG)
+            .bind(Uuid::new_v4())
+            .bind($now)
+            .bind($version)
+            .bind($operation)
+            .bind(&$tuple.namespace)
+            .bind(&$tuple.object_id)
+            .bind(&$tuple.relation)
+            .bind(&$tuple.subject_type)
+            .bind(&$tuple.subject_id)
+            .bind($tuple.userset_namespace())
+            .bind($tuple.subject_relation.as_deref())
+            .bind($token)
+            .bind(Json($tuple))
+            .execute(&mut *$tx)
+            .await?;
+        sqlx::query(INVALIDATE_PERMISSIONS_CACHE)
+            .bind(&$tuple.namespace)
+            .bind(&$tuple.object_id)
+            .bind(&$tuple.relation)
+            .execute(&mut *$tx)
+            .await?
+    };
+}
+
+/// Priority of the first rule of a relation, the column default.
+const DEFAULT_RULE_PRIORITY: i32 = 100;
+
+/// Desired state of one namespace, see [`SchemaRepository::apply`].
+#[derive(Debug, Clone)]
+pub struct NamespaceSchema {
+    pub id: String,
+    pub relations: Vec<RelationSchema>,
+}
+
+#[derive(Debug, Clone)]
+pub struct RelationSchema {
+    pub name: String,
+    pub subject_types: Option<Vec<String>>,
+    /// The single rule defining the relation, `None` if it holds just its own tuples.
+    pub rule: Option<RuleSchema>,
+}
+
+/// Columns of a `relation_rules` row.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RuleSchema {
+    pub rule_type: RuleType,
+    pub ttu_object_namespace: Option<String>,
+    pub ttu_relation: Option<String>,
+    pub child_relations: Option<Vec<String>>,
+    pub expression: String,
+}
+
+impl RuleSchema {
+    fn matches(&self, rule: &RelationRule) -> bool {
+        self.rule_type == rule.rule_type
+            && self.ttu_object_namespace == rule.ttu_object_namespace
+            && self.ttu_relation == rule.ttu_relation
+            && self.child_relations.as_ref() == rule.child_relations.as_ref().map(|c| &c.0)
+            && Some(&self.expression) == rule.expression.as_ref()
+    }
+}
+
+/// Outcome of [`SchemaRepository::apply`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SchemaApply {
+    /// The schema was applied, or would have been on a dry run.
+    Applied(SchemaChanges),
+    /// Nothing was changed because the schema removes relations or namespaces that permissions
+    /// still refer to, or whose tuples are neither migrated nor forced away.
+    Rejected(Vec<OrphanedTuples>),
+}
+
+#[derive(Debug, Clone)]
+pub struct SchemaRepository {
+    pool: DatabasePool,
+}
+
+impl SchemaRepository {
+    pub fn new(pool: DatabasePool) -> Self {
+        Self { pool }
+    }
+
+    /// Makes `namespaces`, `relations` and `relation_rules` match `schema` in one transaction.
+    ///
+    /// Namespaces missing from `schema` are deleted along with their relations and rules,
+    /// relations missing from a namespace are soft-deleted like
+    /// [`RelationRepository::soft_delete`](super::RelationRepository::soft_delete) does. Rules
+    /// are updated in place where possible so their ids and audit trail survive. Tuple writes
+    /// are held off while the schema is applied, so the tuples found are all there are.
+    ///
+    /// Tuples of removed relations, tuples with their usersets as subject and tuples naming a
+    /// removed namespace are moved by `options.migrations`, the rest deleted with
+    /// `options.force`. Any change is committed like a
+    /// [`RelationshipRepository::write`](super::RelationshipRepository::write), as a new version
+    /// under the token `mint_token` returns, and the tuple changes are logged at that version.
+    pub async fn apply(
+        &self,
+        schema: &[NamespaceSchema],
+        options: &SchemaWriteOptions,
+        mint_token: &(dyn Fn(i64) -> String + Sync),
+    ) -> Result<SchemaApply, sqlx::Error> {
+        let serialize_writes = self.pool.serialize_writes_statement();
+        with_pool!(&self.pool, |pool| {
+            let mut tx = pool.begin().await?;
+            sqlx::query(serialize_writes).execute(&mut *tx).await?;
+
+            let namespaces: Vec<Namespace> = sqlx::query_as("SELECT * FROM namespaces ORDER BY id")
+                .fetch_all(&mut *tx)
+                .await?;
+            let relations: Vec<Relation> =
+                sqlx::query_as("SELECT * FROM relations ORDER BY namespace_id, name")
+                    .fetch_all(&mut *tx)
+                    .await?;
+            let rules: Vec<RelationRule> = sqlx::query_as(
+                "SELECT * FROM relation_rules WHERE deleted_at IS NULL \
+                 ORDER BY namespace_id, relation_name, priority",
+            )
+            .fetch_all(&mut *tx)
+            .await?;
+            let priorities: Vec<(String, String, i32)> = sqlx::query_as(
+                "SELECT namespace_id, relation_name, MAX(priority) FROM relation_rules \
+                 GROUP BY namespace_id, relation_name",
+            )
+            .fetch_all(&mut *tx)
+            .await?;
+            let mut plan = SchemaPlan::new(schema, &namespaces, &relations, &rules, &priorities);
+
+            let mut orphaned = Vec::new();
+            for removal in &plan.removals {
+                let (tuple_count, subject_tuple_count): (i64, i64) = match removal.relation {
+                    Some(relation) => (
+                        sqlx::query_scalar(
+                            "SELECT COUNT(*) FROM relationship_tuples \
+                             WHERE namespace_id = $1 AND relation = $2",
+                        )
+                        .bind(removal.namespace)
+                        .bind(relation)
+                        .fetch_one(&mut *tx)
+                        .await?,
+                        sqlx::query_scalar(
+                            "SELECT COUNT(*) FROM relationship_tuples \
+                             WHERE subject_type = $1 AND userset_relation = $2",
+                        )
+                        .bind(removal.namespace)
+                        .bind(relation)
+                        .fetch_one(&mut *tx)
+                        .await?,
+                    ),
+                    None => (
+                        0,
+                        sqlx::query_scalar(
+                            "SELECT COUNT(*) FROM relationship_tuples \
+                             WHERE subject_type = $1 AND userset_relation IS NULL",
+                        )
+                        .bind(removal.namespace)
+                        .fetch_one(&mut *tx)
+                        .await?,
+                    ),
+                };
+                let referenced_by = removal
+                    .relation
+                    .map(|relation| references(schema, removal.namespace, relation))
+                    .unwrap_or_default();
+                if tuple_count + subject_tuple_count == 0 && referenced_by.is_empty() {
+                    continue;
+                }
+                let migrated_to = removal.relation.and_then(|relation| {
+                    migration_target(&options.migrations, removal.namespace, relation)
+                });
+                orphaned.push(OrphanedTuples {
+                    namespace: removal.namespace.to_string(),
+                    relation: removal.relation.map(ToString::to_string),
+                    tuple_count,
+                    subject_tuple_count,
+                    referenced_by,
+                    migrated_to: migrated_to.map(ToString::to_string),
+                });
+            }
+            let rejected = orphaned.iter().any(|orphaned| {
+                !orphaned.referenced_by.is_empty()
+                    || (orphaned.migrated_to.is_none() && !options.force)
+            });
+            if rejected {
+                return Ok(SchemaApply::Rejected(orphaned));
+            }
+
+            // Orphaned tuples go first and migrated ones are written back once their new
+            // relation exists, so neither trips the foreign keys to `relations`.
+            let mut orphaned_tuples = Vec::new();
+            let mut seen = HashSet::new();
+            for removal in &plan.removals {
+                let rows: Vec<TupleKeyRow> = match removal.relation {
+                    Some(relation) => {
+                        sqlx::query_as(
+                            "SELECT namespace_id, object_id, relation, subject_type, \
+                             subject_id, userset_relation FROM relationship_tuples \
+                             WHERE (namespace_id = $1 AND relation = $2) \
+                             OR (subject_type = $1 AND userset_relation = $2)",
+                        )
+                        .bind(removal.namespace)
+                        .bind(relation)
+                        .fetch_all(&mut *tx)
+                        .await?
+                    }
+                    None => {
+                        sqlx::query_as(
+                            "SELECT namespace_id, object_id, relation, subject_type, \
+                             subject_id, userset_relation FROM relationship_tuples \
+                             WHERE subject_type = $1 AND userset_relation IS NULL",
+                        )
+                        .bind(removal.namespace)
+                        .fetch_all(&mut *tx)
+                        .await?
+                    }
+                };
+                for tuple in rows.into_iter().map(tuple_key) {
+                    if seen.insert(tuple.clone()) {
+                        orphaned_tuples.push(tuple);
+                    }
+                }
+            }
+
+            // A change of the schema is committed as a version of its own, so that no node
+            // serves results computed with the old rules at the new version.
+            let now = Utc::now();
+            let version_token = if plan.statements.is_empty() {
+                None
+            } else {
+                let version: i64 = sqlx::query_scalar(CURRENT_VERSION_QUERY)
+                    .fetch_one(&mut *tx)
+                    .await?;
+                Some((version + 1, mint_token(version + 1)))
+            };
+            let mut migrated = Vec::new();
+            if let Some((version, token)) = &version_token {
+                for tuple in &orphaned_tuples {
+                    sqlx::query(&format!(
+                        "DELETE FROM relationship_tuples WHERE {TUPLE_KEY_CONDITION}"
+                    ))
+                    .bind(&tuple.namespace)
+                    .bind(&tuple.object_id)
+                    .bind(&tuple.relation)
+                    .bind(&tuple.subject_type)
+                    .bind(&tuple.subject_id)
+                    .bind(tuple.userset_namespace())
+                    .bind(tuple.subject_relation.as_deref())
+                    .execute(&mut *tx)
+                    .await?;
+                    log_tuple!(tx, OperationType::Delete, tuple, *version, token, now);
+                    match migrate(&plan, &options.migrations, tuple) {
+                        Some(tuple) => migrated.push(tuple),
+                        None => plan.changes.deleted_tuples += 1,
+                    }
+                }
+            }
+
+            // Cached results were computed with the old rules, and `permissions_cache` serves
+            // results of older versions too.
+            if !plan.statements.is_empty() {
+                sqlx::query("DELETE FROM permissions_cache")
+                    .execute(&mut *tx)
+                    .await?;
+            }
+            for statement in &plan.statements {
+                match statement {
+                    Statement::CreateNamespace(id) => {
+                        sqlx::query("INSERT INTO namespaces (id, name) VALUES ($1, $1)")
+                            .bind(id)
+                            .execute(&mut *tx)
+                            .await?;
+                    }
+                    Statement::PutRelation {
+                        namespace,
+                        relation,
+                    } => {
+                        sqlx::query(
+                            "INSERT INTO relations (id, namespace_id, name, subject_types) \
+                             VALUES ($1, $2, $3, $4) \
+                             ON CONFLICT (namespace_id, name) DO UPDATE \
+                             SET subject_types = excluded.subject_types, deleted_at = NULL, \
+                             updated_at = $5",
+                        )
+                        .bind(Uuid::new_v4())
+                        .bind(namespace)
+                        .bind(&relation.name)
+                        .bind(relation.subject_types.as_ref().map(Json))
+                        .bind(now)
+                        .execute(&mut *tx)
+                        .await?;
+                    }
+                    Statement::DeleteRelation { namespace, name } => {
+                        sqlx::query(
+                            "UPDATE relations SET deleted_at = $3, updated_at = $3 \
+                             WHERE namespace_id = $1 AND name = $2",
+                        )
+                        .bind(namespace)
+                        .bind(name)
+                        .bind(now)
+                        .execute(&mut *tx)
+                        .await?;
+                        sqlx::query(
+                            "UPDATE relation_rules SET deleted_at = $3, updated_at = $3 \
+                             WHERE namespace_id = $1 AND relation_name = $2 \
+                             AND deleted_at IS NULL",
+                        )
+                        .bind(namespace)
+                        .bind(name)
+                        .bind(now)
+                        .execute(&mut *tx)
+                        .await?;
+                    }
+                    Statement::InsertRule {
+                        namespace,
+                        relation,
+                        priority,
+                        rule,
+                    } => {
+                        sqlx::query(
+                            "INSERT INTO relation_rules (id, namespace_id, relation_name, \
+                             rule_type, ttu_object_namespace, ttu_relation, child_relations, \
+                             expression, priority) \
+                             VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9)",
+                        )
+                        .bind(Uuid::new_v4())
+                        .bind(namespace)
+                        .bind(relation)
+                        .bind(rule.rule_type)
+                        .bind(&rule.ttu_object_namespace)
+                        .bind(&rule.ttu_relation)
+                        .bind(rule.child_relations.as_ref().map(Json))
+                        .bind(&rule.expression)
+                        .bind(priority)
+                        .execute(&mut *tx)
+                        .await?;
+                    }
+                    Statement::UpdateRule { id, rule } => {
+                        sqlx::query(
+                            "UPDATE relation_rules SET rule_type = $2, \
+                             ttu_object_namespace = $3, ttu_relation = $4, \
+                             child_relations = $5, expression = $6, updated_at = $7 \
+                             WHERE id = $1",
+                        )
+                        .bind(id)
+                        .bind(rule.rule_type)
+                        .bind(&rule.ttu_object_namespace)
+                        .bind(&rule.ttu_relation)
+                        .bind(rule.child_relations.as_ref().map(Json))
+                        .bind(&rule.expression)
+                        .bind(now)
+                        .execute(&mut *tx)
+                        .await?;
+                    }
+                    Statement::DeleteRule(id) => {
+                        sqlx::query(
+                            "UPDATE relation_rules SET deleted_at = $2, updated_at = $2 \
+                             WHERE id = $1",
+                        )
+                        .bind(id)
+                        .bind(now)
+                        .execute(&mut *tx)
+                        .await?;
+                    }
+                    Statement::DeleteNamespace(id) => {
+                        sqlx::query("DELETE FROM relation_rules WHERE namespace_id = $1")
+                            .bind(id)
+                            .execute(&mut *tx)
+                            .await?;
+                        sqlx::query("DELETE FROM relations WHERE namespace_id = $1")
+                            .bind(id)
+                            .execute(&mut *tx)
+                            .await?;
+                        sqlx::query("DELETE FROM namespaces WHERE id = $1")
+                            .bind(id)
+                            .execute(&mut *tx)
+                            .await?;
+                    }
+                }
+            }
+
+            if let Some((version, token)) = version_token {
+                for tuple in &migrated {
+                    let inserted = sqlx::query(INSERT_TUPLE)
+                        .bind(Uuid::new_v4())
+                        .bind(&tuple.namespace)
+                        .bind(&tuple.object_id)
+                        .bind(&tuple.relation)
+                        .bind(&tuple.subject_type)
+                        .bind(&tuple.subject_id)
+                        .bind(tuple.userset_namespace())
+                        .bind(tuple.subject_relation.as_deref())
+                        .bind(now)
+                        .bind(&token)
+                        .execute(&mut *tx)
+                        .await?
+                        .rows_affected();
+                    if inserted > 0 {
+                        log_tuple!(tx, OperationType::Create, tuple, version, &token, now);
+                    }
+                }
+                plan.changes.migrated_tuples = migrated.len() as u64;
+                sqlx::query(INSERT_ZOOKIE)
+                    .bind(&token)
+                    .bind(now)
+                    .bind(version)
+                    .bind(Uuid::new_v4())
+                    .execute(&mut *tx)
+                    .await?;
+                plan.changes.zookie = (!options.dry_run).then_some(token);
+            }
+            plan.changes.orphaned = orphaned;
+
+            if !options.dry_run {
+                tx.commit().await?;
+            }
+            Ok(SchemaApply::Applied(plan.changes))
+        })
+    }
+}
+
+/// One write of a [`SchemaPlan`].
+#[derive(Debug)]
+enum Statement<'a> {
+    CreateNamespace(&'a str),
+    /// Creates, restores or updates the subject types of a relation.
+    PutRelation {
+        namespace: &'a str,
+        relation: &'a RelationSchema,
+    },
+    /// Soft-deletes a relation along with its rules.
+    DeleteRelation {
+        namespace: &'a str,
+        name: &'a str,
+    },
+    InsertRule {
+        namespace: &'a str,
+        relation: &'a str,
+        priority: i32,
+        rule: &'a RuleSchema,
+    },
+    UpdateRule {
+        id: Uuid,
+        rule: &'a RuleSchema,
+    },
+    /// Soft-deletes a rule.
+    DeleteRule(Uuid),
+    /// Deletes a namespace with all its relations and rules, soft-deleted or not.
+    DeleteNamespace(&'a str),
+}
+
+/// The writes turning the stored schema into the desired one, in an order the foreign keys
+/// allow.
+#[derive(Debug)]
+struct SchemaPlan<'a> {
+    statements: Vec<Statement<'a>>,
+    /// Relations and namespaces tuples may no longer use.
+    removals: Vec<Removal<'a>>,
+    changes: SchemaChanges,
+}
+
+/// A relation that is deleted, or with no `relation` a namespace that is deleted and so can no
+/// longer be a subject type.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Removal<'a> {
+    namespace: &'a str,
+    relation: Option<&'a str>,
+}
+
+impl<'a> SchemaPlan<'a> {
+    fn new(
+        schema: &'a [NamespaceSchema],
+        namespaces: &'a [Namespace],
+        relations: &'a [Relation],
+        rules: &'a [RelationRule],
+        priorities: &'a [(String, String, i32)],
+    ) -> Self {
+        let relations: HashMap<(&str, &str), &Relation> = relations
+            .iter()
+            .map(|relation| {
+                (
+                    (relation.namespace_id.as_str(), relation.name.as_str()),
+                    relation,
+                )
+            })
+            .collect();
+        let mut rules_by_relation: HashMap<(&str, &str), Vec<&RelationRule>> = HashMap::new();
+        for rule in rules {
+            rules_by_relation
+                .entry((&rule.namespace_id, &rule.relation_name))
+                .or_default()
+                .push(rule);
+        }
+        let priorities: HashMap<(&str, &str), i32> = priorities
+            .iter()
+            .map(|(namespace, relation, priority)| {
+                ((namespace.as_str(), relation.as_str()), *priority)
+            })
+            .collect();
+        let live_relations = |namespace: &str| {
+            let mut names: Vec<&'a str> = relations
+                .values()
+                .filter(|relation| {
+                    relation.namespace_id == namespace && relation.deleted_at.is_none()
+                })
+                .map(|relation| relation.name.as_str())
+                .collect();
+            names.sort_unstable();
+            names
+        };
+
+        let mut plan = Self {
+            statements: Vec::new(),
+            removals: Vec::new(),
+            changes: SchemaChanges::default(),
+        };
+        let existing: HashSet<&str> = namespaces
+            .iter()
+            .map(|namespace| namespace.id.as_str())
+            .collect();
+        for namespace in schema {
+            let id = namespace.id.as_str();
+            if !existing.contains(id) {
+                plan.statements.push(Statement::CreateNamespace(id));
+                plan.changes.created_namespaces.push(id.to_string());
+            }
+
+            for relation in &namespace.relations {
+                let name = relation.name.as_str();
+                let key = (id, name);
+                let current = relations
+                    .get(&key)
+                    .filter(|current| current.deleted_at.is_none());
+                let current_rules = rules_by_relation.remove(&key).unwrap_or_default();
+                let mut changed = false;
+
+                let current_types = current
+                    .and_then(|current| current.subject_types.as_ref().map(|types| &types.0));
+                if current.is_none() || current_types != relation.subject_types.as_ref() {
+                    plan.statements.push(Statement::PutRelation {
+                        namespace: id,
+                        relation,
+                    });
+                    changed = true;
+                }
+
+                match (&relation.rule, current_rules.as_slice()) {
+                    (Some(rule), [current]) if rule.matches(current) => {}
+                    (Some(rule), [first, rest @ ..]) => {
+                        plan.statements
+                            .push(Statement::UpdateRule { id: first.id, rule });
+                        plan.statements
+                            .extend(rest.iter().map(|rule| Statement::DeleteRule(rule.id)));
+                        changed = true;
+                    }
+                    (Some(rule), []) => {
+                        let priority = priorities
+                            .get(&key)
+                            .map_or(DEFAULT_RULE_PRIORITY, |priority| priority + 1);
+                        plan.statements.push(Statement::InsertRule {
+                            namespace: id,
+                            relation: name,
+                            priority,
+                            rule,
+                        });
+                        changed = true;
+                    }
+                    (None, current_rules) => {
+                        for rule in current_rules {
+                            plan.statements.push(Statement::DeleteRule(rule.id));
+                            changed = true;
+                        }
+                    }
+                }
+
+                let qualified = format!("{id}#{name}");
+                if current.is_none() {
+                    plan.changes.created_relations.push(qualified);
+                } else if changed {
+                    plan.changes.updated_relations.push(qualified);
+                }
+            }
+
+            let desired: HashSet<&str> = namespace
+                .relations
+                .iter()
+                .map(|relation| relation.name.as_str())
+                .collect();
+            for name in live_relations(id) {
+                if !desired.contains(name) {
+                    plan.statements.push(Statement::DeleteRelation {
+                        namespace: id,
+                        name,
+                    });
+                    plan.remove_relation(id, name);
+                }
+            }
+        }
+
+        let desired: HashSet<&str> = schema
+            .iter()
+            .map(|namespace| namespace.id.as_str())
+            .collect();
+        for namespace in namespaces {
+            let id = namespace.id.as_str();
+            if desired.contains(id) {
+                continue;
+            }
+            for name in live_relations(id) {
+                plan.remove_relation(id, name);
+            }
+            // Soft-deleted relations go for good along with the namespace, tuples left in them
+            // would block that.
+            let mut deleted_relations: Vec<&'a str> = relations
+                .values()
+                .filter(|relation| relation.namespace_id == id && relation.deleted_at.is_some())
+                .map(|relation| relation.name.as_str())
+                .collect();
+            deleted_relations.sort_unstable();
+            plan.removals
+                .extend(deleted_relations.into_iter().map(|name| Removal {
+                    namespace: id,
+                    relation: Some(name),
+                }));
+            plan.removals.push(Removal {
+                namespace: id,
+                relation: None,
+            });
+            plan.statements.push(Statement::DeleteNamespace(id));
+            plan.changes.deleted_namespaces.push(id.to_string());
+        }
+        plan
+    }
+
+    fn remove_relation(&mut self, namespace: &'a str, name: &'a str) {
+        self.removals.push(Removal {
+            namespace,
+            relation: Some(name),
+        });
+        self.changes
+            .deleted_relations
+            .push(format!("{namespace}#{name}"));
+    }
+
+    fn removes(&self, namespace: &str, relation: Option<&str>) -> bool {
+        self.removals
+            .iter()
+            .any(|removal| removal.namespace == namespace && removal.relation == relation)
+    }
+}
+
+/// Permissions of `schema`, as `namespace#relation`, whose rule refers to `relation` of
+/// `namespace`: through `child_relations` within the namespace, as the `ttu_relation` of a
+/// tuple-to-userset whose target, or lacking one whose tupleset's subject types, name it, or as
+/// the computed relation of an arrow of its expression whose tupleset's subject types name it.
+pub(super) fn references(
+    schema: &[NamespaceSchema],
+    namespace: &str,
+    relation: &str,
+) -> Vec<String> {
+    let mut references = Vec::new();
+    for desired in schema {
+        for permission in &desired.relations {
+            let Some(rule) = &permission.rule else {
+                continue;
+            };
+            let targets = |tupleset: &str| {
+                tupleset_targets(desired, tupleset).any(|target| target == namespace)
+            };
+            let children = rule.child_relations.as_deref().unwrap_or_default();
+            let by_child = desired.id == namespace && children.iter().any(|c| c == relation);
+            let by_ttu = rule.rule_type == RuleType::TupleToUserset
+                && rule.ttu_relation.as_deref() == Some(relation)
+                && match &rule.ttu_object_namespace {
+                    Some(target) => target == namespace,
+                    None => children.first().is_some_and(|tupleset| targets(tupleset)),
+                };
+            let by_arrow = Expression::parse(&rule.expression).is_ok_and(|expression| {
+                expression
+                    .arrows()
+                    .into_iter()
+                    .any(|(tupleset, computed)| computed == relation && targets(tupleset))
+            });
+            let name = format!("{}#{}", desired.id, permission.name);
+            if (by_child || by_ttu || by_arrow) && !references.contains(&name) {
+                references.push(name);
+            }
+        }
+    }
+    references
+}
+
+/// The live `relations` and `rules` in the shape [`references`] reads, one [`RelationSchema`]
+/// per rule of a relation.
+pub(super) fn stored_schema(
+    relations: &[Relation],
+    rules: &[RelationRule],
+) -> Vec<NamespaceSchema> {
+    let mut schema: Vec<NamespaceSchema> = Vec::new();
+    for relation in relations
+        .iter()
+        .filter(|relation| relation.deleted_at.is_none())
+    {
+        let subject_types = relation.subject_types.as_ref().map(|types| types.0.clone());
+        let mut relation_schemas: Vec<RelationSchema> = rules
+            .iter()
+            .filter(|rule| {
+                rule.deleted_at.is_none()
+                    && rule.namespace_id == relation.namespace_id
+                    && rule.relation_name == relation.name
+            })
+            .map(|rule| RelationSchema {
+                name: relation.name.clone(),
+                subject_types: subject_types.clone(),
+                rule: Some(RuleSchema {
+                    rule_type: rule.rule_type,
+                    ttu_object_namespace: rule.ttu_object_namespace.clone(),
+                    ttu_relation: rule.ttu_relation.clone(),
+                    child_relations: rule.child_relations.as_ref().map(|c| c.0.clone()),
+                    expression: rule.expression.clone().unwrap_or_default(),
+                }),
+            })
+            .collect();
+        if relation_schemas.is_empty() {
+            relation_schemas.push(RelationSchema {
+                name: relation.name.clone(),
+                subject_types,
+                rule: None,
+            });
+        }
+        match schema
+            .iter_mut()
+            .find(|namespace| namespace.id == relation.namespace_id)
+        {
+            Some(namespace) => namespace.relations.extend(relation_schemas),
+            None => schema.push(NamespaceSchema {
+                id: relation.namespace_id.clone(),
+                relations: relation_schemas,
+            }),
+        }
+    }
+    schema
+}
+
+/// Namespaces the subjects of `tupleset` are declared to be in.
+fn tupleset_targets<'a>(
+    namespace: &'a NamespaceSchema,
+    tupleset: &str,
+) -> impl Iterator<Item = &'a str> {
+    namespace
+        .relations
+        .iter()
+        .filter(move |relation| relation.name == tupleset)
+        .flat_map(|relation| relation.subject_types.iter().flatten())
+        .map(|subject_type| {
+            subject_type
+                .split([':', '#'])
+                .next()
+                .unwrap_or(subject_type)
+        })
+}
+
+fn migration_target<'a>(
+    migrations: &'a [RelationMigration],
+    namespace: &str,
+    relation: &str,
+) -> Option<&'a str> {
+    migrations
+        .iter()
+        .find(|migration| migration.namespace == namespace && migration.from == relation)
+        .map(|migration| migration.to.as_str())
+}
+
+/// `tuple` with its removed relation and subject relation replaced by their migration targets,
+/// `None` if it refers to something removed that is not migrated.
+fn migrate(
+    plan: &SchemaPlan<'_>,
+    migrations: &[RelationMigration],
+    tuple: &TupleKey,
+) -> Option<TupleKey> {
+    let mut migrated = tuple.clone();
+    if plan.removes(&tuple.namespace, Some(&tuple.relation)) {
+        migrated.relation =
+            migration_target(migrations, &tuple.namespace, &tuple.relation)?.to_string();
+    }
+    match &tuple.subject_relation {
+        Some(subject_relation) if plan.removes(&tuple.subject_type, Some(subject_relation)) => {
+            migrated.subject_relation = Some(
+                migration_target(migrations, &tuple.subject_type, subject_relation)?.to_string(),
+            );
+        }
+        None if plan.removes(&tuple.subject_type, None) => return None,
+        _ => {}
+    }
+    Some(migrated)
+}
@@ -0,0 +1,48 @@
+use crate::{
+    database::{DatabasePool, with_pool},
+    entities::TransactionLogEntry,
+};
+
+#[derive(Debug, Clone)]
+pub struct TransactionLogRepository {
+    pool: DatabasePool,
+}
+
+impl TransactionLogRepository {
+    pub fn new(pool: DatabasePool) -> Self {
+        Self { pool }
+    }
+
+    /// Entries of the versions after `after` up to and including `up_to`, limited to
+    /// `namespaces` unless it is empty, ordered by version and tuple key.
+    pub async fn find_between(
+        &self,
+        after: i64,
+        up_to: i64,
+        namespaces: &[String],
+    ) -> Result<Vec<TransactionLogEntry>, sqlx::Error> {
+        let namespace_condition = if namespaces.is_empty() {
+            String::new()
+        } else {
+            let placeholders = (0..namespaces.len())
+                .map(|index| format!("${}", index + 3))
+                .collect::<Vec<_>>()
+                .join(", ");
+            format!(" AND namespace_id IN ({placeholders})")
+        };
+        let sql = format!(
+            "SELECT * FROM transaction_log \
+             WHERE version_number > $1 AND version_number <= $2{namespace_condition} \
+             ORDER BY version_number, namespace_id, object_id, relation, subject_type, \
+             subject_id, COALESCE(userset_relation, '')"
+        );
+
+        with_pool!(&self.pool, |pool| {
+            let mut query = sqlx::query_as(&sql).bind(after).bind(up_to);
+            for namespace in namespaces {
+                query = query.bind(namespace);
+            }
+            query.fetch_all(pool).await
+        })
+    }
+}
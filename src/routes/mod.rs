@@ -1 +1,74 @@
+use axum::{
+    Router,
+    extract::DefaultBodyLimit,
+    middleware,
+    routing::{get, post},
+};
+use tower_http::{
+    request_id::{MakeRequestUuid, PropagateRequestIdLayer, SetRequestIdLayer},
+    trace::TraceLayer,
+};
 
+use crate::{
+    handlers::{check, expand, health, lookup, namespace, relation, relationship, schema, watch},
+    middlewares::{request_id, trace},
+    state::AppState,
+};
+
+/// Largest `POST /schema` body accepted, far beyond any real schema, so parsing and validating
+/// one stays cheap.
+const MAX_SCHEMA_BODY_BYTES: usize = 1024 * 1024;
+
+pub fn create_router(app_state: AppState) -> Router {
+    // Layers wrap everything added before them, so the request id is normalized first, then
+    // assigned if there is none, then the request span is opened, and the id is copied to the
+    // response last.
+    Router::new()
+        .route("/health", get(health::health_check))
+        .route(
+            "/namespaces",
+            get(namespace::list_namespaces).post(namespace::create_namespace),
+        )
+        .route(
+            "/namespaces/{namespace_id}",
+            get(namespace::get_namespace)
+                .put(namespace::update_namespace)
+                .delete(namespace::delete_namespace),
+        )
+        .route(
+            "/namespaces/{namespace_id}/relations",
+            get(relation::list_relations).post(relation::create_relation),
+        )
+        .route(
+            "/namespaces/{namespace_id}/relations/{relation_name}",
+            get(relation::get_relation)
+                .put(relation::update_relation)
+                .delete(relation::delete_relation),
+        )
+        .route(
+            "/schema",
+            get(schema::read_schema)
+                .post(schema::write_schema)
+                .layer(DefaultBodyLimit::max(MAX_SCHEMA_BODY_BYTES)),
+        )
+        .route("/check", post(check::check))
+        .route("/check/bulk", post(check::check_bulk))
+        .route("/expand", post(expand::expand))
+        .route("/lookup/resources", post(lookup::lookup_resources))
+        .route("/lookup/subjects", post(lookup::lookup_subjects))
+        .route("/relationships", get(relationship::read_relationships))
+        .route("/watch", get(watch::watch))
+        .route(
+            "/relationships/write",
+            post(relationship::write_relationships),
+        )
+        .layer(PropagateRequestIdLayer::x_request_id())
+        .layer(
+            TraceLayer::new_for_http()
+                .make_span_with(trace::make_span)
+                .on_response(trace::on_response),
+        )
+        .layer(SetRequestIdLayer::x_request_id(MakeRequestUuid))
+        .layer(middleware::from_fn(request_id::normalize_request_id))
+        .with_state(app_state)
+}
@@ -0,0 +1,158 @@
+use std::{
+    collections::HashMap,
+    sync::{
+        Arc, Mutex,
+        atomic::{AtomicU64, Ordering},
+    },
+    time::Duration,
+};
+
+use tokio::{
+    sync::{mpsc, watch},
+    task::JoinHandle,
+};
+use uuid::Uuid;
+
+use crate::{
+    config::AuditConfig, database::DatabasePool, entities::AuthDecision,
+    repositories::AuthDecisionRepository,
+};
+
+/// Records check decisions in `auth_decisions` off the request path.
+///
+/// Decisions are queued and written in batches by a background task, so a check never waits
+/// for its decision to be stored. A decision that does not fit the queue is dropped and
+/// counted, the next batch written logs how many were lost.
+#[derive(Debug, Clone)]
+pub struct DecisionRecorder {
+    sender: mpsc::Sender<AuthDecision>,
+    sample_rate: f64,
+    namespace_sample_rates: Arc<HashMap<String, f64>>,
+    dropped: Arc<AtomicU64>,
+    closed: Arc<watch::Sender<bool>>,
+    writer: Arc<Mutex<Option<JoinHandle<()>>>>,
+}
+
+impl DecisionRecorder {
+    /// Starts the background task writing the decisions to `pool`.
+    pub fn new(pool: DatabasePool, audit_config: &AuditConfig) -> Self {
+        let (sender, receiver) = mpsc::channel(audit_config.buffer_size);
+        let closed = Arc::new(watch::channel(false).0);
+        let dropped = Arc::new(AtomicU64::new(0));
+        let writer = Writer {
+            repository: AuthDecisionRepository::new(pool),
+            receiver,
+            closed: closed.subscribe(),
+            dropped: dropped.clone(),
+            batch_size: audit_config.batch_size,
+            flush_interval: Duration::from_millis(audit_config.flush_interval_ms),
+        };
+        Self {
+            sender,
+            sample_rate: audit_config.sample_rate,
+            namespace_sample_rates: Arc::new(audit_config.namespace_sample_rates.clone()),
+            dropped,
+            closed,
+            writer: Arc::new(Mutex::new(Some(tokio::spawn(writer.run())))),
+        }
+    }
+
+    /// Whether the decision `id` of a check on `namespace` is to be recorded, at the sample
+    /// rate of the namespace. Decision ids are random, so they double as the sampling draw.
+    pub fn is_sampled(&self, namespace: &str, id: Uuid) -> bool {
+        let rate = self
+            .namespace_sample_rates
+            .get(namespace)
+            .copied()
+            .unwrap_or(self.sample_rate);
+        // The low 62 bits of a v4 UUID are random, the two above them are fixed.
+        let draw = (id.as_u64_pair().1 & (u64::MAX >> 2)) as f64 / (1u64 << 62) as f64;
+        draw < rate
+    }
+
+    /// Queues `decisions` to be written, dropping those the queue has no room for.
+    pub fn record(&self, decisions: Vec<AuthDecision>) {
+        for decision in decisions {
+            if self.sender.try_send(decision).is_err() {
+                self.dropped.fetch_add(1, Ordering::Relaxed);
+            }
+        }
+    }
+
+    /// Writes the decisions still queued and stops the background task. Decisions recorded
+    /// afterwards are dropped.
+    pub async fn close(&self) {
+        self.closed.send_replace(true);
+        let writer = self
+            .writer
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+            .take();
+        if let Some(writer) = writer
+            && let Err(error) = writer.await
+        {
+            tracing::warn!(%error, "auth decision writer failed");
+        }
+    }
+}
+
+struct Writer {
+    repository: AuthDecisionRepository,
+    receiver: mpsc::Receiver<AuthDecision>,
+    closed: watch::Receiver<bool>,
+    dropped: Arc<AtomicU64>,
+    batch_size: usize,
+    flush_interval: Duration,
+}
+
+impl Writer {
+    /// Writes a batch whenever `batch_size` decisions are queued or `flush_interval` has passed,
+    /// until the recorder is closed or dropped.
+    async fn run(mut self) {
+        let mut batch = Vec::with_capacity(self.batch_size);
+        let mut flush = tokio::time::interval(self.flush_interval);
+        loop {
+            let room = self.batch_size - batch.len();
+            tokio::select! {
+                received = self.receiver.recv_many(&mut batch, room) => {
+                    if received == 0 {
+                        break;
+                    }
+                    if batch.len() < self.batch_size {
+                        continue;
+                    }
+                }
+                _ = flush.tick() => {}
+                _ = self.closed.changed() => {
+                    self.receiver.close();
+                    break;
+                }
+            }
+            self.write(&mut batch).await;
+        }
+
+        // Whatever was queued before closing is still written.
+        while let Ok(decision) = self.receiver.try_recv() {
+            batch.push(decision);
+            if batch.len() == self.batch_size {
+                self.write(&mut batch).await;
+            }
+        }
+        self.write(&mut batch).await;
+    }
+
+    async fn write(&self, batch: &mut Vec<AuthDecision>) {
+        let dropped = self.dropped.swap(0, Ordering::Relaxed);
+        if dropped > 0 {
+            tracing::warn!(dropped, "auth decision buffer full, decisions dropped");
+        }
+        if batch.is_empty() {
+            return;
+        }
+        // The answers stand even if they cannot be audited.
+        if let Err(error) = self.repository.insert_all(batch).await {
+            tracing::warn!(%error, decisions = batch.len(), "failed to record auth decisions");
+        }
+        batch.clear();
+    }
+}
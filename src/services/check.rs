@@ -0,0 +1,324 @@
+use std::{future::Future, time::Instant};
+
+use chrono::{TimeDelta, Utc};
+use sqlx::types::Json;
+use tracing::{Instrument, field};
+use uuid::Uuid;
+
+use crate::{
+    config::{CacheConfig, EvaluationConfig},
+    database::DatabasePool,
+    entities::{AuthDecision, PermissionsCacheEntry},
+    error::{HeimdallError, HeimdallResult},
+    models::{Consistency, ObjectRelation, Snapshot, SubjectRef},
+    repositories::{PermissionsCacheRepository, permissions_cache::cache_key},
+};
+
+use super::{
+    audit::DecisionRecorder,
+    check_cache::CheckCache,
+    consistency::ConsistencyResolver,
+    evaluator::{EvaluationSource, Evaluator},
+    relationship::{validate_object_relation, validate_subject},
+};
+
+/// Most checks a single bulk check may hold.
+pub const MAX_BULK_CHECK_ITEMS: usize = 1_000;
+
+/// Answers whether a subject holds a relation on an object.
+#[derive(Debug, Clone)]
+pub struct CheckService {
+    source: EvaluationSource,
+    consistency: ConsistencyResolver,
+    decisions: DecisionRecorder,
+    permissions_cache: PermissionsCacheRepository,
+    check_cache: CheckCache,
+    /// How long results stay in `permissions_cache`, `None` if they are not cached.
+    cache_ttl: Option<TimeDelta>,
+}
+
+impl CheckService {
+    pub fn new(
+        pool: DatabasePool,
+        evaluation_config: &EvaluationConfig,
+        cache_config: &CacheConfig,
+        check_cache: CheckCache,
+        decisions: DecisionRecorder,
+        consistency: ConsistencyResolver,
+    ) -> Self {
+        let ttl_secs = cache_config.permissions_cache_ttl_secs;
+        Self {
+            source: EvaluationSource::new(pool.clone(), evaluation_config),
+            consistency,
+            decisions,
+            permissions_cache: PermissionsCacheRepository::new(pool),
+            check_cache,
+            cache_ttl: (ttl_secs > 0).then(|| TimeDelta::seconds(ttl_secs as i64)),
+        }
+    }
+
+    /// Evaluates the check at a snapshot satisfying `consistency` and records the decision in
+    /// `auth_decisions` under `request_id`, if it is sampled. A sampled decision lists the rules
+    /// and tuples the check went through as its `evaluation_path`.
+    ///
+    /// The check and the usersets it reaches are answered from the in-process cache where they
+    /// were computed at the snapshot's version, and from `permissions_cache` where the cached
+    /// result is recent enough for `consistency`. The results computed instead are kept in
+    /// process and cached in `permissions_cache` in the background.
+    pub async fn check(
+        &self,
+        object: &ObjectRelation,
+        subject: &SubjectRef,
+        consistency: &Consistency,
+        request_id: Uuid,
+    ) -> HeimdallResult<(bool, Snapshot)> {
+        in_check_span(
+            request_id,
+            object,
+            self.check_at_snapshot(object, subject, consistency, request_id),
+        )
+        .await
+    }
+
+    async fn check_at_snapshot(
+        &self,
+        object: &ObjectRelation,
+        subject: &SubjectRef,
+        consistency: &Consistency,
+        request_id: Uuid,
+    ) -> HeimdallResult<(bool, Snapshot)> {
+        validate_object_relation(object)?;
+        validate_subject(subject)?;
+
+        let started = Instant::now();
+        let snapshot = self.consistency.resolve(consistency).await?;
+        let request = DecisionRequest {
+            request_id,
+            started,
+            snapshot: &snapshot,
+        };
+        let mut evaluator = self.evaluator(consistency, &snapshot)?;
+        let id = self.start_decision(&mut evaluator, object);
+        let allowed = evaluator.check(object, subject).await?;
+        let served = self
+            .consistency
+            .served_at(&snapshot, evaluator.served_version());
+        tracing::debug!(
+            %object,
+            %subject,
+            allowed,
+            cached = evaluator.is_cached(object, subject),
+            version = served.version,
+            "check evaluated"
+        );
+        let decisions = id
+            .map(|id| {
+                request.decision(id, object, subject, allowed, &served.zookie, &mut evaluator)
+            })
+            .into_iter()
+            .collect();
+        self.finish(evaluator, decisions);
+        Ok((allowed, served))
+    }
+
+    /// Evaluates every check of `items` at one snapshot satisfying `consistency`, and records
+    /// the sampled decisions in `auth_decisions` under `request_id`.
+    ///
+    /// The items share a single evaluation, so tuples and usersets several of them reach are
+    /// read and evaluated once. An item that is invalid or fails to evaluate gets its own error
+    /// and leaves the others alone, only failing to resolve the snapshot fails the whole batch.
+    pub async fn check_bulk(
+        &self,
+        items: &[(ObjectRelation, SubjectRef)],
+        consistency: &Consistency,
+        request_id: Uuid,
+    ) -> HeimdallResult<(Vec<HeimdallResult<bool>>, Snapshot)> {
+        if items.len() > MAX_BULK_CHECK_ITEMS {
+            return Err(HeimdallError::InvalidArgument(format!(
+                "a bulk check may hold at most {MAX_BULK_CHECK_ITEMS} items, got {}",
+                items.len()
+            )));
+        }
+
+        let snapshot = self.consistency.resolve(consistency).await?;
+        let mut evaluator = self.evaluator(consistency, &snapshot)?;
+        let mut results = Vec::with_capacity(items.len());
+        let mut decisions = Vec::new();
+        for (object, subject) in items {
+            let allowed = match validate_object_relation(object)
+                .and_then(|()| validate_subject(subject))
+            {
+                Ok(()) => {
+                    let request = DecisionRequest {
+                        request_id,
+                        started: Instant::now(),
+                        snapshot: &snapshot,
+                    };
+                    let id = self.start_decision(&mut evaluator, object);
+                    let allowed =
+                        in_check_span(request_id, object, evaluator.check(object, subject)).await;
+                    if let (Some(id), Ok(allowed)) = (id, &allowed) {
+                        let served = self
+                            .consistency
+                            .served_at(&snapshot, evaluator.served_version());
+                        decisions.push(request.decision(
+                            id,
+                            object,
+                            subject,
+                            *allowed,
+                            &served.zookie,
+                            &mut evaluator,
+                        ));
+                    }
+                    // Nothing of a failed check is recorded, the next one starts afresh.
+                    evaluator.take_path();
+                    allowed
+                }
+                Err(error) => Err(error),
+            };
+            results.push(allowed);
+        }
+        let served = self
+            .consistency
+            .served_at(&snapshot, evaluator.served_version());
+        tracing::debug!(
+            items = items.len(),
+            version = served.version,
+            "bulk check evaluated"
+        );
+        self.finish(evaluator, decisions);
+        Ok((results, served))
+    }
+
+    /// Evaluator at `snapshot` using whichever caches are enabled.
+    fn evaluator(
+        &self,
+        consistency: &Consistency,
+        snapshot: &Snapshot,
+    ) -> HeimdallResult<Evaluator> {
+        let mut evaluator = Evaluator::new(self.source.clone(), snapshot.version);
+        if self.check_cache.is_enabled() {
+            evaluator = evaluator.with_local_cache(self.check_cache.clone());
+        }
+        if self.cache_ttl.is_some() {
+            let min_version = self.consistency.min_version(consistency, snapshot)?;
+            evaluator = evaluator.with_cache(self.permissions_cache.clone(), min_version);
+        }
+        Ok(evaluator)
+    }
+
+    /// Id of the decision of the next check on `object` if it is sampled, in which case
+    /// `evaluator` records the path of the check.
+    fn start_decision(&self, evaluator: &mut Evaluator, object: &ObjectRelation) -> Option<Uuid> {
+        let id = Uuid::new_v4();
+        let sampled = self.decisions.is_sampled(&object.namespace, id);
+        if sampled {
+            evaluator.record_path();
+        }
+        sampled.then_some(id)
+    }
+
+    /// Caches the results `evaluator` computed and queues `decisions` to be recorded.
+    fn finish(&self, mut evaluator: Evaluator, decisions: Vec<AuthDecision>) {
+        let (computed, version) = evaluator.take_computed();
+        for (object, subject, allowed) in &computed {
+            self.check_cache
+                .insert(object.clone(), subject.clone(), version, *allowed);
+        }
+        self.cache_results(computed, version);
+        self.decisions.record(decisions);
+    }
+
+    /// Writes `results`, computed at `version`, to `permissions_cache` without holding up the
+    /// check.
+    fn cache_results(&self, results: Vec<(ObjectRelation, SubjectRef, bool)>, version: i64) {
+        let Some(ttl) = self.cache_ttl.filter(|_| !results.is_empty()) else {
+            return;
+        };
+        let now = Utc::now();
+        let entries: Vec<PermissionsCacheEntry> = results
+            .into_iter()
+            .map(|(object, subject, permitted)| PermissionsCacheEntry {
+                id: Uuid::new_v4(),
+                cache_key: cache_key(&object, &subject),
+                namespace_id: object.namespace,
+                object_id: object.object_id,
+                relation: object.relation,
+                subject_type: subject.subject_type,
+                subject_id: subject.subject_id,
+                permitted,
+                computed_at: now,
+                valid_until: now + ttl,
+                max_zookie_version: version,
+            })
+            .collect();
+        let permissions_cache = self.permissions_cache.clone();
+        tokio::spawn(async move {
+            if let Err(error) = permissions_cache.upsert_all(&entries).await {
+                tracing::warn!(%error, "failed to cache check results");
+            }
+        });
+    }
+}
+
+/// Runs `check` in a `check` span of its own, which records how long it took once it is done.
+async fn in_check_span<T>(
+    request_id: Uuid,
+    object: &ObjectRelation,
+    check: impl Future<Output = T>,
+) -> T {
+    let span = tracing::info_span!(
+        "check",
+        %request_id,
+        namespace = %object.namespace,
+        relation = %object.relation,
+        latency_ms = field::Empty,
+    );
+    let started = Instant::now();
+    let output = check.instrument(span.clone()).await;
+    span.record("latency_ms", started.elapsed().as_secs_f64() * 1000.0);
+    output
+}
+
+/// What the decision of a check has in common with the others of its request.
+struct DecisionRequest<'a> {
+    request_id: Uuid,
+    /// When the check started: with the request for a single check, which includes resolving
+    /// the snapshot, and with the item for each item of a bulk check.
+    started: Instant,
+    snapshot: &'a Snapshot,
+}
+
+impl DecisionRequest<'_> {
+    /// Decision `id` of checking `subject` against `object`, which `evaluator` found `allowed`
+    /// as of `zookie` and recorded the path of.
+    fn decision(
+        &self,
+        id: Uuid,
+        object: &ObjectRelation,
+        subject: &SubjectRef,
+        allowed: bool,
+        zookie: &str,
+        evaluator: &mut Evaluator,
+    ) -> AuthDecision {
+        let cached = evaluator.is_cached(object, subject);
+        let evaluation_path = serde_json::to_value(evaluator.take_path()).unwrap_or_default();
+        AuthDecision {
+            id,
+            timestamp: Utc::now(),
+            request_id: self.request_id,
+            subject_type: subject.subject_type.clone(),
+            subject_id: subject.subject_id.clone(),
+            namespace_id: object.namespace.clone(),
+            object_id: object.object_id.clone(),
+            relation: object.relation.clone(),
+            permitted: allowed,
+            cached,
+            latency_ms: i32::try_from(self.started.elapsed().as_millis()).unwrap_or(i32::MAX),
+            evaluation_path: Json(evaluation_path),
+            zookie_token: Some(zookie.to_string()),
+            waited_for_consistency: self.snapshot.waited_for_consistency,
+            consistency_wait_ms: self.snapshot.consistency_wait_ms,
+        }
+    }
+}
@@ -0,0 +1,286 @@
+use std::{
+    collections::{BTreeMap, HashMap},
+    sync::{
+        Arc, Mutex, MutexGuard,
+        atomic::{AtomicU64, Ordering},
+    },
+    time::{Duration, Instant},
+};
+
+use serde::Serialize;
+
+use crate::{
+    config::CacheConfig,
+    models::{ObjectRelation, SubjectRef},
+};
+
+/// A check of a subject against an object at a snapshot version.
+type CheckKey = (ObjectRelation, SubjectRef, i64);
+
+/// Counters of a [`CheckCache`] since the process started.
+#[derive(Debug, Clone, Copy, Serialize)]
+pub struct CheckCacheStats {
+    pub hits: u64,
+    pub misses: u64,
+    pub entries: usize,
+}
+
+/// Check results of this process, least recently used first out once `capacity` is reached.
+///
+/// Results are keyed by the snapshot version they were computed at and only served to checks
+/// evaluated at that exact version. A write advances the version, so results predating it are
+/// never served again, on any node, without having to be invalidated.
+#[derive(Debug, Clone)]
+pub struct CheckCache {
+    inner: Arc<Inner>,
+}
+
+#[derive(Debug)]
+struct Inner {
+    capacity: usize,
+    ttl: Duration,
+    entries: Mutex<Entries>,
+    hits: AtomicU64,
+    misses: AtomicU64,
+}
+
+#[derive(Debug, Default)]
+struct Entries {
+    /// Result, expiry and last use of every cached check.
+    results: HashMap<CheckKey, Entry>,
+    /// Key of every cached check by its last use, oldest first.
+    recency: BTreeMap<u64, CheckKey>,
+    /// Incremented on every use, orders `recency`.
+    clock: u64,
+}
+
+#[derive(Debug)]
+struct Entry {
+    allowed: bool,
+    expires_at: Instant,
+    used_at: u64,
+}
+
+impl CheckCache {
+    pub fn new(cache_config: &CacheConfig) -> Self {
+        Self {
+            inner: Arc::new(Inner {
+                capacity: cache_config.check_cache_capacity,
+                ttl: Duration::from_secs(cache_config.check_cache_ttl_secs),
+                entries: Mutex::default(),
+                hits: AtomicU64::new(0),
+                misses: AtomicU64::new(0),
+            }),
+        }
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.inner.capacity > 0
+    }
+
+    /// Cached result of checking `subject` against `object` at `version`, counted as a hit or a
+    /// miss.
+    pub fn get(&self, object: &ObjectRelation, subject: &SubjectRef, version: i64) -> Option<bool> {
+        if !self.is_enabled() {
+            return None;
+        }
+        let key = (object.clone(), subject.clone(), version);
+        let allowed = self.entries().get(&key);
+        let counter = match allowed {
+            Some(_) => &self.inner.hits,
+            None => &self.inner.misses,
+        };
+        counter.fetch_add(1, Ordering::Relaxed);
+        allowed
+    }
+
+    /// Caches the result of checking `subject` against `object` at `version`, evicting the least
+    /// recently used result if the cache is full.
+    pub fn insert(&self, object: ObjectRelation, subject: SubjectRef, version: i64, allowed: bool) {
+        if !self.is_enabled() {
+            return;
+        }
+        let expires_at = Instant::now() + self.inner.ttl;
+        self.entries().insert(
+            (object, subject, version),
+            allowed,
+            expires_at,
+            self.inner.capacity,
+        );
+    }
+
+    /// Drops every cached result, for changes that do not advance the version.
+    pub fn clear(&self) {
+        let mut entries = self.entries();
+        entries.results.clear();
+        entries.recency.clear();
+    }
+
+    pub fn stats(&self) -> CheckCacheStats {
+        CheckCacheStats {
+            hits: self.inner.hits.load(Ordering::Relaxed),
+            misses: self.inner.misses.load(Ordering::Relaxed),
+            entries: self.entries().results.len(),
+        }
+    }
+
+    fn entries(&self) -> MutexGuard<'_, Entries> {
+        // The entries are consistent between statements, a panic elsewhere cannot corrupt them.
+        self.inner
+            .entries
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+impl Entries {
+    fn get(&mut self, key: &CheckKey) -> Option<bool> {
+        let entry = self.results.get(key)?;
+        if entry.expires_at <= Instant::now() {
+            self.remove(key);
+            return None;
+        }
+        let (allowed, used_at) = (entry.allowed, entry.used_at);
+        let now = self.tick();
+        if let Some(key) = self.recency.remove(&used_at) {
+            self.recency.insert(now, key);
+        }
+        if let Some(entry) = self.results.get_mut(key) {
+            entry.used_at = now;
+        }
+        Some(allowed)
+    }
+
+    fn insert(&mut self, key: CheckKey, allowed: bool, expires_at: Instant, capacity: usize) {
+        self.remove(&key);
+        while self.results.len() >= capacity {
+            let Some((_, oldest)) = self.recency.pop_first() else {
+                break;
+            };
+            self.results.remove(&oldest);
+        }
+        let used_at = self.tick();
+        self.recency.insert(used_at, key.clone());
+        self.results.insert(
+            key,
+            Entry {
+                allowed,
+                expires_at,
+                used_at,
+            },
+        );
+    }
+
+    fn remove(&mut self, key: &CheckKey) {
+        if let Some(entry) = self.results.remove(key) {
+            self.recency.remove(&entry.used_at);
+        }
+    }
+
+    fn tick(&mut self) -> u64 {
+        self.clock += 1;
+        self.clock
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn cache(capacity: usize, ttl_secs: u64) -> CheckCache {
+        CheckCache::new(&CacheConfig {
+            check_cache_capacity: capacity,
+            check_cache_ttl_secs: ttl_secs,
+            ..CacheConfig::default()
+        })
+    }
+
+    fn document(object_id: &str) -> ObjectRelation {
+        ObjectRelation {
+            namespace: "document".to_string(),
+            object_id: object_id.to_string(),
+            relation: "view".to_string(),
+        }
+    }
+
+    fn alice() -> SubjectRef {
+        SubjectRef {
+            subject_type: "user".to_string(),
+            subject_id: "alice".to_string(),
+            subject_relation: None,
+        }
+    }
+
+    #[test]
+    fn serves_results_at_their_version_only() {
+        let cache = cache(10, 60);
+        cache.insert(document("1"), alice(), 7, true);
+        assert_eq!(cache.get(&document("1"), &alice(), 7), Some(true));
+        assert_eq!(cache.get(&document("1"), &alice(), 8), None);
+        assert_eq!(cache.get(&document("2"), &alice(), 7), None);
+
+        let stats = cache.stats();
+        assert_eq!((stats.hits, stats.misses, stats.entries), (1, 2, 1));
+    }
+
+    #[test]
+    fn evicts_the_least_recently_used_result() {
+        let cache = cache(2, 60);
+        cache.insert(document("1"), alice(), 1, true);
+        cache.insert(document("2"), alice(), 1, false);
+        // Using the first result makes the second the least recently used.
+        assert_eq!(cache.get(&document("1"), &alice(), 1), Some(true));
+        cache.insert(document("3"), alice(), 1, true);
+
+        assert_eq!(cache.get(&document("2"), &alice(), 1), None);
+        assert_eq!(cache.get(&document("1"), &alice(), 1), Some(true));
+        assert_eq!(cache.get(&document("3"), &alice(), 1), Some(true));
+        assert_eq!(cache.stats().entries, 2);
+    }
+
+    #[test]
+    fn replacing_a_result_does_not_evict_another() {
+        let cache = cache(2, 60);
+        cache.insert(document("1"), alice(), 1, true);
+        cache.insert(document("2"), alice(), 1, true);
+        cache.insert(document("1"), alice(), 1, false);
+
+        assert_eq!(cache.get(&document("1"), &alice(), 1), Some(false));
+        assert_eq!(cache.get(&document("2"), &alice(), 1), Some(true));
+    }
+
+    #[test]
+    fn expired_results_are_misses() {
+        let cache = cache(10, 0);
+        cache.insert(document("1"), alice(), 1, true);
+        assert_eq!(cache.get(&document("1"), &alice(), 1), None);
+        assert_eq!(cache.stats().entries, 0);
+    }
+
+    #[test]
+    fn clear_drops_every_result() {
+        let cache = cache(2, 60);
+        cache.insert(document("1"), alice(), 1, true);
+        cache.insert(document("2"), alice(), 1, false);
+        cache.clear();
+
+        assert_eq!(cache.stats().entries, 0);
+        assert_eq!(cache.get(&document("1"), &alice(), 1), None);
+        // The cache keeps working, and evicting, after being cleared.
+        for object_id in ["3", "4", "5"] {
+            cache.insert(document(object_id), alice(), 1, true);
+        }
+        assert_eq!(cache.stats().entries, 2);
+        assert_eq!(cache.get(&document("3"), &alice(), 1), None);
+    }
+
+    #[test]
+    fn a_disabled_cache_holds_nothing() {
+        let cache = cache(0, 60);
+        assert!(!cache.is_enabled());
+        cache.insert(document("1"), alice(), 1, true);
+        assert_eq!(cache.get(&document("1"), &alice(), 1), None);
+        let stats = cache.stats();
+        assert_eq!((stats.hits, stats.misses, stats.entries), (0, 0, 0));
+    }
+}
@@ -0,0 +1,272 @@
+use std::{
+    fmt,
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+use base64::{Engine, engine::general_purpose::URL_SAFE_NO_PAD};
+use hmac::{Hmac, Mac};
+use sha2::Sha256;
+
+use crate::{
+    config::ConsistencyConfig,
+    database::DatabasePool,
+    error::{HeimdallError, HeimdallResult},
+    models::{Consistency, Snapshot},
+    repositories::RelationshipRepository,
+};
+
+type HmacSha256 = Hmac<Sha256>;
+
+/// First byte of every zookie, bumped whenever the layout below changes.
+const ZOOKIE_FORMAT: u8 = 1;
+
+/// Bytes of the HMAC kept in a zookie, enough to make forging one impractical.
+const ZOOKIE_MAC_LENGTH: usize = 16;
+
+/// Format byte, big-endian version, truncated MAC.
+const ZOOKIE_LENGTH: usize = 1 + 8 + ZOOKIE_MAC_LENGTH;
+
+/// Delay between two reads of the current version while waiting for a zookie.
+const POLL_INTERVAL: Duration = Duration::from_millis(10);
+
+/// Issues and verifies zookies: opaque, URL-safe tokens naming a version of the relationship
+/// tuples, signed so clients can neither forge nor alter them.
+///
+/// A zookie depends on nothing but the version and the key, so every node sharing the key
+/// issues the same token for the same version.
+#[derive(Clone)]
+pub struct ZookieCodec {
+    key: Arc<[u8]>,
+}
+
+impl fmt::Debug for ZookieCodec {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ZookieCodec").finish_non_exhaustive()
+    }
+}
+
+impl ZookieCodec {
+    pub fn new(consistency_config: &ConsistencyConfig) -> Self {
+        Self {
+            key: consistency_config.zookie_secret.as_bytes().into(),
+        }
+    }
+
+    pub fn encode(&self, version: i64) -> String {
+        let mut token = Vec::with_capacity(ZOOKIE_LENGTH);
+        token.push(ZOOKIE_FORMAT);
+        token.extend_from_slice(&version.to_be_bytes());
+        let mac = self.mac(&token).finalize().into_bytes();
+        token.extend_from_slice(&mac[..ZOOKIE_MAC_LENGTH]);
+        URL_SAFE_NO_PAD.encode(token)
+    }
+
+    /// Version `zookie` was issued for, failing unless it was issued with this key.
+    pub fn decode(&self, zookie: &str) -> HeimdallResult<i64> {
+        let invalid = || {
+            HeimdallError::InvalidConsistencyToken(format!(
+                "`{zookie}` is not a zookie issued by this deployment"
+            ))
+        };
+        let token = URL_SAFE_NO_PAD.decode(zookie).map_err(|_| invalid())?;
+        if token.len() != ZOOKIE_LENGTH || token[0] != ZOOKIE_FORMAT {
+            return Err(invalid());
+        }
+        let (payload, mac) = token.split_at(1 + 8);
+        self.mac(payload)
+            .verify_truncated_left(mac)
+            .map_err(|_| invalid())?;
+
+        let mut version = [0; 8];
+        version.copy_from_slice(&payload[1..]);
+        Ok(i64::from_be_bytes(version))
+    }
+
+    fn mac(&self, payload: &[u8]) -> HmacSha256 {
+        let mut mac =
+            HmacSha256::new_from_slice(&self.key).expect("HMAC accepts keys of any length");
+        mac.update(payload);
+        mac
+    }
+}
+
+/// Turns the [`Consistency`] a read asks for into the [`Snapshot`] it is evaluated at.
+#[derive(Debug, Clone)]
+pub struct ConsistencyResolver {
+    relationships: RelationshipRepository,
+    zookies: ZookieCodec,
+    max_wait: Duration,
+}
+
+impl ConsistencyResolver {
+    pub fn new(
+        pool: DatabasePool,
+        zookies: ZookieCodec,
+        consistency_config: &ConsistencyConfig,
+    ) -> Self {
+        Self {
+            relationships: RelationshipRepository::new(pool),
+            zookies,
+            max_wait: Duration::from_millis(consistency_config.max_wait_ms),
+        }
+    }
+
+    /// Snapshot at exactly the version of `zookie`, for reads continuing an earlier one.
+    pub fn pinned(&self, zookie: &str) -> HeimdallResult<Snapshot> {
+        Ok(Snapshot {
+            version: self.zookies.decode(zookie)?,
+            zookie: zookie.to_string(),
+            waited_for_consistency: false,
+            consistency_wait_ms: None,
+        })
+    }
+
+    /// `snapshot` taken back to `version` if that is older, for reads that served results
+    /// computed at `version` and so only hold at it.
+    pub fn served_at(&self, snapshot: &Snapshot, version: i64) -> Snapshot {
+        if version >= snapshot.version {
+            return snapshot.clone();
+        }
+        Snapshot {
+            version,
+            zookie: self.zookies.encode(version),
+            ..snapshot.clone()
+        }
+    }
+
+    /// Oldest version a result may have been computed at to satisfy `consistency`, given the
+    /// `snapshot` it resolved to. Any version will do for `minimize_latency`.
+    pub fn min_version(
+        &self,
+        consistency: &Consistency,
+        snapshot: &Snapshot,
+    ) -> HeimdallResult<i64> {
+        match consistency {
+            Consistency::MinimizeLatency => Ok(0),
+            Consistency::AtLeastAsFresh { zookie } => self.zookies.decode(zookie),
+            Consistency::FullyConsistent => Ok(snapshot.version),
+        }
+    }
+
+    /// Snapshot satisfying `consistency`. An `at_least_as_fresh` read waits up to `max_wait_ms`
+    /// for the version of its zookie to become visible, and fails if it does not.
+    pub async fn resolve(&self, consistency: &Consistency) -> HeimdallResult<Snapshot> {
+        let required = match consistency {
+            Consistency::MinimizeLatency | Consistency::FullyConsistent => None,
+            Consistency::AtLeastAsFresh { zookie } => Some(self.zookies.decode(zookie)?),
+        };
+
+        let started = Instant::now();
+        let mut waited = false;
+        let version = loop {
+            let version = self.relationships.current_version().await?;
+            match required {
+                Some(required) if version < required => {
+                    if started.elapsed() >= self.max_wait {
+                        return Err(HeimdallError::ConsistencyTimeout(format!(
+                            "version {required} did not become visible within {} ms, \
+                             the latest visible version is {version}",
+                            self.max_wait.as_millis()
+                        )));
+                    }
+                    waited = true;
+                    tokio::time::sleep(POLL_INTERVAL).await;
+                }
+                _ => break version,
+            }
+        };
+
+        Ok(Snapshot {
+            version,
+            zookie: self.zookies.encode(version),
+            waited_for_consistency: waited,
+            consistency_wait_ms: waited
+                .then(|| i32::try_from(started.elapsed().as_millis()).unwrap_or(i32::MAX)),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn codec(secret: &str) -> ZookieCodec {
+        ZookieCodec::new(&ConsistencyConfig {
+            zookie_secret: secret.to_string(),
+            ..ConsistencyConfig::default()
+        })
+    }
+
+    fn is_rejected(codec: &ZookieCodec, zookie: &str) -> bool {
+        matches!(
+            codec.decode(zookie),
+            Err(HeimdallError::InvalidConsistencyToken(_))
+        )
+    }
+
+    /// `zookie` with `edit` applied to its decoded bytes.
+    fn altered(zookie: &str, edit: impl FnOnce(&mut Vec<u8>)) -> String {
+        let mut token = URL_SAFE_NO_PAD.decode(zookie).unwrap();
+        edit(&mut token);
+        URL_SAFE_NO_PAD.encode(token)
+    }
+
+    #[test]
+    fn decodes_the_version_it_encoded() {
+        let codec = codec("first-secret-of-at-least-32-bytes");
+        for version in [0, 1, 42, i64::MAX] {
+            let zookie = codec.encode(version);
+            assert_eq!(codec.decode(&zookie).unwrap(), version);
+            // Every node sharing the key issues the same zookie.
+            assert_eq!(zookie, codec.clone().encode(version));
+        }
+        assert_ne!(codec.encode(1), codec.encode(2));
+    }
+
+    #[test]
+    fn rejects_altered_zookies() {
+        let codec = codec("first-secret-of-at-least-32-bytes");
+        let zookie = codec.encode(7);
+
+        let last_mac_byte = altered(&zookie, |token| *token.last_mut().unwrap() ^= 1);
+        let version_byte = altered(&zookie, |token| token[8] = 8);
+        assert!(is_rejected(&codec, &last_mac_byte));
+        assert!(is_rejected(&codec, &version_byte));
+    }
+
+    #[test]
+    fn rejects_zookies_signed_with_another_key() {
+        let zookie = codec("second-secret-of-at-least-32-bytes").encode(7);
+        assert!(is_rejected(
+            &codec("first-secret-of-at-least-32-bytes"),
+            &zookie
+        ));
+    }
+
+    #[test]
+    fn rejects_other_formats_and_lengths() {
+        let codec = codec("first-secret-of-at-least-32-bytes");
+        let zookie = codec.encode(7);
+
+        let format = altered(&zookie, |token| token[0] = ZOOKIE_FORMAT + 1);
+        let shorter = altered(&zookie, |token| {
+            token.pop();
+        });
+        let longer = altered(&zookie, |token| token.push(0));
+        for zookie in [format, shorter, longer, String::new()] {
+            assert!(is_rejected(&codec, &zookie), "{zookie}");
+        }
+    }
+
+    #[test]
+    fn rejects_what_is_not_base64() {
+        let codec = codec("first-secret-of-at-least-32-bytes");
+        let zookie = codec.encode(7);
+        // Standard base64 and padding are not accepted either.
+        let padded = format!("{zookie}=");
+        for zookie in ["not a zookie!", "AQ+/", padded.as_str()] {
+            assert!(is_rejected(&codec, zookie), "{zookie}");
+        }
+    }
+}
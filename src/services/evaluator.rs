@@ -0,0 +1,1225 @@
+use std::{
+    collections::{HashMap, HashSet},
+    future::Future,
+    pin::Pin,
+    sync::Arc,
+};
+
+use crate::{
+    config::EvaluationConfig,
+    database::DatabasePool,
+    entities::RelationshipTuple,
+    error::{HeimdallError, HeimdallResult},
+    models::{
+        EvaluationStep, ObjectRelation, Rewrite, SubjectRef, SubjectSet, TupleKey, UsersetNode,
+        UsersetTree, WILDCARD_SUBJECT_ID,
+    },
+    repositories::{
+        PermissionsCacheRepository, RelationRepository, RelationRuleRepository,
+        RelationshipRepository, permissions_cache::cache_key,
+    },
+};
+
+use super::{check_cache::CheckCache, singleflight::Singleflight};
+
+type BoxFuture<'a, T> = Pin<Box<dyn Future<Output = T> + Send + 'a>>;
+
+/// How the evaluation got to a userset: from the rewrite of another relation of the same object,
+/// or through a tuple.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Hop {
+    Rewrite,
+    Tuple,
+}
+
+/// A userset on the path to the one being evaluated.
+#[derive(Debug)]
+struct Visit {
+    object: ObjectRelation,
+    hop: Hop,
+    /// [`Evaluator::cut`] of the evaluation `object` was reached from.
+    outer_cut: Option<usize>,
+}
+
+/// Repositories an [`Evaluator`] reads from, cheap to clone into every evaluation.
+///
+/// Clones share the tuple reads in flight, so evaluations running at the same time at the same
+/// version issue each distinct read once and share its result.
+#[derive(Debug, Clone)]
+pub struct EvaluationSource {
+    pub relations: RelationRepository,
+    pub rules: RelationRuleRepository,
+    pub relationships: RelationshipRepository,
+    pub max_depth: u32,
+    contains_reads: Singleflight<(ObjectRelation, SubjectRef, i64), bool>,
+    object_reads: Singleflight<(ObjectRelation, bool, i64), Vec<RelationshipTuple>>,
+}
+
+impl EvaluationSource {
+    pub fn new(pool: DatabasePool, evaluation_config: &EvaluationConfig) -> Self {
+        Self {
+            relations: RelationRepository::new(pool.clone()),
+            rules: RelationRuleRepository::new(pool.clone()),
+            relationships: RelationshipRepository::new(pool),
+            max_depth: evaluation_config.max_depth,
+            contains_reads: Singleflight::default(),
+            object_reads: Singleflight::default(),
+        }
+    }
+
+    /// [`RelationshipRepository::contains`], shared with concurrent evaluations at `version`.
+    async fn contains(
+        &self,
+        object: &ObjectRelation,
+        subject: &SubjectRef,
+        version: i64,
+    ) -> Result<bool, sqlx::Error> {
+        let key = (object.clone(), subject.clone(), version);
+        self.contains_reads
+            .run(key, || self.relationships.contains(object, subject))
+            .await
+    }
+
+    /// [`RelationshipRepository::find_by_object`], shared with concurrent evaluations at
+    /// `version`.
+    async fn find_by_object(
+        &self,
+        object: &ObjectRelation,
+        usersets_only: bool,
+        version: i64,
+    ) -> Result<Vec<RelationshipTuple>, sqlx::Error> {
+        let key = (object.clone(), usersets_only, version);
+        self.object_reads
+            .run(key, || {
+                self.relationships.find_by_object(object, usersets_only)
+            })
+            .await
+    }
+}
+
+/// Check results of earlier requests an [`Evaluator`] may reuse, see [`Evaluator::with_cache`]
+/// and [`Evaluator::with_local_cache`].
+struct ResultCache {
+    /// In-process results, served only if computed at exactly `snapshot_version`.
+    local: Option<CheckCache>,
+    /// `permissions_cache` and the oldest version a result in it may have been computed at to be
+    /// served.
+    shared: Option<(PermissionsCacheRepository, i64)>,
+    /// Version of the snapshot the evaluation runs at.
+    snapshot_version: i64,
+    /// Version the computed results hold at: the snapshot's, unless they may build on older
+    /// cached results.
+    version: i64,
+    /// Results served from either cache.
+    hits: HashSet<(ObjectRelation, SubjectRef)>,
+    /// Results computed by this evaluation, to be cached.
+    computed: Vec<(ObjectRelation, SubjectRef, bool)>,
+}
+
+impl ResultCache {
+    fn new(snapshot_version: i64) -> Self {
+        Self {
+            local: None,
+            shared: None,
+            snapshot_version,
+            version: snapshot_version,
+            hits: HashSet::new(),
+            computed: Vec::new(),
+        }
+    }
+
+    /// Cached result of checking `subject` against `object`, from the in-process cache if it
+    /// has one and `permissions_cache` otherwise. Results found in `permissions_cache` are kept
+    /// in process at the version they were computed at.
+    async fn find(
+        &mut self,
+        object: &ObjectRelation,
+        subject: &SubjectRef,
+    ) -> HeimdallResult<Option<bool>> {
+        let mut allowed = self
+            .local
+            .as_ref()
+            .and_then(|local| local.get(object, subject, self.snapshot_version));
+        if let (None, Some((repository, min_version))) = (allowed, &self.shared) {
+            let cached = repository
+                .find(&cache_key(object, subject), *min_version)
+                .await?;
+            if let Some((cached, version)) = cached {
+                self.version = self.version.min(version);
+                if let Some(local) = &self.local {
+                    local.insert(object.clone(), subject.clone(), version, cached);
+                }
+                allowed = Some(cached);
+            }
+        }
+        if allowed.is_some() {
+            self.hits.insert((object.clone(), subject.clone()));
+        }
+        Ok(allowed)
+    }
+}
+
+/// Evaluates relation rewrites for a single request.
+///
+/// Rewrites and sub-results are memoized for the lifetime of the evaluator, so the same
+/// userset reached through several paths is only evaluated once. Tuple reads are shared with
+/// the other evaluations at the same `version` through the [`EvaluationSource`]. Evaluation is depth-first and
+/// sequential, which keeps the path to the current node available for cycle detection.
+pub struct Evaluator {
+    source: EvaluationSource,
+    /// Version of the snapshot the evaluation runs at.
+    version: i64,
+    /// Rewrite of every `(namespace, relation)` looked up so far, `None` if it does not exist.
+    rewrites: HashMap<(String, String), Option<Arc<Rewrite>>>,
+    /// Results of [`Evaluator::check`] for the subject they were computed for.
+    results: HashMap<(ObjectRelation, SubjectRef), bool>,
+    /// Results of [`Evaluator::expand`].
+    expansions: HashMap<ObjectRelation, UsersetTree>,
+    /// Results of [`Evaluator::lookup_subjects`] for the subject type they were computed for.
+    subject_sets: HashMap<(ObjectRelation, String), SubjectSet>,
+    path: Vec<Visit>,
+    /// Lowest index into `path` of a userset a data cycle led back to while evaluating the
+    /// current one. Results depending on such a cut only hold while that userset is on the path,
+    /// so they are not memoized.
+    cut: Option<usize>,
+    cache: Option<ResultCache>,
+    /// Steps of the checks since [`Evaluator::record_path`], `None` when not recording.
+    steps: Option<Vec<EvaluationStep>>,
+}
+
+impl Evaluator {
+    /// Evaluator for a request served at the snapshot `version`.
+    pub fn new(source: EvaluationSource, version: i64) -> Self {
+        Self {
+            source,
+            version,
+            rewrites: HashMap::new(),
+            results: HashMap::new(),
+            expansions: HashMap::new(),
+            subject_sets: HashMap::new(),
+            path: Vec::new(),
+            cut: None,
+            cache: None,
+            steps: None,
+        }
+    }
+
+    /// Makes [`Evaluator::check`] answer the top-level check and every userset it reaches from
+    /// `permissions_cache` where a result computed at `min_version` or later exists. The results
+    /// it computes instead, at the snapshot's version, are collected for
+    /// [`Evaluator::take_computed`].
+    pub fn with_cache(mut self, repository: PermissionsCacheRepository, min_version: i64) -> Self {
+        self.result_cache().shared = Some((repository, min_version));
+        self
+    }
+
+    /// Like [`Evaluator::with_cache`], but answers from `cache` where a result computed at
+    /// exactly the snapshot's version exists, before turning to `permissions_cache`.
+    pub fn with_local_cache(mut self, cache: CheckCache) -> Self {
+        self.result_cache().local = Some(cache);
+        self
+    }
+
+    /// Starts recording the rules and tuples the following checks go through, until
+    /// [`Evaluator::take_path`].
+    pub fn record_path(&mut self) {
+        self.steps = Some(Vec::new());
+    }
+
+    /// Steps recorded since [`Evaluator::record_path`], which stops recording.
+    pub fn take_path(&mut self) -> Vec<EvaluationStep> {
+        self.steps.take().unwrap_or_default()
+    }
+
+    /// Whether the result of checking `subject` against `object` came from a cache.
+    pub fn is_cached(&self, object: &ObjectRelation, subject: &SubjectRef) -> bool {
+        self.cache
+            .as_ref()
+            .is_some_and(|cache| cache.hits.contains(&(object.clone(), subject.clone())))
+    }
+
+    /// Version the results so far hold at, the oldest of the snapshot and every cached result
+    /// served.
+    pub fn served_version(&self) -> i64 {
+        self.cache
+            .as_ref()
+            .map_or(self.version, |cache| cache.version)
+    }
+
+    /// Check results computed rather than served from a cache since the last call,
+    /// and the version they hold at. That is the oldest of the snapshot and every cached result
+    /// served, as any of them may have gone into the computed ones.
+    pub fn take_computed(&mut self) -> (Vec<(ObjectRelation, SubjectRef, bool)>, i64) {
+        match &mut self.cache {
+            Some(cache) => (std::mem::take(&mut cache.computed), cache.version),
+            None => (Vec::new(), 0),
+        }
+    }
+
+    /// Whether `subject` holds `object`. Fails if the relation does not exist, a rule is
+    /// malformed, the evaluation runs into a cycle of rewrites or nests deeper than `max_depth`.
+    ///
+    /// Cycles through tuples, like two groups that are members of each other, are data and not
+    /// errors: a userset reached again while it is being evaluated holds no one on that branch.
+    pub async fn check(
+        &mut self,
+        object: &ObjectRelation,
+        subject: &SubjectRef,
+    ) -> HeimdallResult<bool> {
+        self.check_relation(object, subject, Hop::Rewrite).await
+    }
+
+    /// Every subject holding `object`, as a tree following the relation's rewrite. Usersets are
+    /// expanded recursively and fail the same way [`Evaluator::check`] does. A userset reached
+    /// again through a tuple while it is being expanded is left without subjects.
+    pub async fn expand(&mut self, object: &ObjectRelation) -> HeimdallResult<UsersetTree> {
+        self.expand_relation(object, Hop::Rewrite).await
+    }
+
+    /// Every subject of `subject_type` holding `object`. Usersets are followed down to plain
+    /// subjects and fail the same way [`Evaluator::check`] does.
+    pub async fn lookup_subjects(
+        &mut self,
+        object: &ObjectRelation,
+        subject_type: &str,
+    ) -> HeimdallResult<SubjectSet> {
+        self.lookup_relation(object, subject_type, Hop::Rewrite)
+            .await
+    }
+
+    /// Rewrite of a relation, `None` if the relation does not exist or was soft-deleted.
+    pub async fn rewrite(
+        &mut self,
+        namespace: &str,
+        relation: &str,
+    ) -> HeimdallResult<Option<Arc<Rewrite>>> {
+        let key = (namespace.to_string(), relation.to_string());
+        if let Some(rewrite) = self.rewrites.get(&key) {
+            return Ok(rewrite.clone());
+        }
+
+        let rewrite = match self.source.relations.find(namespace, relation).await? {
+            Some(_) => {
+                let rules = self
+                    .source
+                    .rules
+                    .find_by_relation(namespace, relation)
+                    .await?;
+                Some(Arc::new(Rewrite::from_rules(&rules)?))
+            }
+            None => None,
+        };
+        self.rewrites.insert(key, rewrite.clone());
+        Ok(rewrite)
+    }
+
+    /// Like [`Evaluator::rewrite`], but a missing relation is an error.
+    pub async fn require_rewrite(
+        &mut self,
+        namespace: &str,
+        relation: &str,
+    ) -> HeimdallResult<Arc<Rewrite>> {
+        self.rewrite(namespace, relation)
+            .await?
+            .ok_or_else(|| HeimdallError::RelationNotFound {
+                namespace: namespace.to_string(),
+                relation: relation.to_string(),
+            })
+    }
+
+    /// Marks `object`, reached through `hop`, as being evaluated. Returns `false` without
+    /// entering it if `object` is already being evaluated and the way back to it passes through a
+    /// tuple, fails if it passes through rewrites only and when `max_depth` is exceeded. Every
+    /// call returning `true` has to be paired with [`Evaluator::leave`].
+    fn enter(&mut self, object: &ObjectRelation, hop: Hop) -> HeimdallResult<bool> {
+        if let Some(start) = self.path.iter().position(|visit| &visit.object == object) {
+            let through_tuple = hop == Hop::Tuple
+                || self.path[start + 1..]
+                    .iter()
+                    .any(|visit| visit.hop == Hop::Tuple);
+            if through_tuple {
+                self.cut = self.cut.into_iter().chain([start]).min();
+                return Ok(false);
+            }
+            let cycle = self.path[start..]
+                .iter()
+                .map(|visit| &visit.object)
+                .chain([object])
+                .map(ToString::to_string)
+                .collect::<Vec<_>>()
+                .join(" -> ");
+            return Err(HeimdallError::RuleEvaluation(format!(
+                "cycle detected: {cycle}"
+            )));
+        }
+        if self.path.len() >= self.source.max_depth as usize {
+            return Err(HeimdallError::RuleEvaluation(format!(
+                "maximum depth of {} exceeded while evaluating `{object}`",
+                self.source.max_depth
+            )));
+        }
+        self.path.push(Visit {
+            object: object.clone(),
+            hop,
+            outer_cut: self.cut.take(),
+        });
+        Ok(true)
+    }
+
+    /// Marks the object of the last [`Evaluator::enter`] as evaluated. Returns whether its result
+    /// holds on its own, rather than only while a userset a data cycle led back to is still
+    /// being evaluated.
+    fn leave(&mut self) -> bool {
+        let Some(visit) = self.path.pop() else {
+            return true;
+        };
+        let depth = self.path.len();
+        let cut = self.cut.filter(|&cut| cut < depth);
+        self.cut = visit.outer_cut.into_iter().chain(cut).min();
+        cut.is_none()
+    }
+
+    fn record(&mut self, step: impl FnOnce() -> EvaluationStep) {
+        if let Some(steps) = &mut self.steps {
+            steps.push(step());
+        }
+    }
+
+    fn result_cache(&mut self) -> &mut ResultCache {
+        let version = self.version;
+        self.cache.get_or_insert_with(|| ResultCache::new(version))
+    }
+
+    fn check_relation<'a>(
+        &'a mut self,
+        object: &'a ObjectRelation,
+        subject: &'a SubjectRef,
+        hop: Hop,
+    ) -> BoxFuture<'a, HeimdallResult<bool>> {
+        Box::pin(async move {
+            // Cached results may predate the relation being deleted, it has to exist first.
+            let rewrite = self
+                .require_rewrite(&object.namespace, &object.relation)
+                .await?;
+            // A userset always contains itself.
+            if subject.as_userset().as_ref() == Some(object) {
+                return Ok(true);
+            }
+            let key = (object.clone(), subject.clone());
+            if let Some(&allowed) = self.results.get(&key) {
+                self.record(|| EvaluationStep::Reused {
+                    object: object.to_string(),
+                    result: allowed,
+                });
+                return Ok(allowed);
+            }
+            if let Some(cache) = &mut self.cache
+                && let Some(allowed) = cache.find(object, subject).await?
+            {
+                self.record(|| EvaluationStep::Cached {
+                    object: object.to_string(),
+                    result: allowed,
+                });
+                self.results.insert(key, allowed);
+                return Ok(allowed);
+            }
+
+            if !self.enter(object, hop)? {
+                return Ok(false);
+            }
+            let allowed = self.evaluate(&rewrite, object, subject).await;
+            let settled = self.leave();
+
+            let allowed = allowed?;
+            self.record(|| EvaluationStep::Rule {
+                object: object.to_string(),
+                rule: rewrite.to_string(),
+                result: allowed,
+            });
+            if !settled {
+                return Ok(allowed);
+            }
+            if let Some(cache) = &mut self.cache {
+                cache
+                    .computed
+                    .push((object.clone(), subject.clone(), allowed));
+            }
+            self.results.insert(key, allowed);
+            Ok(allowed)
+        })
+    }
+
+    fn evaluate<'a>(
+        &'a mut self,
+        rewrite: &'a Rewrite,
+        object: &'a ObjectRelation,
+        subject: &'a SubjectRef,
+    ) -> BoxFuture<'a, HeimdallResult<bool>> {
+        Box::pin(async move {
+            match rewrite {
+                Rewrite::This => {
+                    if self.source.contains(object, subject, self.version).await? {
+                        self.record(|| EvaluationStep::Direct {
+                            object: object.to_string(),
+                            subject: subject.to_string(),
+                        });
+                        return Ok(true);
+                    }
+                    let usersets = self
+                        .source
+                        .find_by_object(object, true, self.version)
+                        .await?;
+                    for tuple in usersets {
+                        let tuple = TupleKey::from(tuple);
+                        let Some(userset) = tuple.subject().as_userset() else {
+                            continue;
+                        };
+                        if self.check_relation(&userset, subject, Hop::Tuple).await? {
+                            self.record(|| EvaluationStep::Tuple {
+                                tuple: tuple.to_string(),
+                            });
+                            return Ok(true);
+                        }
+                    }
+                    Ok(false)
+                }
+                Rewrite::ComputedUserset(relation) => {
+                    let computed = ObjectRelation {
+                        namespace: object.namespace.clone(),
+                        object_id: object.object_id.clone(),
+                        relation: relation.clone(),
+                    };
+                    self.check_relation(&computed, subject, Hop::Rewrite).await
+                }
+                Rewrite::TupleToUserset {
+                    tupleset,
+                    computed_relation,
+                    target_namespace,
+                } => {
+                    let targets = self
+                        .ttu_targets(
+                            object,
+                            tupleset,
+                            computed_relation,
+                            target_namespace.as_deref(),
+                        )
+                        .await?;
+                    for target in targets {
+                        if self.check_relation(&target, subject, Hop::Tuple).await? {
+                            self.record(|| EvaluationStep::Tuple {
+                                tuple: format!(
+                                    "{}:{}#{tupleset}@{}:{}",
+                                    object.namespace,
+                                    object.object_id,
+                                    target.namespace,
+                                    target.object_id
+                                ),
+                            });
+                            return Ok(true);
+                        }
+                    }
+                    Ok(false)
+                }
+                Rewrite::Union(children) => {
+                    for child in children {
+                        if self.evaluate(child, object, subject).await? {
+                            return Ok(true);
+                        }
+                    }
+                    Ok(false)
+                }
+                Rewrite::Intersection(children) => {
+                    for child in children {
+                        if !self.evaluate(child, object, subject).await? {
+                            return Ok(false);
+                        }
+                    }
+                    Ok(!children.is_empty())
+                }
+                Rewrite::Exclusion { base, subtract } => {
+                    Ok(self.evaluate(base, object, subject).await?
+                        && !self.evaluate(subtract, object, subject).await?)
+                }
+            }
+        })
+    }
+
+    fn expand_relation<'a>(
+        &'a mut self,
+        object: &'a ObjectRelation,
+        hop: Hop,
+    ) -> BoxFuture<'a, HeimdallResult<UsersetTree>> {
+        Box::pin(async move {
+            if let Some(tree) = self.expansions.get(object) {
+                return Ok(tree.clone());
+            }
+
+            let rewrite = self
+                .require_rewrite(&object.namespace, &object.relation)
+                .await?;
+            if !self.enter(object, hop)? {
+                return Ok(UsersetTree {
+                    object: object.clone(),
+                    node: UsersetNode::This {
+                        subjects: Vec::new(),
+                        children: Vec::new(),
+                    },
+                });
+            }
+            let node = self.expand_rewrite(&rewrite, object).await;
+            let settled = self.leave();
+
+            let tree = UsersetTree {
+                object: object.clone(),
+                node: node?,
+            };
+            if settled {
+                self.expansions.insert(object.clone(), tree.clone());
+            }
+            Ok(tree)
+        })
+    }
+
+    fn expand_rewrite<'a>(
+        &'a mut self,
+        rewrite: &'a Rewrite,
+        object: &'a ObjectRelation,
+    ) -> BoxFuture<'a, HeimdallResult<UsersetNode>> {
+        Box::pin(async move {
+            Ok(match rewrite {
+                Rewrite::This => {
+                    let tuples = self
+                        .source
+                        .find_by_object(object, false, self.version)
+                        .await?;
+                    let subjects: Vec<SubjectRef> = tuples
+                        .into_iter()
+                        .map(|tuple| TupleKey::from(tuple).subject())
+                        .collect();
+                    let mut children = Vec::new();
+                    for userset in subjects.iter().filter_map(SubjectRef::as_userset) {
+                        children.push(self.expand_relation(&userset, Hop::Tuple).await?);
+                    }
+                    UsersetNode::This { subjects, children }
+                }
+                Rewrite::ComputedUserset(relation) => {
+                    let computed = ObjectRelation {
+                        namespace: object.namespace.clone(),
+                        object_id: object.object_id.clone(),
+                        relation: relation.clone(),
+                    };
+                    UsersetNode::ComputedUserset {
+                        child: Box::new(self.expand_relation(&computed, Hop::Rewrite).await?),
+                    }
+                }
+                Rewrite::TupleToUserset {
+                    tupleset,
+                    computed_relation,
+                    target_namespace,
+                } => {
+                    let targets = self
+                        .ttu_targets(
+                            object,
+                            tupleset,
+                            computed_relation,
+                            target_namespace.as_deref(),
+                        )
+                        .await?;
+                    let mut children = Vec::with_capacity(targets.len());
+                    for target in &targets {
+                        children.push(self.expand_relation(target, Hop::Tuple).await?);
+                    }
+                    UsersetNode::TupleToUserset {
+                        tupleset: tupleset.clone(),
+                        computed_relation: computed_relation.clone(),
+                        children,
+                    }
+                }
+                Rewrite::Union(rewrites) => UsersetNode::Union {
+                    children: self.expand_all(rewrites, object).await?,
+                },
+                Rewrite::Intersection(rewrites) => UsersetNode::Intersection {
+                    children: self.expand_all(rewrites, object).await?,
+                },
+                Rewrite::Exclusion { base, subtract } => UsersetNode::Exclusion {
+                    base: Box::new(self.expand_rewrite(base, object).await?),
+                    subtract: Box::new(self.expand_rewrite(subtract, object).await?),
+                },
+            })
+        })
+    }
+
+    fn lookup_relation<'a>(
+        &'a mut self,
+        object: &'a ObjectRelation,
+        subject_type: &'a str,
+        hop: Hop,
+    ) -> BoxFuture<'a, HeimdallResult<SubjectSet>> {
+        Box::pin(async move {
+            let key = (object.clone(), subject_type.to_string());
+            if let Some(subjects) = self.subject_sets.get(&key) {
+                return Ok(subjects.clone());
+            }
+
+            let rewrite = self
+                .require_rewrite(&object.namespace, &object.relation)
+                .await?;
+            if !self.enter(object, hop)? {
+                return Ok(SubjectSet::default());
+            }
+            let subjects = self.lookup_rewrite(&rewrite, object, subject_type).await;
+            let settled = self.leave();
+
+            let subjects = subjects?;
+            if settled {
+                self.subject_sets.insert(key, subjects.clone());
+            }
+            Ok(subjects)
+        })
+    }
+
+    fn lookup_rewrite<'a>(
+        &'a mut self,
+        rewrite: &'a Rewrite,
+        object: &'a ObjectRelation,
+        subject_type: &'a str,
+    ) -> BoxFuture<'a, HeimdallResult<SubjectSet>> {
+        Box::pin(async move {
+            match rewrite {
+                Rewrite::This => {
+                    let tuples = self
+                        .source
+                        .find_by_object(object, false, self.version)
+                        .await?;
+                    let mut subjects = SubjectSet::default();
+                    for tuple in tuples {
+                        let subject = TupleKey::from(tuple).subject();
+                        if let Some(userset) = subject.as_userset() {
+                            let members = self
+                                .lookup_relation(&userset, subject_type, Hop::Tuple)
+                                .await?;
+                            subjects = subjects.union(members);
+                        } else if subject.subject_type != subject_type {
+                            continue;
+                        } else if subject.subject_id == WILDCARD_SUBJECT_ID {
+                            subjects = subjects.union(SubjectSet::wildcard());
+                        } else {
+                            subjects = subjects.union(SubjectSet::single(subject.subject_id));
+                        }
+                    }
+                    Ok(subjects)
+                }
+                Rewrite::ComputedUserset(relation) => {
+                    let computed = ObjectRelation {
+                        namespace: object.namespace.clone(),
+                        object_id: object.object_id.clone(),
+                        relation: relation.clone(),
+                    };
+                    self.lookup_relation(&computed, subject_type, Hop::Rewrite)
+                        .await
+                }
+                Rewrite::TupleToUserset {
+                    tupleset,
+                    computed_relation,
+                    target_namespace,
+                } => {
+                    let targets = self
+                        .ttu_targets(
+                            object,
+                            tupleset,
+                            computed_relation,
+                            target_namespace.as_deref(),
+                        )
+                        .await?;
+                    let mut subjects = SubjectSet::default();
+                    for target in &targets {
+                        let members = self
+                            .lookup_relation(target, subject_type, Hop::Tuple)
+                            .await?;
+                        subjects = subjects.union(members);
+                    }
+                    Ok(subjects)
+                }
+                Rewrite::Union(children) => {
+                    let mut subjects = SubjectSet::default();
+                    for child in children {
+                        let members = self.lookup_rewrite(child, object, subject_type).await?;
+                        subjects = subjects.union(members);
+                    }
+                    Ok(subjects)
+                }
+                Rewrite::Intersection(children) => {
+                    let mut subjects: Option<SubjectSet> = None;
+                    for child in children {
+                        let members = self.lookup_rewrite(child, object, subject_type).await?;
+                        subjects = Some(match subjects {
+                            Some(subjects) => subjects.intersection(members),
+                            None => members,
+                        });
+                    }
+                    Ok(subjects.unwrap_or_default())
+                }
+                Rewrite::Exclusion { base, subtract } => {
+                    let base = self.lookup_rewrite(base, object, subject_type).await?;
+                    let subtract = self.lookup_rewrite(subtract, object, subject_type).await?;
+                    Ok(base.exclusion(subtract))
+                }
+            }
+        })
+    }
+
+    async fn expand_all(
+        &mut self,
+        rewrites: &[Rewrite],
+        object: &ObjectRelation,
+    ) -> HeimdallResult<Vec<UsersetNode>> {
+        let mut nodes = Vec::with_capacity(rewrites.len());
+        for rewrite in rewrites {
+            nodes.push(self.expand_rewrite(rewrite, object).await?);
+        }
+        Ok(nodes)
+    }
+
+    /// Userset `computed_relation` of every object referenced by the `tupleset` relation of
+    /// `object`. Userset subjects contribute their object, wildcards are skipped since they do
+    /// not name an object, and objects whose namespace lacks `computed_relation` are left out.
+    async fn ttu_targets(
+        &mut self,
+        object: &ObjectRelation,
+        tupleset: &str,
+        computed_relation: &str,
+        target_namespace: Option<&str>,
+    ) -> HeimdallResult<Vec<ObjectRelation>> {
+        self.require_rewrite(&object.namespace, tupleset).await?;
+        let tupleset = ObjectRelation {
+            namespace: object.namespace.clone(),
+            object_id: object.object_id.clone(),
+            relation: tupleset.to_string(),
+        };
+        let tuples = self
+            .source
+            .find_by_object(&tupleset, false, self.version)
+            .await?;
+
+        let mut seen = HashSet::with_capacity(tuples.len());
+        let mut targets = Vec::with_capacity(tuples.len());
+        for tuple in tuples {
+            if tuple.subject_id == WILDCARD_SUBJECT_ID
+                || target_namespace.is_some_and(|target| target != tuple.subject_type)
+                || self
+                    .rewrite(&tuple.subject_type, computed_relation)
+                    .await?
+                    .is_none()
+            {
+                continue;
+            }
+            let target = ObjectRelation {
+                namespace: tuple.subject_type,
+                object_id: tuple.subject_id,
+                relation: computed_relation.to_string(),
+            };
+            if seen.insert(target.clone()) {
+                targets.push(target);
+            }
+        }
+        Ok(targets)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::Utc;
+    use uuid::Uuid;
+
+    use crate::{
+        config::CacheConfig,
+        entities::PermissionsCacheEntry,
+        repositories::RelationDeletion,
+        services::testing::{Rule, parse_object, parse_subject, pool, rule},
+    };
+
+    use super::*;
+
+    fn evaluator(pool: DatabasePool, max_depth: u32) -> Evaluator {
+        let source = EvaluationSource::new(pool, &EvaluationConfig { max_depth });
+        Evaluator::new(source, 1)
+    }
+
+    /// Result of checking `subject` against `object` with a fresh evaluation.
+    async fn check(pool: &DatabasePool, object: &str, subject: &str) -> HeimdallResult<bool> {
+        evaluator(pool.clone(), 50)
+            .check(&parse_object(object), &parse_subject(subject))
+            .await
+    }
+
+    fn rule_evaluation_error(result: HeimdallResult<bool>) -> String {
+        match result {
+            Err(HeimdallError::RuleEvaluation(message)) => message,
+            other => panic!("expected a rule evaluation error, got {other:?}"),
+        }
+    }
+
+    #[tokio::test]
+    async fn direct_rules_and_relations_without_rules_hold_their_tuples() {
+        let pool = pool(
+            &["document#owner", "document#viewer"],
+            &[rule("document#viewer", "direct", &[])],
+            &["document:1#viewer@user:alice", "document:1#owner@user:bob"],
+        )
+        .await;
+        assert!(
+            check(&pool, "document:1#viewer", "user:alice")
+                .await
+                .unwrap()
+        );
+        assert!(!check(&pool, "document:1#viewer", "user:bob").await.unwrap());
+        assert!(check(&pool, "document:1#owner", "user:bob").await.unwrap());
+        assert!(!check(&pool, "document:2#owner", "user:bob").await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn union_intersection_and_exclusion_combine_their_children() {
+        let pool = pool(
+            &[
+                "document#reader",
+                "document#editor",
+                "document#member",
+                "document#banned",
+                "document#suspended",
+                "document#viewer",
+                "document#approved",
+                "document#readable",
+            ],
+            &[
+                rule("document#viewer", "union", &["reader", "editor"]),
+                rule("document#approved", "intersection", &["viewer", "member"]),
+                rule(
+                    "document#readable",
+                    "exclusion",
+                    &["viewer", "banned", "suspended"],
+                ),
+            ],
+            &[
+                "document:1#reader@user:alice",
+                "document:1#editor@user:bob",
+                "document:1#member@user:bob",
+                "document:1#banned@user:alice",
+                "document:1#editor@user:carol",
+                "document:1#suspended@user:carol",
+            ],
+        )
+        .await;
+        for (relation, subject, allowed) in [
+            ("viewer", "alice", true),
+            ("viewer", "bob", true),
+            ("viewer", "dave", false),
+            ("approved", "bob", true),
+            ("approved", "alice", false),
+            ("readable", "bob", true),
+            ("readable", "alice", false),
+            ("readable", "carol", false),
+        ] {
+            let object = format!("document:1#{relation}");
+            let subject = format!("user:{subject}");
+            assert_eq!(
+                check(&pool, &object, &subject).await.unwrap(),
+                allowed,
+                "{object}@{subject}"
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn tuple_to_userset_follows_only_its_target_namespace() {
+        let pool = pool(
+            &[
+                "document#parent",
+                "document#viewer",
+                "folder#viewer",
+                "team#viewer",
+                "user#self",
+            ],
+            &[Rule {
+                ttu_relation: Some("viewer"),
+                ttu_object_namespace: Some("folder"),
+                ..rule("document#viewer", "tuple-to-userset", &["parent"])
+            }],
+            &[
+                "document:1#parent@folder:a",
+                "document:1#parent@team:t",
+                "document:1#parent@user:u",
+                "document:1#parent@folder:*",
+                "folder:a#viewer@user:alice",
+                "team:t#viewer@user:bob",
+            ],
+        )
+        .await;
+        assert!(
+            check(&pool, "document:1#viewer", "user:alice")
+                .await
+                .unwrap()
+        );
+        // `team` is not the target namespace, and `user` lacks the computed relation.
+        assert!(!check(&pool, "document:1#viewer", "user:bob").await.unwrap());
+        // A wildcard parent does not name a folder to follow.
+        assert!(
+            !check(&pool, "document:1#viewer", "user:carol")
+                .await
+                .unwrap()
+        );
+
+        let targets = evaluator(pool, 50)
+            .ttu_targets(&parse_object("document:1#viewer"), "parent", "viewer", None)
+            .await
+            .unwrap();
+        let targets: Vec<String> = targets.iter().map(ToString::to_string).collect();
+        assert_eq!(targets, ["folder:a#viewer", "team:t#viewer"]);
+    }
+
+    #[tokio::test]
+    async fn wildcards_hold_every_subject_of_their_type() {
+        let pool = pool(
+            &["document#viewer", "group#member"],
+            &[],
+            &[
+                "document:1#viewer@user:*",
+                "document:1#viewer@group:eng#member",
+                "group:eng#member@bot:*",
+            ],
+        )
+        .await;
+        assert!(
+            check(&pool, "document:1#viewer", "user:alice")
+                .await
+                .unwrap()
+        );
+        assert!(check(&pool, "document:1#viewer", "bot:ci").await.unwrap());
+        assert!(!check(&pool, "document:1#viewer", "team:t").await.unwrap());
+        // A userset is not a subject of its type.
+        assert!(
+            !check(&pool, "document:1#viewer", "user:x#member")
+                .await
+                .unwrap()
+        );
+
+        let mut evaluator = evaluator(pool, 50);
+        let users = evaluator
+            .lookup_subjects(&parse_object("document:1#viewer"), "user")
+            .await
+            .unwrap();
+        assert_eq!(users, SubjectSet::wildcard());
+    }
+
+    #[tokio::test]
+    async fn rules_are_evaluated_by_priority() {
+        let pool = pool(
+            &["document#viewer", "document#editor", "document#owner"],
+            &[
+                Rule {
+                    priority: 20,
+                    ..rule("document#viewer", "union", &["editor"])
+                },
+                Rule {
+                    priority: 10,
+                    ..rule("document#viewer", "union", &["owner"])
+                },
+                Rule {
+                    priority: 30,
+                    ..rule("document#viewer", "direct", &[])
+                },
+            ],
+            &["document:1#editor@user:alice"],
+        )
+        .await;
+        let mut evaluator = evaluator(pool, 50);
+        let rewrite = evaluator
+            .require_rewrite("document", "viewer")
+            .await
+            .unwrap();
+        assert_eq!(rewrite.to_string(), "((owner) + (editor) + this)");
+
+        evaluator.record_path();
+        let object = parse_object("document:1#viewer");
+        assert!(
+            evaluator
+                .check(&object, &parse_subject("user:alice"))
+                .await
+                .unwrap()
+        );
+        let checked: Vec<String> = evaluator
+            .take_path()
+            .into_iter()
+            .filter_map(|step| match step {
+                EvaluationStep::Rule { object, .. } => Some(object),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(
+            checked,
+            ["document:1#owner", "document:1#editor", "document:1#viewer"]
+        );
+    }
+
+    #[tokio::test]
+    async fn malformed_rules_fail_the_checks_reaching_them() {
+        let pool = pool(
+            &["document#viewer", "document#editor", "document#owner"],
+            &[
+                rule("document#viewer", "union", &["owner", "editor"]),
+                rule("document#editor", "exclusion", &["owner"]),
+            ],
+            &["document:1#owner@user:alice"],
+        )
+        .await;
+        // The union is answered before it gets to the malformed rule.
+        assert!(
+            check(&pool, "document:1#viewer", "user:alice")
+                .await
+                .unwrap()
+        );
+        let message = rule_evaluation_error(check(&pool, "document:1#viewer", "user:bob").await);
+        assert!(message.contains("`document#editor`"), "{message}");
+        assert!(message.contains("base relation"), "{message}");
+
+        let missing = check(&pool, "document:1#unknown", "user:alice").await;
+        assert!(
+            matches!(missing, Err(HeimdallError::RelationNotFound { .. })),
+            "{missing:?}"
+        );
+    }
+
+    #[tokio::test]
+    async fn cycles_through_tuples_hold_no_one_on_their_branch() {
+        let pool = pool(
+            &["group#member"],
+            &[],
+            &[
+                "group:a#member@group:b#member",
+                "group:b#member@group:a#member",
+                "group:b#member@user:alice",
+            ],
+        )
+        .await;
+        assert!(check(&pool, "group:a#member", "user:alice").await.unwrap());
+        assert!(check(&pool, "group:b#member", "user:alice").await.unwrap());
+        assert!(!check(&pool, "group:a#member", "user:bob").await.unwrap());
+
+        let mut evaluator = evaluator(pool, 50);
+        let members = evaluator
+            .lookup_subjects(&parse_object("group:a#member"), "user")
+            .await
+            .unwrap();
+        assert_eq!(members, SubjectSet::single("alice".to_string()));
+        evaluator
+            .expand(&parse_object("group:a#member"))
+            .await
+            .unwrap();
+    }
+
+    #[tokio::test]
+    async fn cycles_of_rewrites_are_errors() {
+        let pool = pool(
+            &["document#reader", "document#viewer", "document#editor"],
+            &[
+                rule("document#viewer", "union", &["reader", "editor"]),
+                rule("document#editor", "union", &["viewer"]),
+            ],
+            &[],
+        )
+        .await;
+        let message = rule_evaluation_error(check(&pool, "document:1#viewer", "user:bob").await);
+        assert_eq!(
+            message,
+            "cycle detected: document:1#viewer -> document:1#editor -> document:1#viewer"
+        );
+    }
+
+    #[tokio::test]
+    async fn nesting_deeper_than_max_depth_is_an_error() {
+        let pool = pool(
+            &["group#member"],
+            &[],
+            &[
+                "group:1#member@group:2#member",
+                "group:2#member@group:3#member",
+                "group:3#member@group:4#member",
+                "group:4#member@user:alice",
+            ],
+        )
+        .await;
+        let object = parse_object("group:1#member");
+        let alice = parse_subject("user:alice");
+        assert!(
+            evaluator(pool.clone(), 4)
+                .check(&object, &alice)
+                .await
+                .unwrap()
+        );
+        let message = rule_evaluation_error(evaluator(pool, 3).check(&object, &alice).await);
+        assert_eq!(
+            message,
+            "maximum depth of 3 exceeded while evaluating `group:4#member`"
+        );
+    }
+
+    #[tokio::test]
+    async fn cached_results_of_deleted_relations_are_not_served() {
+        let pool = pool(&["document#viewer"], &[], &[]).await;
+        let object = parse_object("document:1#viewer");
+        let alice = parse_subject("user:alice");
+        let local = CheckCache::new(&CacheConfig {
+            check_cache_capacity: 10,
+            ..CacheConfig::default()
+        });
+        local.insert(object.clone(), alice.clone(), 1, true);
+        let shared = PermissionsCacheRepository::new(pool.clone());
+        let now = Utc::now();
+        shared
+            .upsert_all(&[PermissionsCacheEntry {
+                id: Uuid::new_v4(),
+                cache_key: cache_key(&object, &alice),
+                namespace_id: object.namespace.clone(),
+                object_id: object.object_id.clone(),
+                relation: object.relation.clone(),
+                subject_type: alice.subject_type.clone(),
+                subject_id: alice.subject_id.clone(),
+                permitted: true,
+                computed_at: now,
+                valid_until: now + chrono::Duration::minutes(1),
+                max_zookie_version: 1,
+            }])
+            .await
+            .unwrap();
+        let mut cached = evaluator(pool.clone(), 50)
+            .with_local_cache(local.clone())
+            .with_cache(shared.clone(), 0);
+        assert!(cached.check(&object, &alice).await.unwrap());
+        assert!(cached.is_cached(&object, &alice));
+
+        let relationships = RelationshipRepository::new(pool.clone());
+        let version = relationships.current_version().await.unwrap();
+        let deleted = RelationRepository::new(pool.clone())
+            .soft_delete("document", "viewer", &|version| version.to_string())
+            .await
+            .unwrap();
+        let advanced = relationships.current_version().await.unwrap();
+        assert_eq!(advanced, version + 1);
+        assert_eq!(
+            deleted,
+            RelationDeletion::Deleted {
+                zookie: advanced.to_string()
+            }
+        );
+        let key = cache_key(&object, &alice);
+        assert_eq!(shared.find(&key, 0).await.unwrap(), None);
+
+        // The in-process result is still there, for a node that has not seen the delete.
+        let mut stale = evaluator(pool, 50).with_local_cache(local.clone());
+        let checked = stale.check(&object, &alice).await;
+        assert!(
+            matches!(checked, Err(HeimdallError::RelationNotFound { .. })),
+            "{checked:?}"
+        );
+        assert_eq!(local.get(&object, &alice, 1), Some(true));
+    }
+}
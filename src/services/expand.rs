@@ -0,0 +1,45 @@
+use crate::{
+    config::EvaluationConfig,
+    database::DatabasePool,
+    error::HeimdallResult,
+    models::{Consistency, ObjectRelation, Snapshot, UsersetTree},
+};
+
+use super::{
+    consistency::ConsistencyResolver,
+    evaluator::{EvaluationSource, Evaluator},
+    relationship::validate_object_relation,
+};
+
+/// Explains who holds a relation on an object, Zanzibar's Expand.
+#[derive(Debug, Clone)]
+pub struct ExpandService {
+    source: EvaluationSource,
+    consistency: ConsistencyResolver,
+}
+
+impl ExpandService {
+    pub fn new(
+        pool: DatabasePool,
+        evaluation_config: &EvaluationConfig,
+        consistency: ConsistencyResolver,
+    ) -> Self {
+        Self {
+            source: EvaluationSource::new(pool, evaluation_config),
+            consistency,
+        }
+    }
+
+    pub async fn expand(
+        &self,
+        object: &ObjectRelation,
+        consistency: &Consistency,
+    ) -> HeimdallResult<(UsersetTree, Snapshot)> {
+        validate_object_relation(object)?;
+        let snapshot = self.consistency.resolve(consistency).await?;
+        let tree = Evaluator::new(self.source.clone(), snapshot.version)
+            .expand(object)
+            .await?;
+        Ok((tree, snapshot))
+    }
+}
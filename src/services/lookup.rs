@@ -0,0 +1,568 @@
+use std::{
+    collections::{HashMap, HashSet, VecDeque},
+    sync::{Arc, Mutex, MutexGuard},
+    time::{Duration, Instant},
+};
+
+use base64::{Engine, engine::general_purpose::URL_SAFE_NO_PAD};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    config::EvaluationConfig,
+    database::DatabasePool,
+    entities::RelationRule,
+    error::{HeimdallError, HeimdallResult},
+    models::{
+        Consistency, ObjectRelation, Rewrite, Snapshot, SubjectRef, SubjectSet, TupleFilter,
+        WILDCARD_SUBJECT_ID,
+    },
+    repositories::RelationshipRepository,
+};
+
+use super::{
+    consistency::ConsistencyResolver,
+    evaluator::{EvaluationSource, Evaluator},
+    page_size,
+    relationship::{validate_object_relation, validate_subject},
+    validate_identifier,
+};
+
+/// Most steps a page of resources may take, a step being a userset the reverse walk visits or
+/// a candidate it checks. A page running out of steps returns the objects confirmed so far,
+/// possibly none, and a cursor to continue from.
+const MAX_STEPS_PER_PAGE: usize = 10_000;
+
+/// Most reverse walks kept for the pages continuing them.
+const MAX_KEPT_WALKS: usize = 1_000;
+
+/// How long a reverse walk is kept after the page that last advanced it.
+const WALK_TTL: Duration = Duration::from_secs(300);
+
+/// Lookup, namespace and relation, subject and version a reverse walk belongs to.
+type WalkKey = (String, String, SubjectRef, i64);
+
+/// One page of object ids, in the order the reverse walk found them.
+#[derive(Debug, Clone)]
+pub struct ResourcePage {
+    pub object_ids: Vec<String>,
+    /// Pass as `cursor` to fetch the next page, `None` on the last page.
+    pub next_cursor: Option<String>,
+}
+
+/// Position in a paginated lookup: the snapshot of the first page and how many candidates of
+/// the reverse walk the pages so far went through.
+#[derive(Debug, Serialize, Deserialize)]
+struct LookupCursor {
+    zookie: String,
+    position: usize,
+}
+
+/// Lists what a subject can access, the reverse of [`CheckService`](super::CheckService).
+#[derive(Debug, Clone)]
+pub struct LookupService {
+    source: EvaluationSource,
+    consistency: ConsistencyResolver,
+    /// Reverse walks of lookups with pages left, and when their last page was served.
+    walks: Arc<Mutex<HashMap<WalkKey, (Walk, Instant)>>>,
+}
+
+impl LookupService {
+    pub fn new(
+        pool: DatabasePool,
+        evaluation_config: &EvaluationConfig,
+        consistency: ConsistencyResolver,
+    ) -> Self {
+        Self {
+            source: EvaluationSource::new(pool, evaluation_config),
+            consistency,
+            walks: Arc::default(),
+        }
+    }
+
+    /// Ids of the objects in `namespace` on which `subject` holds `relation`, one page at a
+    /// time.
+    ///
+    /// Tuples are walked backwards from the subject to find every object it could reach,
+    /// ignoring the subtracted side of exclusions. Each candidate is then confirmed with a
+    /// regular check, so exclusions and intersections are honoured exactly. The walk only
+    /// advances as far as a page needs, and is kept for the following pages, which continue it
+    /// instead of starting over. A page continuing a walk this node does not hold retraces it up
+    /// to the cursor.
+    ///
+    /// The cursor pins every following page to the snapshot of the first one and the position
+    /// in its walk, so no object is returned twice. Tuples are still read as they are when a
+    /// page is requested: an object granted while paging is only returned if the walk has not
+    /// passed it yet, one revoked is left out unless it was returned already.
+    pub async fn lookup_resources(
+        &self,
+        namespace: &str,
+        relation: &str,
+        subject: &SubjectRef,
+        cursor: Option<&str>,
+        limit: Option<usize>,
+        consistency: &Consistency,
+    ) -> HeimdallResult<(ResourcePage, Snapshot)> {
+        validate_identifier("namespace", namespace)?;
+        validate_identifier("relation", relation)?;
+        validate_subject(subject)?;
+        let limit = page_size(limit)?;
+        let (snapshot, mut position) = match cursor {
+            Some(cursor) => {
+                let invalid =
+                    || HeimdallError::InvalidArgument("`cursor` is not valid".to_string());
+                let cursor: LookupCursor = URL_SAFE_NO_PAD
+                    .decode(cursor)
+                    .ok()
+                    .and_then(|json| serde_json::from_slice(&json).ok())
+                    .ok_or_else(invalid)?;
+                (self.consistency.pinned(&cursor.zookie)?, cursor.position)
+            }
+            None => (self.consistency.resolve(consistency).await?, 0),
+        };
+
+        let mut evaluator = Evaluator::new(self.source.clone(), snapshot.version);
+        evaluator.require_rewrite(namespace, relation).await?;
+
+        let relationships = &self.source.relationships;
+        let key = (
+            namespace.to_string(),
+            relation.to_string(),
+            subject.clone(),
+            snapshot.version,
+        );
+        let mut walk = match self.take_walk(&key) {
+            Some(walk) => walk,
+            None => Walk::new(self.reverse_index().await?, namespace, relation, subject),
+        };
+
+        // A walk started over is advanced to the position of the cursor before anything is
+        // checked, within the same steps.
+        let mut object_ids = Vec::new();
+        let mut steps = 0;
+        while object_ids.len() < limit && steps < MAX_STEPS_PER_PAGE {
+            steps += 1;
+            let Some(object_id) = walk.candidates.get(position).cloned() else {
+                if walk.is_done() {
+                    break;
+                }
+                walk.step(relationships).await?;
+                continue;
+            };
+            position += 1;
+            let object = ObjectRelation {
+                namespace: namespace.to_string(),
+                object_id,
+                relation: relation.to_string(),
+            };
+            if evaluator.check(&object, subject).await? {
+                object_ids.push(object.object_id);
+            }
+        }
+
+        let next_cursor = if walk.is_done() && position >= walk.candidates.len() {
+            None
+        } else {
+            self.keep_walk(key, walk);
+            let cursor = LookupCursor {
+                zookie: snapshot.zookie.clone(),
+                position,
+            };
+            Some(URL_SAFE_NO_PAD.encode(serde_json::to_vec(&cursor).expect("cursors serialize")))
+        };
+
+        let page = ResourcePage {
+            object_ids,
+            next_cursor,
+        };
+        Ok((page, snapshot))
+    }
+
+    /// Every subject of `subject_type` holding `object`, with usersets expanded down to plain
+    /// subjects. A `*` wildcard and the subjects exclusions removed are reported in the set
+    /// rather than resolved, since they cannot be enumerated.
+    pub async fn lookup_subjects(
+        &self,
+        object: &ObjectRelation,
+        subject_type: &str,
+        consistency: &Consistency,
+    ) -> HeimdallResult<(SubjectSet, Snapshot)> {
+        validate_object_relation(object)?;
+        validate_identifier("subject_type", subject_type)?;
+        let snapshot = self.consistency.resolve(consistency).await?;
+
+        let subjects = Evaluator::new(self.source.clone(), snapshot.version)
+            .lookup_subjects(object, subject_type)
+            .await?;
+        Ok((subjects, snapshot))
+    }
+
+    async fn reverse_index(&self) -> HeimdallResult<ReverseIndex> {
+        let relations = self.source.relations.find_all().await?;
+        let rules = self.source.rules.find_all().await?;
+
+        let mut rules_by_relation: HashMap<(&str, &str), Vec<RelationRule>> = HashMap::new();
+        for rule in &rules {
+            rules_by_relation
+                .entry((&rule.namespace_id, &rule.relation_name))
+                .or_default()
+                .push(rule.clone());
+        }
+
+        let mut index = ReverseIndex::default();
+        for relation in &relations {
+            let rules = rules_by_relation
+                .get(&(relation.namespace_id.as_str(), relation.name.as_str()))
+                .map(Vec::as_slice)
+                .unwrap_or_default();
+            let rewrite = Rewrite::from_rules(rules)?;
+            index.add(&relation.namespace_id, &relation.name, &rewrite);
+        }
+        Ok(index)
+    }
+
+    /// The walk kept for `key`, if it has not expired. It is no longer kept, so that concurrent
+    /// pages do not advance it at the same time.
+    fn take_walk(&self, key: &WalkKey) -> Option<Walk> {
+        let (walk, kept_at) = self.walks().remove(key)?;
+        (kept_at.elapsed() < WALK_TTL).then_some(walk)
+    }
+
+    /// Keeps `walk` for the next page, dropping expired walks and, if too many are kept, the
+    /// one kept the longest.
+    fn keep_walk(&self, key: WalkKey, walk: Walk) {
+        let mut walks = self.walks();
+        walks.retain(|_, (_, kept_at)| kept_at.elapsed() < WALK_TTL);
+        if walks.len() >= MAX_KEPT_WALKS {
+            let oldest = walks
+                .iter()
+                .min_by_key(|(_, (_, kept_at))| *kept_at)
+                .map(|(key, _)| key.clone());
+            if let Some(oldest) = oldest {
+                walks.remove(&oldest);
+            }
+        }
+        walks.insert(key, (walk, Instant::now()));
+    }
+
+    fn walks(&self) -> MutexGuard<'_, HashMap<WalkKey, (Walk, Instant)>> {
+        // The map is consistent between statements, a panic elsewhere cannot corrupt it.
+        self.walks
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+/// Walk of the tuples backwards from a subject to the objects in `namespace` whose `relation`
+/// it may hold, one userset at a time.
+#[derive(Debug)]
+struct Walk {
+    index: ReverseIndex,
+    namespace: String,
+    relation: String,
+    /// Usersets holding the subject, found but not visited yet.
+    queue: VecDeque<SubjectRef>,
+    visited: HashSet<SubjectRef>,
+    /// Ids of the objects found, in the order they were found. A superset of the ones the
+    /// subject actually holds `relation` on.
+    candidates: Vec<String>,
+}
+
+impl Walk {
+    fn new(index: ReverseIndex, namespace: &str, relation: &str, subject: &SubjectRef) -> Self {
+        let mut queue = VecDeque::new();
+        queue.push_back(subject.clone());
+        if subject.subject_relation.is_none() && subject.subject_id != WILDCARD_SUBJECT_ID {
+            queue.push_back(SubjectRef {
+                subject_type: subject.subject_type.clone(),
+                subject_id: WILDCARD_SUBJECT_ID.to_string(),
+                subject_relation: None,
+            });
+        }
+        Self {
+            index,
+            namespace: namespace.to_string(),
+            relation: relation.to_string(),
+            queue,
+            visited: HashSet::new(),
+            candidates: Vec::new(),
+        }
+    }
+
+    fn is_done(&self) -> bool {
+        self.queue.is_empty()
+    }
+
+    /// Visits the next userset found, queueing the usersets holding it.
+    async fn step(&mut self, relationships: &RelationshipRepository) -> HeimdallResult<()> {
+        let Some(member) = self.queue.pop_front() else {
+            return Ok(());
+        };
+        if !self.visited.insert(member.clone()) {
+            return Ok(());
+        }
+
+        // Relations storing the member directly.
+        for tuple in relationships.find_by_subject(&member).await? {
+            if self
+                .index
+                .direct
+                .contains(&(tuple.namespace_id.clone(), tuple.relation.clone()))
+            {
+                self.queue.push_back(SubjectRef {
+                    subject_type: tuple.namespace_id,
+                    subject_id: tuple.object_id,
+                    subject_relation: Some(tuple.relation),
+                });
+            }
+        }
+
+        let Some(userset) = member.as_userset() else {
+            return Ok(());
+        };
+        if userset.namespace == self.namespace && userset.relation == self.relation {
+            self.candidates.push(userset.object_id.clone());
+        }
+
+        // Relations of the same object computed from this one.
+        let key = (userset.namespace.clone(), userset.relation.clone());
+        for computed in self.index.computed.get(&key).into_iter().flatten() {
+            self.queue.push_back(SubjectRef {
+                subject_type: userset.namespace.clone(),
+                subject_id: userset.object_id.clone(),
+                subject_relation: Some(computed.clone()),
+            });
+        }
+
+        // Relations of objects that point at this object through a tupleset.
+        for ttu in self
+            .index
+            .tuple_to_userset
+            .get(&userset.relation)
+            .into_iter()
+            .flatten()
+        {
+            if ttu
+                .target_namespace
+                .as_ref()
+                .is_some_and(|target| *target != userset.namespace)
+            {
+                continue;
+            }
+            let filter = TupleFilter {
+                namespace: Some(ttu.namespace.clone()),
+                relation: Some(ttu.tupleset.clone()),
+                subject_type: Some(userset.namespace.clone()),
+                subject_id: Some(userset.object_id.clone()),
+                ..TupleFilter::default()
+            };
+            for tuple in relationships.find_by_filter(&filter).await? {
+                self.queue.push_back(SubjectRef {
+                    subject_type: tuple.namespace_id,
+                    subject_id: tuple.object_id,
+                    subject_relation: Some(ttu.relation.clone()),
+                });
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Use of a computed relation by a tuple-to-userset rewrite.
+#[derive(Debug)]
+struct TupleToUsersetUse {
+    namespace: String,
+    relation: String,
+    tupleset: String,
+    target_namespace: Option<String>,
+}
+
+/// The rewrites of every relation turned inside out: for a relation, which relations holding
+/// it grants. Subtracted sides of exclusions grant nothing and are left out.
+#[derive(Debug, Default)]
+struct ReverseIndex {
+    /// `(namespace, relation)` of relations whose own tuples count.
+    direct: HashSet<(String, String)>,
+    /// `(namespace, relation)` to the relations of the same namespace computed from it.
+    computed: HashMap<(String, String), Vec<String>>,
+    /// Computed relation name to the tuple-to-userset rewrites using it.
+    tuple_to_userset: HashMap<String, Vec<TupleToUsersetUse>>,
+}
+
+impl ReverseIndex {
+    fn add(&mut self, namespace: &str, relation: &str, rewrite: &Rewrite) {
+        match rewrite {
+            Rewrite::This => {
+                self.direct
+                    .insert((namespace.to_string(), relation.to_string()));
+            }
+            Rewrite::ComputedUserset(child) => self
+                .computed
+                .entry((namespace.to_string(), child.clone()))
+                .or_default()
+                .push(relation.to_string()),
+            Rewrite::TupleToUserset {
+                tupleset,
+                computed_relation,
+                target_namespace,
+            } => self
+                .tuple_to_userset
+                .entry(computed_relation.clone())
+                .or_default()
+                .push(TupleToUsersetUse {
+                    namespace: namespace.to_string(),
+                    relation: relation.to_string(),
+                    tupleset: tupleset.clone(),
+                    target_namespace: target_namespace.clone(),
+                }),
+            Rewrite::Union(children) | Rewrite::Intersection(children) => {
+                for child in children {
+                    self.add(namespace, relation, child);
+                }
+            }
+            Rewrite::Exclusion { base, .. } => self.add(namespace, relation, base),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        config::ConsistencyConfig,
+        services::{
+            ZookieCodec,
+            testing::{parse_subject, pool, rule},
+        },
+    };
+
+    use super::*;
+
+    fn service(pool: DatabasePool) -> LookupService {
+        let consistency_config = ConsistencyConfig::default();
+        let consistency = ConsistencyResolver::new(
+            pool.clone(),
+            ZookieCodec::new(&consistency_config),
+            &consistency_config,
+        );
+        LookupService::new(pool, &EvaluationConfig::default(), consistency)
+    }
+
+    /// Every page of the lookup of the documents `user:alice` can view, starting at `cursor`.
+    async fn pages(service: &LookupService, cursor: Option<String>) -> Vec<ResourcePage> {
+        let alice = parse_subject("user:alice");
+        let mut pages = Vec::new();
+        let mut cursor = cursor;
+        loop {
+            let (page, _) = service
+                .lookup_resources(
+                    "document",
+                    "view",
+                    &alice,
+                    cursor.as_deref(),
+                    Some(3),
+                    &Consistency::default(),
+                )
+                .await
+                .unwrap();
+            cursor = page.next_cursor.clone();
+            pages.push(page);
+            if cursor.is_none() {
+                return pages;
+            }
+        }
+    }
+
+    fn sorted(pages: &[ResourcePage]) -> Vec<String> {
+        let mut object_ids: Vec<String> = pages
+            .iter()
+            .flat_map(|page| page.object_ids.clone())
+            .collect();
+        object_ids.sort();
+        object_ids
+    }
+
+    /// Documents 0 to 9 viewed through `group:eng`, 10 to 12 by alice herself, 13 by every user,
+    /// and 14 by alice but banned.
+    async fn documents() -> DatabasePool {
+        let mut tuples = vec!["group:eng#member@user:alice".to_string()];
+        tuples.extend((0..10).map(|id| format!("document:{id:02}#viewer@group:eng#member")));
+        tuples.extend((10..13).map(|id| format!("document:{id:02}#viewer@user:alice")));
+        tuples.push("document:13#viewer@user:*".to_string());
+        tuples.push("document:14#viewer@user:alice".to_string());
+        tuples.push("document:14#banned@user:alice".to_string());
+        tuples.push("document:15#viewer@user:bob".to_string());
+        let tuples: Vec<&str> = tuples.iter().map(String::as_str).collect();
+        pool(
+            &[
+                "group#member",
+                "document#viewer",
+                "document#banned",
+                "document#view",
+            ],
+            &[rule("document#view", "exclusion", &["viewer", "banned"])],
+            &tuples,
+        )
+        .await
+    }
+
+    fn expected() -> Vec<String> {
+        (0..14).map(|id| format!("{id:02}")).collect()
+    }
+
+    #[tokio::test]
+    async fn pages_continue_the_walk_of_the_first_one() {
+        let service = service(documents().await);
+        let (first, _) = service
+            .lookup_resources(
+                "document",
+                "view",
+                &parse_subject("user:alice"),
+                None,
+                Some(3),
+                &Consistency::default(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(first.object_ids.len(), 3);
+        assert_eq!(service.walks().len(), 1);
+
+        let pages = pages(&service, None).await;
+        assert!(pages.len() >= 5, "{pages:?}");
+        assert!(pages.iter().all(|page| page.object_ids.len() <= 3));
+        assert_eq!(sorted(&pages), expected());
+        // The walk, which the lookup starting over took up again, is no longer kept once its
+        // last page was served.
+        assert!(service.walks().is_empty());
+    }
+
+    #[tokio::test]
+    async fn pages_retrace_a_walk_they_do_not_find() {
+        let pool = documents().await;
+        let first = pages(&service(pool.clone()), None).await;
+        let first_page = first[0].clone();
+
+        // Another node, which never saw the first page.
+        let rest = pages(&service(pool), first_page.next_cursor.clone()).await;
+        let mut pages = vec![first_page];
+        pages.extend(rest);
+        assert_eq!(sorted(&pages), expected());
+    }
+
+    #[tokio::test]
+    async fn invalid_cursors_are_rejected() {
+        let service = service(documents().await);
+        let result = service
+            .lookup_resources(
+                "document",
+                "view",
+                &parse_subject("user:alice"),
+                Some("not a cursor"),
+                None,
+                &Consistency::default(),
+            )
+            .await;
+        assert!(
+            matches!(result, Err(HeimdallError::InvalidArgument(_))),
+            "{result:?}"
+        );
+    }
+}
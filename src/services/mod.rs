@@ -1 +1,88 @@
+pub mod audit;
+pub mod check;
+pub mod check_cache;
+pub mod consistency;
+pub mod evaluator;
+pub mod expand;
+pub mod lookup;
+pub mod namespace;
+pub mod relationship;
+pub mod schema;
+pub mod singleflight;
+#[cfg(test)]
+mod testing;
+pub mod watch;
 
+pub use audit::DecisionRecorder;
+pub use check::CheckService;
+pub use check_cache::CheckCache;
+pub use consistency::{ConsistencyResolver, ZookieCodec};
+pub use expand::ExpandService;
+pub use lookup::LookupService;
+pub use namespace::NamespaceService;
+pub use relationship::RelationshipService;
+pub use schema::SchemaService;
+pub use watch::WatchService;
+
+use crate::error::{HeimdallError, HeimdallResult};
+
+/// Longest namespace id or relation name the schema accepts (`VARCHAR(64)`).
+pub const MAX_IDENTIFIER_LENGTH: usize = 64;
+
+/// Longest object or subject id the schema accepts (`VARCHAR(255)`).
+pub const MAX_OBJECT_ID_LENGTH: usize = 255;
+
+/// Page size used when a paginated read does not ask for one.
+pub const DEFAULT_PAGE_SIZE: usize = 100;
+
+/// Largest page size a paginated read may ask for.
+pub const MAX_PAGE_SIZE: usize = 1_000;
+
+/// Checks that `value` is usable as a namespace id or relation name: ASCII letters, digits and
+/// underscores, not starting with a digit. Anything else would be ambiguous in `type:id#relation`
+/// notation and rule expressions.
+pub fn validate_identifier(field: &str, value: &str) -> HeimdallResult<()> {
+    let valid = value.len() <= MAX_IDENTIFIER_LENGTH
+        && value
+            .chars()
+            .next()
+            .is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+        && value.chars().all(|c| c.is_ascii_alphanumeric() || c == '_');
+    if valid {
+        Ok(())
+    } else {
+        Err(HeimdallError::InvalidArgument(format!(
+            "`{field}` must be 1 to {MAX_IDENTIFIER_LENGTH} ASCII letters, digits or underscores \
+             and must not start with a digit, got `{value}`"
+        )))
+    }
+}
+
+/// Checks that `value` is usable as an object or subject id: not empty, and free of whitespace
+/// and the `:`, `#` and `@` separators of `type:id#relation@type:id` notation.
+pub fn validate_object_id(field: &str, value: &str) -> HeimdallResult<()> {
+    let valid = !value.is_empty()
+        && value.len() <= MAX_OBJECT_ID_LENGTH
+        && !value
+            .chars()
+            .any(|c| c.is_whitespace() || matches!(c, ':' | '#' | '@'));
+    if valid {
+        Ok(())
+    } else {
+        Err(HeimdallError::InvalidArgument(format!(
+            "`{field}` must be 1 to {MAX_OBJECT_ID_LENGTH} characters without whitespace, `:`, \
+             `#` or `@`, got `{value}`"
+        )))
+    }
+}
+
+/// Resolves the `limit` of a paginated read, [`DEFAULT_PAGE_SIZE`] if it asks for none.
+pub fn page_size(limit: Option<usize>) -> HeimdallResult<usize> {
+    match limit {
+        None => Ok(DEFAULT_PAGE_SIZE),
+        Some(limit) if (1..=MAX_PAGE_SIZE).contains(&limit) => Ok(limit),
+        Some(_) => Err(HeimdallError::InvalidArgument(format!(
+            "`limit` must be between 1 and {MAX_PAGE_SIZE}"
+        ))),
+    }
+}
@@ -0,0 +1,190 @@
+use crate::{
+    database::DatabasePool,
+    dtos::{
+        namespace::{CreateNamespaceRequest, UpdateNamespaceRequest},
+        relation::{CreateRelationRequest, UpdateRelationRequest},
+    },
+    entities::{Namespace, Relation},
+    error::{HeimdallError, HeimdallResult},
+    repositories::{NamespaceRepository, RelationDeletion, RelationRepository},
+};
+
+use super::{consistency::ZookieCodec, validate_identifier};
+
+/// Longest human-readable namespace name the schema accepts (`VARCHAR(255)`).
+const MAX_NAME_LENGTH: usize = 255;
+
+/// Management of namespaces and the relations defined in them.
+#[derive(Debug, Clone)]
+pub struct NamespaceService {
+    namespaces: NamespaceRepository,
+    relations: RelationRepository,
+    zookies: ZookieCodec,
+}
+
+impl NamespaceService {
+    pub fn new(pool: DatabasePool, zookies: ZookieCodec) -> Self {
+        Self {
+            namespaces: NamespaceRepository::new(pool.clone()),
+            relations: RelationRepository::new(pool),
+            zookies,
+        }
+    }
+
+    pub async fn list_namespaces(&self) -> HeimdallResult<Vec<Namespace>> {
+        Ok(self.namespaces.find_all().await?)
+    }
+
+    pub async fn get_namespace(&self, id: &str) -> HeimdallResult<Namespace> {
+        self.namespaces
+            .find_by_id(id)
+            .await?
+            .ok_or_else(|| HeimdallError::NamespaceNotFound(id.to_string()))
+    }
+
+    pub async fn create_namespace(
+        &self,
+        request: CreateNamespaceRequest,
+    ) -> HeimdallResult<Namespace> {
+        validate_identifier("id", &request.id)?;
+        validate_name(&request.name)?;
+
+        self.namespaces
+            .create(&request.id, &request.name, request.description.as_deref())
+            .await
+            .map_err(|e| {
+                HeimdallError::conflict_on_constraint(e, || {
+                    format!(
+                        "a namespace with id `{}` or name `{}` already exists",
+                        request.id, request.name
+                    )
+                })
+            })
+    }
+
+    pub async fn update_namespace(
+        &self,
+        id: &str,
+        request: UpdateNamespaceRequest,
+    ) -> HeimdallResult<Namespace> {
+        validate_name(&request.name)?;
+
+        self.namespaces
+            .update(id, &request.name, request.description.as_deref())
+            .await
+            .map_err(|e| {
+                HeimdallError::conflict_on_constraint(e, || {
+                    format!("a namespace named `{}` already exists", request.name)
+                })
+            })?
+            .ok_or_else(|| HeimdallError::NamespaceNotFound(id.to_string()))
+    }
+
+    pub async fn delete_namespace(&self, id: &str) -> HeimdallResult<()> {
+        let deleted = self.namespaces.delete(id).await.map_err(|e| {
+            HeimdallError::conflict_on_constraint(e, || {
+                format!("namespace `{id}` still has relations, delete them first")
+            })
+        })?;
+        if deleted {
+            Ok(())
+        } else {
+            Err(HeimdallError::NamespaceNotFound(id.to_string()))
+        }
+    }
+
+    pub async fn list_relations(
+        &self,
+        namespace_id: &str,
+        include_deleted: bool,
+    ) -> HeimdallResult<Vec<Relation>> {
+        self.get_namespace(namespace_id).await?;
+        Ok(self
+            .relations
+            .find_by_namespace(namespace_id, include_deleted)
+            .await?)
+    }
+
+    pub async fn get_relation(&self, namespace_id: &str, name: &str) -> HeimdallResult<Relation> {
+        self.relations
+            .find(namespace_id, name)
+            .await?
+            .ok_or_else(|| relation_not_found(namespace_id, name))
+    }
+
+    pub async fn create_relation(
+        &self,
+        namespace_id: &str,
+        request: CreateRelationRequest,
+    ) -> HeimdallResult<Relation> {
+        validate_identifier("name", &request.name)?;
+        self.get_namespace(namespace_id).await?;
+
+        self.relations
+            .create(namespace_id, &request.name, request.description.as_deref())
+            .await
+            .map_err(|e| {
+                // The namespace can only vanish if it was deleted concurrently.
+                HeimdallError::conflict_on_constraint(e, || {
+                    format!("namespace `{namespace_id}` was modified concurrently")
+                })
+            })?
+            .ok_or_else(|| {
+                HeimdallError::Conflict(format!(
+                    "relation `{}` already exists in namespace `{namespace_id}`",
+                    request.name
+                ))
+            })
+    }
+
+    pub async fn update_relation(
+        &self,
+        namespace_id: &str,
+        name: &str,
+        request: UpdateRelationRequest,
+    ) -> HeimdallResult<Relation> {
+        self.relations
+            .update(namespace_id, name, request.description.as_deref())
+            .await?
+            .ok_or_else(|| relation_not_found(namespace_id, name))
+    }
+
+    /// Soft-deletes the relation and returns the zookie of the version it was deleted at.
+    pub async fn delete_relation(&self, namespace_id: &str, name: &str) -> HeimdallResult<String> {
+        let mint_token = |version: i64| self.zookies.encode(version);
+        match self
+            .relations
+            .soft_delete(namespace_id, name, &mint_token)
+            .await?
+        {
+            RelationDeletion::Deleted { zookie } => Ok(zookie),
+            RelationDeletion::NotFound => Err(relation_not_found(namespace_id, name)),
+            RelationDeletion::InUse { tuple_count } => Err(HeimdallError::Conflict(format!(
+                "relation `{name}` in namespace `{namespace_id}` is still used by \
+                 {tuple_count} relationship tuple(s)"
+            ))),
+            RelationDeletion::Referenced { referenced_by } => {
+                Err(HeimdallError::Conflict(format!(
+                    "relation `{name}` in namespace `{namespace_id}` is still referred to by {}",
+                    referenced_by.join(", ")
+                )))
+            }
+        }
+    }
+}
+
+fn validate_name(name: &str) -> HeimdallResult<()> {
+    if name.trim().is_empty() || name.len() > MAX_NAME_LENGTH {
+        return Err(HeimdallError::InvalidArgument(format!(
+            "`name` must be between 1 and {MAX_NAME_LENGTH} characters"
+        )));
+    }
+    Ok(())
+}
+
+fn relation_not_found(namespace_id: &str, name: &str) -> HeimdallError {
+    HeimdallError::RelationNotFound {
+        namespace: namespace_id.to_string(),
+        relation: name.to_string(),
+    }
+}
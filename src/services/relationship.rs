@@ -0,0 +1,270 @@
+use std::collections::HashSet;
+
+use base64::{Engine, engine::general_purpose::URL_SAFE_NO_PAD};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    database::DatabasePool,
+    entities::Zookie,
+    error::{HeimdallError, HeimdallResult},
+    models::{
+        Consistency, ObjectRelation, Precondition, PreconditionOperation, Snapshot, SubjectRef,
+        TupleFilter, TupleKey, TupleUpdate, WILDCARD_SUBJECT_ID,
+    },
+    repositories::{RelationshipRepository, WriteOutcome},
+};
+
+use super::{
+    consistency::{ConsistencyResolver, ZookieCodec},
+    page_size, validate_identifier, validate_object_id,
+};
+
+/// Most updates a single write may contain.
+pub const MAX_UPDATES_PER_WRITE: usize = 1_000;
+
+/// Most preconditions a single write may contain.
+pub const MAX_PRECONDITIONS_PER_WRITE: usize = 100;
+
+/// One page of tuples, in key order.
+#[derive(Debug, Clone)]
+pub struct RelationshipPage {
+    pub relationships: Vec<TupleKey>,
+    /// Pass as `cursor` to fetch the next page, `None` on the last page.
+    pub next_cursor: Option<String>,
+}
+
+/// Position in a paginated read: the snapshot every page is read at and the last tuple
+/// returned. The zookie keeps clients from moving the snapshot to a version never issued.
+#[derive(Debug, Serialize, Deserialize)]
+struct ReadCursor {
+    zookie: String,
+    after: TupleKey,
+}
+
+/// Reads and writes of relationship tuples.
+#[derive(Debug, Clone)]
+pub struct RelationshipService {
+    relationships: RelationshipRepository,
+    zookies: ZookieCodec,
+    consistency: ConsistencyResolver,
+}
+
+impl RelationshipService {
+    pub fn new(pool: DatabasePool, zookies: ZookieCodec, consistency: ConsistencyResolver) -> Self {
+        Self {
+            relationships: RelationshipRepository::new(pool),
+            zookies,
+            consistency,
+        }
+    }
+
+    /// Tuples matching `filter`, one page at a time.
+    ///
+    /// The first page is read at a snapshot satisfying `consistency`, and its cursor pins every
+    /// following page to that snapshot, so concurrent writes neither skip nor repeat tuples.
+    pub async fn read(
+        &self,
+        filter: &TupleFilter,
+        cursor: Option<&str>,
+        limit: Option<usize>,
+        consistency: &Consistency,
+    ) -> HeimdallResult<(RelationshipPage, Snapshot)> {
+        validate_filter_fields(filter)?;
+        let limit = page_size(limit)?;
+
+        let (snapshot, after) = match cursor {
+            Some(cursor) => {
+                let invalid =
+                    || HeimdallError::InvalidArgument("`cursor` is not valid".to_string());
+                let cursor: ReadCursor = URL_SAFE_NO_PAD
+                    .decode(cursor)
+                    .ok()
+                    .and_then(|json| serde_json::from_slice(&json).ok())
+                    .ok_or_else(invalid)?;
+                let snapshot = self.consistency.pinned(&cursor.zookie)?;
+                (snapshot, Some(cursor.after))
+            }
+            None => (self.consistency.resolve(consistency).await?, None),
+        };
+
+        // One extra tuple tells whether there is a next page.
+        let mut relationships = self
+            .relationships
+            .find_at_version(filter, snapshot.version, after.as_ref(), limit as i64 + 1)
+            .await?;
+        let next_cursor = if relationships.len() > limit {
+            relationships.truncate(limit);
+            relationships.last().map(|last| {
+                let cursor = ReadCursor {
+                    zookie: snapshot.zookie.clone(),
+                    after: last.clone(),
+                };
+                URL_SAFE_NO_PAD.encode(serde_json::to_vec(&cursor).expect("cursors serialize"))
+            })
+        } else {
+            None
+        };
+
+        let page = RelationshipPage {
+            relationships,
+            next_cursor,
+        };
+        Ok((page, snapshot))
+    }
+
+    /// Applies `updates` atomically if every precondition holds, returning the zookie of the
+    /// commit.
+    pub async fn write(
+        &self,
+        updates: &[TupleUpdate],
+        preconditions: &[Precondition],
+    ) -> HeimdallResult<Zookie> {
+        if updates.is_empty() {
+            return Err(HeimdallError::InvalidArgument(
+                "`updates` must not be empty".to_string(),
+            ));
+        }
+        if updates.len() > MAX_UPDATES_PER_WRITE {
+            return Err(HeimdallError::InvalidArgument(format!(
+                "at most {MAX_UPDATES_PER_WRITE} updates are allowed per write, got {}",
+                updates.len()
+            )));
+        }
+        if preconditions.len() > MAX_PRECONDITIONS_PER_WRITE {
+            return Err(HeimdallError::InvalidArgument(format!(
+                "at most {MAX_PRECONDITIONS_PER_WRITE} preconditions are allowed per write, got {}",
+                preconditions.len()
+            )));
+        }
+
+        // Two updates of one tuple would make the result depend on their order.
+        let mut seen = HashSet::with_capacity(updates.len());
+        for (index, update) in updates.iter().enumerate() {
+            validate_tuple(&update.relationship)
+                .map_err(|e| in_field(e, &format!("updates[{index}].relationship")))?;
+            if !seen.insert(&update.relationship) {
+                return Err(HeimdallError::InvalidArgument(format!(
+                    "`updates[{index}]` updates `{}` a second time",
+                    update.relationship
+                )));
+            }
+        }
+        for (index, precondition) in preconditions.iter().enumerate() {
+            validate_filter(&precondition.filter)
+                .map_err(|e| in_field(e, &format!("preconditions[{index}].filter")))?;
+        }
+
+        let mint_token = |version: i64| self.zookies.encode(version);
+        match self
+            .relationships
+            .write(updates, preconditions, &mint_token)
+            .await
+            .map_err(|e| {
+                // A relation can only disappear this late if it was hard-deleted concurrently.
+                HeimdallError::conflict_on_constraint(e, || {
+                    "a relation used by the write was deleted concurrently".to_string()
+                })
+            })? {
+            WriteOutcome::Committed(zookie) => Ok(zookie),
+            WriteOutcome::PreconditionFailed(index) => {
+                let expectation = match preconditions[index].operation {
+                    PreconditionOperation::MustExist => "a matching tuple to exist",
+                    PreconditionOperation::MustNotExist => "no matching tuple to exist",
+                };
+                Err(HeimdallError::PreconditionFailed(format!(
+                    "`preconditions[{index}]` expected {expectation}"
+                )))
+            }
+            WriteOutcome::AlreadyExists(index) => Err(HeimdallError::Conflict(format!(
+                "`updates[{index}]` creates `{}`, which already exists",
+                updates[index].relationship
+            ))),
+            WriteOutcome::NamespaceNotFound(namespace) => {
+                Err(HeimdallError::NamespaceNotFound(namespace))
+            }
+            WriteOutcome::RelationNotFound {
+                namespace,
+                relation,
+            } => Err(HeimdallError::RelationNotFound {
+                namespace,
+                relation,
+            }),
+        }
+    }
+}
+
+/// Checks the shape of a tuple; whether its namespaces and relations exist is up to the
+/// repository.
+pub fn validate_tuple(tuple: &TupleKey) -> HeimdallResult<()> {
+    validate_object_relation(&tuple.object())?;
+    validate_subject(&tuple.subject())
+}
+
+pub fn validate_object_relation(object: &ObjectRelation) -> HeimdallResult<()> {
+    validate_identifier("namespace", &object.namespace)?;
+    validate_object_id("object_id", &object.object_id)?;
+    validate_identifier("relation", &object.relation)?;
+    if object.object_id == WILDCARD_SUBJECT_ID {
+        return Err(HeimdallError::InvalidArgument(format!(
+            "`object_id` must not be the wildcard `{WILDCARD_SUBJECT_ID}`"
+        )));
+    }
+    Ok(())
+}
+
+pub fn validate_subject(subject: &SubjectRef) -> HeimdallResult<()> {
+    validate_identifier("subject_type", &subject.subject_type)?;
+    validate_object_id("subject_id", &subject.subject_id)?;
+    if let Some(subject_relation) = &subject.subject_relation {
+        validate_identifier("subject_relation", subject_relation)?;
+        if subject.subject_id == WILDCARD_SUBJECT_ID {
+            return Err(HeimdallError::InvalidArgument(format!(
+                "a wildcard `{WILDCARD_SUBJECT_ID}` subject cannot have a `subject_relation`"
+            )));
+        }
+    }
+    Ok(())
+}
+
+pub fn validate_filter(filter: &TupleFilter) -> HeimdallResult<()> {
+    if filter.is_empty() {
+        return Err(HeimdallError::InvalidArgument(
+            "at least one field must be set".to_string(),
+        ));
+    }
+    validate_filter_fields(filter)
+}
+
+/// Checks the fields a filter sets, an empty filter matching every tuple is fine.
+pub fn validate_filter_fields(filter: &TupleFilter) -> HeimdallResult<()> {
+    let identifiers = [
+        ("namespace", &filter.namespace),
+        ("relation", &filter.relation),
+        ("subject_type", &filter.subject_type),
+        ("subject_relation", &filter.subject_relation),
+    ];
+    for (field, value) in identifiers {
+        if let Some(value) = value {
+            validate_identifier(field, value)?;
+        }
+    }
+    for (field, value) in [
+        ("object_id", &filter.object_id),
+        ("subject_id", &filter.subject_id),
+    ] {
+        if let Some(value) = value {
+            validate_object_id(field, value)?;
+        }
+    }
+    Ok(())
+}
+
+/// Prefixes an argument error with the path of the offending field in the request.
+fn in_field(error: HeimdallError, path: &str) -> HeimdallError {
+    match error {
+        HeimdallError::InvalidArgument(message) => {
+            HeimdallError::InvalidArgument(format!("`{path}`: {message}"))
+        }
+        other => other,
+    }
+}
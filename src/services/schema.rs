@@ -0,0 +1,323 @@
+mod validation;
+
+use std::collections::{HashMap, HashSet};
+
+use crate::{
+    database::DatabasePool,
+    entities::RelationRule,
+    error::{HeimdallError, HeimdallResult},
+    models::{
+        Definition, Expression, ExpressionKind, OrphanedTuples, RelationDefinition, RelationKind,
+        RelationMigration, Rewrite, Schema, SchemaChanges, SchemaWarning, SchemaWriteOptions,
+        SubjectType,
+    },
+    repositories::{
+        NamespaceRepository, NamespaceSchema, RelationRepository, RelationRuleRepository,
+        RelationSchema, RuleSchema, SchemaApply, SchemaRepository,
+    },
+};
+
+use super::{check_cache::CheckCache, consistency::ZookieCodec};
+
+/// Reads and writes the namespaces, relations and rules as a whole, in the schema language.
+#[derive(Debug, Clone)]
+pub struct SchemaService {
+    schemas: SchemaRepository,
+    namespaces: NamespaceRepository,
+    relations: RelationRepository,
+    rules: RelationRuleRepository,
+    zookies: ZookieCodec,
+    check_cache: CheckCache,
+}
+
+impl SchemaService {
+    pub fn new(pool: DatabasePool, zookies: ZookieCodec, check_cache: CheckCache) -> Self {
+        Self {
+            schemas: SchemaRepository::new(pool.clone()),
+            namespaces: NamespaceRepository::new(pool.clone()),
+            relations: RelationRepository::new(pool.clone()),
+            rules: RelationRuleRepository::new(pool),
+            zookies,
+            check_cache,
+        }
+    }
+
+    /// The stored schema. Relations with rules become permissions, whether the rules were
+    /// written by a schema or by hand.
+    pub async fn read_schema(&self) -> HeimdallResult<Schema> {
+        let namespaces = self.namespaces.find_all().await?;
+        let relations = self.relations.find_all().await?;
+        let rules = self.rules.find_all().await?;
+
+        let mut rules_by_relation: HashMap<(&str, &str), Vec<RelationRule>> = HashMap::new();
+        for rule in &rules {
+            rules_by_relation
+                .entry((&rule.namespace_id, &rule.relation_name))
+                .or_default()
+                .push(rule.clone());
+        }
+
+        let mut definitions: Vec<Definition> = namespaces
+            .into_iter()
+            .map(|namespace| Definition {
+                name: namespace.id,
+                relations: Vec::new(),
+                position: None,
+            })
+            .collect();
+        for relation in relations {
+            let rules = rules_by_relation
+                .get(&(relation.namespace_id.as_str(), relation.name.as_str()))
+                .map(Vec::as_slice)
+                .unwrap_or_default();
+            let kind = if rules.is_empty() {
+                let subject_types = relation.subject_types.map(|types| types.0);
+                RelationKind::Relation {
+                    subject_types: subject_types
+                        .iter()
+                        .flatten()
+                        .map(|subject_type| SubjectType::from_stored(subject_type))
+                        .collect(),
+                }
+            } else {
+                RelationKind::Permission(Expression::from_rewrite(&Rewrite::from_rules(rules)?))
+            };
+            if let Some(definition) = definitions
+                .iter_mut()
+                .find(|definition| definition.name == relation.namespace_id)
+            {
+                definition.relations.push(RelationDefinition {
+                    name: relation.name,
+                    kind,
+                    position: None,
+                });
+            }
+        }
+        Ok(Schema { definitions })
+    }
+
+    /// Parses `source` and makes the stored schema match it. Namespaces and relations the
+    /// schema leaves out are deleted. That fails with a report of the tuples and permissions
+    /// still using them, unless `options` migrates or forces away every such tuple.
+    pub async fn write_schema(
+        &self,
+        source: &str,
+        options: &SchemaWriteOptions,
+    ) -> HeimdallResult<SchemaChanges> {
+        let (schema, warnings) = parse_schema(source)?;
+        validate_migrations(&schema, &options.migrations)?;
+        let namespaces: Vec<NamespaceSchema> =
+            schema.definitions.iter().map(namespace_schema).collect();
+        let mint_token = |version: i64| self.zookies.encode(version);
+        let applied = self
+            .schemas
+            .apply(&namespaces, options, &mint_token)
+            .await
+            .map_err(|e| {
+                HeimdallError::conflict_on_constraint(e, || {
+                    "the schema conflicts with an existing namespace name or relationship"
+                        .to_string()
+                })
+            })?;
+        match applied {
+            SchemaApply::Applied(changes) => {
+                // The results held in process were computed at older versions, which the apply
+                // leaves behind; other nodes let theirs age out of the cache.
+                self.check_cache.clear();
+                Ok(SchemaChanges {
+                    warnings,
+                    ..changes
+                })
+            }
+            SchemaApply::Rejected(orphaned) => Err(HeimdallError::SchemaInUse {
+                message: rejection_message(&orphaned, options.force),
+                orphaned,
+            }),
+        }
+    }
+}
+
+/// Explains why [`SchemaRepository::apply`] refused to remove relations and namespaces, and
+/// how to get past it.
+fn rejection_message(orphaned: &[OrphanedTuples], force: bool) -> String {
+    let mut problems = Vec::new();
+    let mut forceable = false;
+    for orphaned in orphaned {
+        let name = orphaned.name();
+        if !orphaned.referenced_by.is_empty() {
+            let permissions = orphaned
+                .referenced_by
+                .iter()
+                .map(|permission| format!("`{permission}`"))
+                .collect::<Vec<_>>()
+                .join(", ");
+            problems.push(format!("`{name}` is referenced by {permissions}"));
+        }
+        if orphaned.migrated_to.is_none() && !force {
+            let mut uses = Vec::new();
+            if orphaned.tuple_count > 0 {
+                uses.push(format!("{} tuple(s)", orphaned.tuple_count));
+            }
+            if orphaned.subject_tuple_count > 0 {
+                uses.push(format!(
+                    "{} tuple(s) with it as subject",
+                    orphaned.subject_tuple_count
+                ));
+            }
+            if !uses.is_empty() {
+                problems.push(format!("`{name}` is used by {}", uses.join(" and ")));
+                forceable = true;
+            }
+        }
+    }
+    let mut message = format!(
+        "the schema removes what is still in use: {}",
+        problems.join("; ")
+    );
+    if forceable {
+        message.push_str(
+            "; migrate the tuples to another relation with `migrate`, or delete them with `force`",
+        );
+    }
+    message
+}
+
+/// Migrations must move tuples off a relation of a defined namespace the schema removes, onto
+/// one it keeps.
+fn validate_migrations(schema: &Schema, migrations: &[RelationMigration]) -> HeimdallResult<()> {
+    let mut sources = HashSet::new();
+    for migration in migrations {
+        let name = format!("{}#{}", migration.namespace, migration.from);
+        let Some(definition) = schema
+            .definitions
+            .iter()
+            .find(|definition| definition.name == migration.namespace)
+        else {
+            return Err(HeimdallError::InvalidArgument(format!(
+                "cannot migrate `{name}`: the schema does not define `{}`",
+                migration.namespace
+            )));
+        };
+        let defines = |relation: &str| {
+            definition
+                .relations
+                .iter()
+                .any(|defined| defined.name == relation)
+        };
+        if defines(&migration.from) {
+            return Err(HeimdallError::InvalidArgument(format!(
+                "cannot migrate `{name}`: the schema keeps it"
+            )));
+        }
+        if !defines(&migration.to) {
+            return Err(HeimdallError::InvalidArgument(format!(
+                "cannot migrate `{name}` to `{}`: the schema does not define it",
+                migration.to
+            )));
+        }
+        if !sources.insert(name.clone()) {
+            return Err(HeimdallError::InvalidArgument(format!(
+                "`{name}` is migrated twice"
+            )));
+        }
+    }
+    Ok(())
+}
+
+/// Parses `source` and checks what the grammar cannot, see [`validation`]. The warnings point
+/// out likely mistakes that do not keep the schema from working.
+pub fn parse_schema(source: &str) -> HeimdallResult<(Schema, Vec<SchemaWarning>)> {
+    let schema = Schema::parse(source)?;
+    let warnings = validation::validate_schema(&schema)?;
+    Ok((schema, warnings))
+}
+
+fn namespace_schema(definition: &Definition) -> NamespaceSchema {
+    NamespaceSchema {
+        id: definition.name.clone(),
+        relations: definition
+            .relations
+            .iter()
+            .map(|relation| match &relation.kind {
+                RelationKind::Relation { subject_types } => RelationSchema {
+                    name: relation.name.clone(),
+                    subject_types: (!subject_types.is_empty())
+                        .then(|| subject_types.iter().map(ToString::to_string).collect()),
+                    rule: None,
+                },
+                RelationKind::Permission(expression) => RelationSchema {
+                    name: relation.name.clone(),
+                    subject_types: None,
+                    rule: Some(rule_schema(expression)),
+                },
+            })
+            .collect(),
+    }
+}
+
+/// The single rule storing a permission. The expression defines it, the other columns
+/// summarize it for readers of the table: the outermost operation, the relations of the
+/// namespace it refers to, and the computed relation of an outermost arrow.
+fn rule_schema(expression: &Expression) -> RuleSchema {
+    let relations = expression.relations();
+    let ttu_relation = match &expression.kind {
+        ExpressionKind::Arrow {
+            computed_relation, ..
+        } => Some(computed_relation.clone()),
+        _ => None,
+    };
+    RuleSchema {
+        rule_type: expression.rule_type(),
+        ttu_object_namespace: None,
+        ttu_relation,
+        child_relations: (!relations.is_empty())
+            .then(|| relations.into_iter().map(ToString::to_string).collect()),
+        expression: expression.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        config::{CacheConfig, ConsistencyConfig},
+        database::memory_pool,
+        repositories::RelationshipRepository,
+    };
+
+    use super::*;
+
+    const SCHEMA: &str = "definition user {}\ndefinition document {\n    relation owner: user\n    \
+                          relation reader: user\n    permission view = owner + reader\n}\n";
+
+    #[tokio::test]
+    async fn changes_are_committed_as_a_new_version() {
+        let pool = memory_pool().await;
+        let zookies = ZookieCodec::new(&ConsistencyConfig::default());
+        let check_cache = CheckCache::new(&CacheConfig::default());
+        let service = SchemaService::new(pool.clone(), zookies.clone(), check_cache);
+        let relationships = RelationshipRepository::new(pool);
+        let options = SchemaWriteOptions::default();
+
+        let created = service.write_schema(SCHEMA, &options).await.unwrap();
+        let version = relationships.current_version().await.unwrap();
+        assert_eq!(created.zookie, Some(zookies.encode(version)));
+
+        let unchanged = service.write_schema(SCHEMA, &options).await.unwrap();
+        assert!(unchanged.is_empty(), "{unchanged:?}");
+        assert_eq!(relationships.current_version().await.unwrap(), version);
+
+        let narrowed = SCHEMA.replace("owner + reader", "owner");
+        let dry_run = SchemaWriteOptions {
+            dry_run: true,
+            ..SchemaWriteOptions::default()
+        };
+        let planned = service.write_schema(&narrowed, &dry_run).await.unwrap();
+        assert_eq!(planned.updated_relations, ["document#view"]);
+        assert_eq!(planned.zookie, None);
+        assert_eq!(relationships.current_version().await.unwrap(), version);
+
+        let updated = service.write_schema(&narrowed, &options).await.unwrap();
+        assert_eq!(updated.zookie, Some(zookies.encode(version + 1)));
+        assert_eq!(relationships.current_version().await.unwrap(), version + 1);
+    }
+}
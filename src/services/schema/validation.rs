@@ -0,0 +1,696 @@
+//! Checks of a parsed schema that the grammar cannot express: unique names that fit their
+//! columns, references to relations and definitions that exist, and permissions that can be
+//! computed at all. Errors name the position of the offending element when the schema was
+//! parsed from source.
+
+use std::collections::{HashMap, HashSet};
+
+use crate::{
+    error::{HeimdallError, HeimdallResult},
+    models::{
+        Definition, Expression, ExpressionKind, Position, RelationDefinition, RelationKind, Schema,
+        SchemaWarning,
+    },
+    services::MAX_IDENTIFIER_LENGTH,
+};
+
+/// The relations of every definition, by name.
+type Definitions<'a> = HashMap<&'a str, HashMap<&'a str, &'a RelationDefinition>>;
+
+/// A relation or permission, `(namespace, relation)`.
+type Node<'a> = (&'a str, &'a str);
+
+/// Fails on the first error in `schema`, otherwise returns its warnings.
+pub(super) fn validate_schema(schema: &Schema) -> HeimdallResult<Vec<SchemaWarning>> {
+    validate_names(schema)?;
+    let definitions: Definitions = schema
+        .definitions
+        .iter()
+        .map(|definition| {
+            let relations = definition
+                .relations
+                .iter()
+                .map(|relation| (relation.name.as_str(), relation))
+                .collect();
+            (definition.name.as_str(), relations)
+        })
+        .collect();
+    validate_references(schema, &definitions)?;
+    validate_rewrite_cycles(schema, &definitions)?;
+    validate_cycles(schema, &definitions)?;
+    Ok(unreachable_relations(schema, &definitions))
+}
+
+fn validate_names(schema: &Schema) -> HeimdallResult<()> {
+    let mut definitions = HashSet::new();
+    for definition in &schema.definitions {
+        validate_length("definition name", &definition.name, definition.position)?;
+        if !definitions.insert(definition.name.as_str()) {
+            return Err(invalid_at(
+                definition.position,
+                format!("definition `{}` is defined twice", definition.name),
+            ));
+        }
+
+        let mut relations = HashSet::new();
+        for relation in &definition.relations {
+            validate_length("relation name", &relation.name, relation.position)?;
+            if !relations.insert(relation.name.as_str()) {
+                return Err(invalid_at(
+                    relation.position,
+                    format!(
+                        "`{}` is defined twice in definition `{}`",
+                        relation.name, definition.name
+                    ),
+                ));
+            }
+            match &relation.kind {
+                RelationKind::Relation { subject_types } => {
+                    for subject_type in subject_types {
+                        validate_length(
+                            "subject type",
+                            &subject_type.namespace,
+                            subject_type.position,
+                        )?;
+                        if let Some(relation) = &subject_type.relation {
+                            validate_length("relation name", relation, subject_type.position)?;
+                        }
+                    }
+                }
+                RelationKind::Permission(expression) => {
+                    for leaf in leaves(expression) {
+                        match &leaf.kind {
+                            ExpressionKind::Relation(relation) => {
+                                validate_length("relation name", relation, leaf.position)?;
+                            }
+                            ExpressionKind::Arrow {
+                                tupleset,
+                                computed_relation,
+                            } => {
+                                validate_length("relation name", tupleset, leaf.position)?;
+                                validate_length("relation name", computed_relation, leaf.position)?;
+                            }
+                            _ => {}
+                        }
+                    }
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Subject types must name definitions and their relations, permissions the relations of
+/// their own definition, and arrows relations that exist where their tupleset leads.
+fn validate_references(schema: &Schema, definitions: &Definitions) -> HeimdallResult<()> {
+    for definition in &schema.definitions {
+        let relations = &definitions[definition.name.as_str()];
+        for relation in &definition.relations {
+            let qualified = format!("{}#{}", definition.name, relation.name);
+            let expression = match &relation.kind {
+                RelationKind::Relation { subject_types } => {
+                    for subject_type in subject_types {
+                        let Some(target) = definitions.get(subject_type.namespace.as_str()) else {
+                            return Err(invalid_at(
+                                subject_type.position,
+                                format!(
+                                    "subject type `{subject_type}` of `{qualified}` is not a \
+                                     definition"
+                                ),
+                            ));
+                        };
+                        if let Some(subject_relation) = &subject_type.relation
+                            && !target.contains_key(subject_relation.as_str())
+                        {
+                            return Err(invalid_at(
+                                subject_type.position,
+                                format!(
+                                    "subject type `{subject_type}` of `{qualified}` is not \
+                                     defined in definition `{}`",
+                                    subject_type.namespace
+                                ),
+                            ));
+                        }
+                    }
+                    continue;
+                }
+                RelationKind::Permission(expression) => expression,
+            };
+
+            for leaf in leaves(expression) {
+                let (referenced, arrow) = match &leaf.kind {
+                    ExpressionKind::Relation(referenced) => (referenced, None),
+                    ExpressionKind::Arrow {
+                        tupleset,
+                        computed_relation,
+                    } => (tupleset, Some(computed_relation)),
+                    _ => continue,
+                };
+                if !relations.contains_key(referenced.as_str()) {
+                    return Err(invalid_at(
+                        leaf.position,
+                        format!(
+                            "`{qualified}` refers to `{referenced}`, which is not defined in \
+                             definition `{}`",
+                            definition.name
+                        ),
+                    ));
+                }
+                let (Some(computed_relation), Some(targets)) =
+                    (arrow, arrow_targets(relations, referenced))
+                else {
+                    continue;
+                };
+                let mut reachable = false;
+                for target in &targets {
+                    let Some(target_relations) = definitions.get(target) else {
+                        return Err(invalid_at(
+                            leaf.position,
+                            format!(
+                                "`{referenced}->{computed_relation}` of `{qualified}` leads to \
+                                 `{target}`, which is not a definition"
+                            ),
+                        ));
+                    };
+                    reachable |= target_relations.contains_key(computed_relation.as_str());
+                }
+                if !reachable {
+                    let targets = match targets.as_slice() {
+                        [target] => format!("`{target}`, which does not define"),
+                        _ => format!("{}, none of which defines", names(&targets)),
+                    };
+                    return Err(invalid_at(
+                        leaf.position,
+                        format!(
+                            "`{referenced}->{computed_relation}` of `{qualified}` leads to \
+                             {targets} `{computed_relation}`"
+                        ),
+                    ));
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Permissions must not be computed from themselves through other permissions alone, like
+/// `viewer = reader + editor` and `editor = owner + viewer`: checks fail on such a cycle, even
+/// though `reader` and `owner` could end it. A cycle through an arrow follows tuples instead and
+/// ends where they do.
+fn validate_rewrite_cycles(schema: &Schema, definitions: &Definitions) -> HeimdallResult<()> {
+    let mut finished = HashSet::new();
+    for definition in &schema.definitions {
+        for relation in &definition.relations {
+            let node = (definition.name.as_str(), relation.name.as_str());
+            find_rewrite_cycle(definitions, node, &mut Vec::new(), &mut finished)?;
+        }
+    }
+    Ok(())
+}
+
+/// Follows the permissions `node` is computed from depth-first, failing once one leads back into
+/// `path`. Nodes in `finished` are known not to lead into a cycle.
+fn find_rewrite_cycle<'a>(
+    definitions: &Definitions<'a>,
+    node: Node<'a>,
+    path: &mut Vec<Node<'a>>,
+    finished: &mut HashSet<Node<'a>>,
+) -> HeimdallResult<()> {
+    if finished.contains(&node) {
+        return Ok(());
+    }
+    if let Some(start) = path.iter().position(|visited| *visited == node) {
+        let cycle = path[start..]
+            .iter()
+            .chain([&node])
+            .map(|(namespace, relation)| format!("`{namespace}#{relation}`"))
+            .collect::<Vec<_>>()
+            .join(" -> ");
+        let (namespace, relation) = path[start];
+        return Err(invalid_at(
+            definitions[namespace][relation].position,
+            format!("{cycle} is a cycle of rewrites, which fails every check reaching it"),
+        ));
+    }
+
+    let (namespace, relation) = node;
+    let definition: &'a RelationDefinition = definitions[namespace][relation];
+    if let RelationKind::Permission(expression) = &definition.kind {
+        path.push(node);
+        for leaf in leaves(expression) {
+            if let ExpressionKind::Relation(next) = &leaf.kind {
+                find_rewrite_cycle(definitions, (namespace, next.as_str()), path, finished)?;
+            }
+        }
+        path.pop();
+    }
+    finished.insert(node);
+    Ok(())
+}
+
+/// Permissions must be able to hold a subject. One that can only be computed from itself through
+/// arrows, like `view = parent->view` of a folder whose parent is a folder, holds no one whatever
+/// tuples are written.
+fn validate_cycles(schema: &Schema, definitions: &Definitions) -> HeimdallResult<()> {
+    let permissions: Vec<(&Definition, &RelationDefinition, &Expression)> = schema
+        .definitions
+        .iter()
+        .flat_map(|definition| {
+            definition
+                .relations
+                .iter()
+                .filter_map(move |relation| match &relation.kind {
+                    RelationKind::Permission(expression) => {
+                        Some((definition, relation, expression))
+                    }
+                    RelationKind::Relation { .. } => None,
+                })
+        })
+        .collect();
+
+    // Relations hold their tuples, permissions are grounded once their expression is.
+    let mut grounded: HashSet<Node> = schema
+        .definitions
+        .iter()
+        .flat_map(|definition| {
+            definition
+                .relations
+                .iter()
+                .filter(|relation| matches!(relation.kind, RelationKind::Relation { .. }))
+                .map(|relation| (definition.name.as_str(), relation.name.as_str()))
+        })
+        .collect();
+    loop {
+        let mut changed = false;
+        for (definition, relation, expression) in &permissions {
+            let node = (definition.name.as_str(), relation.name.as_str());
+            if !grounded.contains(&node)
+                && is_grounded(definitions, definition, expression, &grounded)
+            {
+                grounded.insert(node);
+                changed = true;
+            }
+        }
+        if !changed {
+            break;
+        }
+    }
+
+    // Every ungrounded permission depends on another one, following them ends in a cycle.
+    let Some((definition, relation, expression)) =
+        permissions.iter().find(|(definition, relation, _)| {
+            !grounded.contains(&(definition.name.as_str(), relation.name.as_str()))
+        })
+    else {
+        return Ok(());
+    };
+    let mut path = vec![(definition.name.as_str(), relation.name.as_str())];
+    let mut expression = *expression;
+    let mut definition = *definition;
+    loop {
+        let Some(next) = ungrounded_dependency(definitions, definition, expression, &grounded)
+        else {
+            return Ok(());
+        };
+        if let Some(start) = path.iter().position(|node| *node == next) {
+            let cycle = path[start..]
+                .iter()
+                .chain([&next])
+                .map(|(namespace, relation)| format!("`{namespace}#{relation}`"))
+                .collect::<Vec<_>>()
+                .join(" -> ");
+            let (namespace, relation) = path[start];
+            return Err(invalid_at(
+                definitions[namespace][relation].position,
+                format!("{cycle} is a rewrite cycle without a relation to end it"),
+            ));
+        }
+        path.push(next);
+        let (namespace, relation) = next;
+        let Some(next_definition) = schema
+            .definitions
+            .iter()
+            .find(|definition| definition.name == namespace)
+        else {
+            return Ok(());
+        };
+        let RelationKind::Permission(next_expression) = &definitions[namespace][relation].kind
+        else {
+            return Ok(());
+        };
+        definition = next_definition;
+        expression = next_expression;
+    }
+}
+
+/// Whether `expression` can hold a subject given the `grounded` relations and permissions.
+/// Arrows whose targets are unknown are assumed to.
+fn is_grounded(
+    definitions: &Definitions,
+    definition: &Definition,
+    expression: &Expression,
+    grounded: &HashSet<Node>,
+) -> bool {
+    let namespace = definition.name.as_str();
+    match &expression.kind {
+        ExpressionKind::This => true,
+        ExpressionKind::Relation(relation) => grounded.contains(&(namespace, relation.as_str())),
+        ExpressionKind::Arrow {
+            tupleset,
+            computed_relation,
+        } => {
+            grounded.contains(&(namespace, tupleset.as_str()))
+                && arrow_targets(&definitions[namespace], tupleset).is_none_or(|targets| {
+                    targets
+                        .iter()
+                        .any(|target| grounded.contains(&(target, computed_relation.as_str())))
+                })
+        }
+        ExpressionKind::Union(children) => children
+            .iter()
+            .any(|child| is_grounded(definitions, definition, child, grounded)),
+        ExpressionKind::Intersection(children) => children
+            .iter()
+            .all(|child| is_grounded(definitions, definition, child, grounded)),
+        ExpressionKind::Exclusion { base, .. } => {
+            is_grounded(definitions, definition, base, grounded)
+        }
+    }
+}
+
+/// A relation or permission keeping the ungrounded `expression` from being grounded.
+fn ungrounded_dependency<'a>(
+    definitions: &Definitions<'a>,
+    definition: &'a Definition,
+    expression: &'a Expression,
+    grounded: &HashSet<Node>,
+) -> Option<Node<'a>> {
+    let namespace = definition.name.as_str();
+    match &expression.kind {
+        ExpressionKind::This => None,
+        ExpressionKind::Relation(relation) => {
+            let node = (namespace, relation.as_str());
+            (!grounded.contains(&node)).then_some(node)
+        }
+        ExpressionKind::Arrow {
+            tupleset,
+            computed_relation,
+        } => {
+            let node = (namespace, tupleset.as_str());
+            if !grounded.contains(&node) {
+                return Some(node);
+            }
+            arrow_targets(&definitions[namespace], tupleset)?
+                .into_iter()
+                .filter_map(|target| definitions.get_key_value(target))
+                .find(|(_, relations)| relations.contains_key(computed_relation.as_str()))
+                .map(|(target, _)| (*target, computed_relation.as_str()))
+        }
+        ExpressionKind::Union(children) | ExpressionKind::Intersection(children) => children
+            .iter()
+            .filter(|child| !is_grounded(definitions, definition, child, grounded))
+            .find_map(|child| ungrounded_dependency(definitions, definition, child, grounded)),
+        ExpressionKind::Exclusion { base, .. } => {
+            ungrounded_dependency(definitions, definition, base, grounded)
+        }
+    }
+}
+
+/// Warns about relations of definitions with permissions that no permission, arrow or subject
+/// type refers to, so no permission is computed from them.
+fn unreachable_relations(schema: &Schema, definitions: &Definitions) -> Vec<SchemaWarning> {
+    let mut used: HashSet<Node> = HashSet::new();
+    for definition in &schema.definitions {
+        let namespace = definition.name.as_str();
+        for relation in &definition.relations {
+            let expression = match &relation.kind {
+                RelationKind::Relation { subject_types } => {
+                    used.extend(subject_types.iter().filter_map(|subject_type| {
+                        let relation = subject_type.relation.as_deref()?;
+                        Some((subject_type.namespace.as_str(), relation))
+                    }));
+                    continue;
+                }
+                RelationKind::Permission(expression) => expression,
+            };
+            for leaf in leaves(expression) {
+                match &leaf.kind {
+                    ExpressionKind::Relation(relation) => {
+                        used.insert((namespace, relation));
+                    }
+                    ExpressionKind::Arrow {
+                        tupleset,
+                        computed_relation,
+                    } => {
+                        used.insert((namespace, tupleset));
+                        let relations = &definitions[namespace];
+                        match arrow_targets(relations, tupleset) {
+                            Some(targets) => used.extend(
+                                targets
+                                    .into_iter()
+                                    .map(|target| (target, computed_relation.as_str())),
+                            ),
+                            None => used.extend(
+                                definitions
+                                    .keys()
+                                    .map(|target| (*target, computed_relation.as_str())),
+                            ),
+                        }
+                    }
+                    _ => {}
+                }
+            }
+        }
+    }
+
+    let mut warnings = Vec::new();
+    for definition in &schema.definitions {
+        let has_permissions = definition
+            .relations
+            .iter()
+            .any(|relation| matches!(relation.kind, RelationKind::Permission(_)));
+        if !has_permissions {
+            continue;
+        }
+        for relation in &definition.relations {
+            if matches!(relation.kind, RelationKind::Relation { .. })
+                && !used.contains(&(definition.name.as_str(), relation.name.as_str()))
+            {
+                warnings.push(SchemaWarning {
+                    message: format!(
+                        "relation `{}#{}` is unreachable, no permission refers to it",
+                        definition.name, relation.name
+                    ),
+                    position: relation.position,
+                });
+            }
+        }
+    }
+    warnings
+}
+
+/// Definitions an arrow over `tupleset` leads to, the namespaces of its subject types. `None`
+/// if they are not declared, the arrow then follows whatever the tuples point at.
+fn arrow_targets<'a>(
+    relations: &HashMap<&str, &'a RelationDefinition>,
+    tupleset: &str,
+) -> Option<Vec<&'a str>> {
+    let RelationKind::Relation { subject_types } = &relations.get(tupleset)?.kind else {
+        return None;
+    };
+    let mut targets: Vec<&str> = Vec::new();
+    for subject_type in subject_types {
+        if !targets.contains(&subject_type.namespace.as_str()) {
+            targets.push(&subject_type.namespace);
+        }
+    }
+    (!targets.is_empty()).then_some(targets)
+}
+
+/// The relations, arrows and `this` an expression is built from.
+fn leaves(expression: &Expression) -> Vec<&Expression> {
+    match &expression.kind {
+        ExpressionKind::This | ExpressionKind::Relation(_) | ExpressionKind::Arrow { .. } => {
+            vec![expression]
+        }
+        ExpressionKind::Union(children) | ExpressionKind::Intersection(children) => {
+            children.iter().flat_map(leaves).collect()
+        }
+        ExpressionKind::Exclusion { base, subtract } => {
+            let mut leaves_of = leaves(base);
+            leaves_of.extend(leaves(subtract));
+            leaves_of
+        }
+    }
+}
+
+fn names(names: &[&str]) -> String {
+    names
+        .iter()
+        .map(|name| format!("`{name}`"))
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+fn validate_length(what: &str, name: &str, position: Option<Position>) -> HeimdallResult<()> {
+    if name.len() > MAX_IDENTIFIER_LENGTH {
+        return Err(invalid_at(
+            position,
+            format!("{what} `{name}` is longer than {MAX_IDENTIFIER_LENGTH} characters"),
+        ));
+    }
+    Ok(())
+}
+
+fn invalid_at(position: Option<Position>, message: String) -> HeimdallError {
+    HeimdallError::SchemaValidation(match position {
+        Some(position) => format!("{message} at {position}"),
+        None => message,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fmt;
+
+    use super::*;
+
+    fn validate(source: &str) -> HeimdallResult<Vec<SchemaWarning>> {
+        validate_schema(&Schema::parse(source).unwrap())
+    }
+
+    fn error_message(result: HeimdallResult<impl fmt::Debug>) -> String {
+        match result {
+            Err(HeimdallError::SchemaValidation(message)) => message,
+            other => panic!("expected a schema validation error, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn references_must_be_defined() {
+        let message = error_message(validate(
+            "definition document {\n    relation viewer: user\n}",
+        ));
+        assert_eq!(
+            message,
+            "subject type `user` of `document#viewer` is not a definition at line 2, column 22"
+        );
+
+        let message = error_message(validate(
+            "definition group {}\ndefinition document {\n    relation viewer: group#member\n}",
+        ));
+        assert_eq!(
+            message,
+            "subject type `group#member` of `document#viewer` is not defined in definition \
+             `group` at line 3, column 22"
+        );
+
+        let message = error_message(validate(
+            "definition document {\n    relation owner: document\n    permission view = owner + \
+             editor\n}",
+        ));
+        assert_eq!(
+            message,
+            "`document#view` refers to `editor`, which is not defined in definition `document` at \
+             line 3, column 31"
+        );
+    }
+
+    #[test]
+    fn arrows_must_lead_to_the_relation_they_compute() {
+        let message = error_message(validate(
+            "definition folder {}\ndefinition document {\n    relation parent: folder\n    \
+             permission view = parent->view\n}",
+        ));
+        assert_eq!(
+            message,
+            "`parent->view` of `document#view` leads to `folder`, which does not define `view` \
+             at line 4, column 23"
+        );
+
+        let message = error_message(validate(
+            "definition user {}\ndefinition folder {}\ndefinition document {\n    relation \
+             parent: folder | user\n    permission view = parent->view\n}",
+        ));
+        assert_eq!(
+            message,
+            "`parent->view` of `document#view` leads to `folder`, `user`, none of which defines \
+             `view` at line 5, column 23"
+        );
+
+        // One target defining the relation is enough, and so is an arrow over a relation
+        // whose subject types are not declared.
+        validate(
+            "definition user {}\ndefinition folder {\n    relation viewer: user\n    permission \
+             view = viewer\n}\ndefinition document {\n    relation parent: folder | user\n    \
+             relation owner\n    permission view = parent->view + owner->view\n}",
+        )
+        .unwrap();
+    }
+
+    #[test]
+    fn cycles_through_arrows_must_be_grounded() {
+        // A folder's viewers are its own and those of its parents, the relation ends the cycle.
+        validate(
+            "definition user {}\ndefinition folder {\n    relation parent: folder\n    \
+             relation viewer: user\n    permission view = viewer + parent->view\n}",
+        )
+        .unwrap();
+
+        let message = error_message(validate(
+            "definition folder {\n    relation parent: folder\n    permission view = \
+             parent->view\n}",
+        ));
+        assert_eq!(
+            message,
+            "`folder#view` -> `folder#view` is a rewrite cycle without a relation to end it at \
+             line 3, column 5"
+        );
+    }
+
+    #[test]
+    fn cycles_of_rewrites_are_rejected_even_if_grounded() {
+        let message = error_message(validate(
+            "definition user {}\ndefinition document {\n    relation reader: user\n    \
+             relation owner: user\n    permission viewer = reader + editor\n    permission \
+             editor = owner + viewer\n}",
+        ));
+        assert_eq!(
+            message,
+            "`document#viewer` -> `document#editor` -> `document#viewer` is a cycle of rewrites, \
+             which fails every check reaching it at line 5, column 5"
+        );
+
+        let message = error_message(validate(
+            "definition user {}\ndefinition document {\n    relation reader: user\n    \
+             permission view = reader - banned\n    permission banned = view\n}",
+        ));
+        assert_eq!(
+            message,
+            "`document#view` -> `document#banned` -> `document#view` is a cycle of rewrites, \
+             which fails every check reaching it at line 4, column 5"
+        );
+    }
+
+    #[test]
+    fn warns_about_unreachable_relations() {
+        let warnings = validate(
+            "definition user {}\ndefinition group {\n    relation member: user\n}\ndefinition \
+             document {\n    relation viewer: user | group#member\n    relation auditor: user\n    \
+             permission view = viewer\n}",
+        )
+        .unwrap();
+        let warnings: Vec<(&str, Option<Position>)> = warnings
+            .iter()
+            .map(|warning| (warning.message.as_str(), warning.position))
+            .collect();
+        // `group` has no permissions, its relations are only there to be referred to.
+        assert_eq!(
+            warnings,
+            [(
+                "relation `document#auditor` is unreachable, no permission refers to it",
+                Some(Position { line: 7, column: 5 })
+            )]
+        );
+    }
+}
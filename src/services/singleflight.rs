@@ -0,0 +1,208 @@
+use std::{
+    collections::HashMap,
+    future::Future,
+    hash::Hash,
+    sync::{Arc, Mutex, MutexGuard},
+};
+
+use tokio::sync::watch;
+
+/// Coalesces concurrent calls for the same key: the first caller runs the call, and callers
+/// arriving while it is in flight wait for its result instead of running it again.
+///
+/// Only successful results are shared. Callers waiting on a call that fails or is cancelled run
+/// the call themselves, so each of them gets an error of its own.
+#[derive(Debug)]
+pub struct Singleflight<K, V> {
+    calls: Arc<Mutex<HashMap<K, watch::Receiver<Option<V>>>>>,
+}
+
+impl<K, V> Clone for Singleflight<K, V> {
+    fn clone(&self) -> Self {
+        Self {
+            calls: self.calls.clone(),
+        }
+    }
+}
+
+impl<K, V> Default for Singleflight<K, V> {
+    fn default() -> Self {
+        Self {
+            calls: Arc::default(),
+        }
+    }
+}
+
+/// Removes the call from the in-flight calls once its leader is done with it, whether it
+/// finished, failed or was dropped.
+struct Flight<'a, K: Eq + Hash, V> {
+    calls: &'a Mutex<HashMap<K, watch::Receiver<Option<V>>>>,
+    key: Option<K>,
+}
+
+impl<K: Eq + Hash, V> Drop for Flight<'_, K, V> {
+    fn drop(&mut self) {
+        if let Some(key) = self.key.take() {
+            lock(self.calls).remove(&key);
+        }
+    }
+}
+
+impl<K, V> Singleflight<K, V>
+where
+    K: Eq + Hash + Clone,
+    V: Clone,
+{
+    /// Result of `call` for `key`, shared with every caller asking for `key` while it runs.
+    pub async fn run<F, Fut, E>(&self, key: K, call: F) -> Result<V, E>
+    where
+        F: FnOnce() -> Fut,
+        Fut: Future<Output = Result<V, E>>,
+    {
+        let waiting = {
+            let mut calls = lock(&self.calls);
+            match calls.get(&key) {
+                Some(receiver) => Err(receiver.clone()),
+                None => {
+                    let (sender, receiver) = watch::channel(None);
+                    calls.insert(key.clone(), receiver);
+                    Ok(sender)
+                }
+            }
+        };
+
+        match waiting {
+            Ok(sender) => {
+                let _flight = Flight {
+                    calls: &self.calls,
+                    key: Some(key),
+                };
+                let value = call().await?;
+                sender.send_replace(Some(value.clone()));
+                Ok(value)
+            }
+            Err(mut receiver) => {
+                let shared = receiver
+                    .wait_for(Option::is_some)
+                    .await
+                    .ok()
+                    .and_then(|value| value.clone());
+                match shared {
+                    Some(value) => Ok(value),
+                    None => call().await,
+                }
+            }
+        }
+    }
+}
+
+fn lock<T>(mutex: &Mutex<T>) -> MutexGuard<'_, T> {
+    // The map is consistent between statements, a panic elsewhere cannot corrupt it.
+    mutex
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        sync::atomic::{AtomicUsize, Ordering},
+        time::Duration,
+    };
+
+    use tokio::{sync::oneshot, task::yield_now};
+
+    use super::*;
+
+    /// Yields `times` times, so that futures joined with it get polled in between.
+    async fn yield_times(times: usize) {
+        for _ in 0..times {
+            yield_now().await;
+        }
+    }
+
+    #[tokio::test]
+    async fn waiters_share_the_result_of_the_call_in_flight() {
+        let flights = Singleflight::<&str, u32>::default();
+        let calls = AtomicUsize::new(0);
+        let (release, released) = oneshot::channel();
+
+        let leader = flights.run("key", || {
+            calls.fetch_add(1, Ordering::SeqCst);
+            async {
+                released.await.ok();
+                Ok::<_, ()>(1)
+            }
+        });
+        let waiter = async {
+            yield_times(1).await;
+            flights
+                .run("key", || {
+                    calls.fetch_add(1, Ordering::SeqCst);
+                    async { Ok::<_, ()>(2) }
+                })
+                .await
+        };
+        let release = async {
+            yield_times(3).await;
+            release.send(()).ok();
+        };
+        let (leader, waiter, ()) = tokio::join!(leader, waiter, release);
+
+        assert_eq!((leader, waiter), (Ok(1), Ok(1)));
+        assert_eq!(calls.load(Ordering::SeqCst), 1);
+        // Finished calls are not remembered.
+        assert_eq!(flights.run("key", || async { Ok::<_, ()>(3) }).await, Ok(3));
+    }
+
+    #[tokio::test]
+    async fn waiters_run_the_call_themselves_when_the_leader_fails() {
+        let flights = Singleflight::<&str, u32>::default();
+        let (release, released) = oneshot::channel();
+
+        let leader = flights.run("key", || async {
+            released.await.ok();
+            Err("leader failed")
+        });
+        let waiter = async {
+            yield_times(1).await;
+            flights.run("key", || async { Ok::<_, &str>(2) }).await
+        };
+        let release = async {
+            yield_times(3).await;
+            release.send(()).ok();
+        };
+        let (leader, waiter, ()) = tokio::join!(leader, waiter, release);
+
+        assert_eq!((leader, waiter), (Err("leader failed"), Ok(2)));
+    }
+
+    #[tokio::test]
+    async fn waiters_run_the_call_themselves_when_the_leader_is_cancelled() {
+        let flights = Singleflight::<&str, u32>::default();
+
+        let leader = tokio::time::timeout(
+            Duration::from_millis(10),
+            flights.run("key", std::future::pending::<Result<u32, ()>>),
+        );
+        let waiter = async {
+            yield_times(1).await;
+            flights.run("key", || async { Ok::<_, ()>(2) }).await
+        };
+        let (leader, waiter) = tokio::join!(leader, waiter);
+
+        assert!(leader.is_err());
+        assert_eq!(waiter, Ok(2));
+        assert!(lock(&*flights.calls).is_empty());
+    }
+
+    #[tokio::test]
+    async fn different_keys_do_not_share() {
+        let flights = Singleflight::<&str, u32>::default();
+        let (first, second) = tokio::join!(
+            flights.run("first", || async { Ok::<_, ()>(1) }),
+            flights.run("second", || async { Ok::<_, ()>(2) }),
+        );
+        assert_eq!((first, second), (Ok(1), Ok(2)));
+    }
+}
@@ -0,0 +1,124 @@
+//! Fixtures for tests of the services against an in-memory SQLite database.
+
+use crate::{
+    database::{DatabasePool, memory_pool, with_pool},
+    models::{ObjectRelation, SubjectRef},
+};
+
+/// A row of `relation_rules` for the relation `namespace#relation`.
+pub(crate) struct Rule {
+    pub relation: &'static str,
+    pub rule_type: &'static str,
+    pub children: &'static [&'static str],
+    pub ttu_relation: Option<&'static str>,
+    pub ttu_object_namespace: Option<&'static str>,
+    pub expression: Option<&'static str>,
+    pub priority: i32,
+}
+
+pub(crate) fn rule(
+    relation: &'static str,
+    rule_type: &'static str,
+    children: &'static [&'static str],
+) -> Rule {
+    Rule {
+        relation,
+        rule_type,
+        children,
+        ttu_relation: None,
+        ttu_object_namespace: None,
+        expression: None,
+        priority: 100,
+    }
+}
+
+/// A pool holding the relations `namespace#relation`, their `rules` and the `tuples`
+/// `namespace:object_id#relation@subject`.
+pub(crate) async fn pool(relations: &[&str], rules: &[Rule], tuples: &[&str]) -> DatabasePool {
+    let pool = memory_pool().await;
+    with_pool!(&pool, |pool| {
+        for relation in relations {
+            let (namespace, relation) = relation.split_once('#').unwrap();
+            sqlx::query("INSERT INTO namespaces (id, name) VALUES ($1, $1) ON CONFLICT DO NOTHING")
+                .bind(namespace)
+                .execute(pool)
+                .await
+                .unwrap();
+            sqlx::query("INSERT INTO relations (namespace_id, name) VALUES ($1, $2)")
+                .bind(namespace)
+                .bind(relation)
+                .execute(pool)
+                .await
+                .unwrap();
+        }
+        for rule in rules {
+            let (namespace, relation) = rule.relation.split_once('#').unwrap();
+            let children =
+                (!rule.children.is_empty()).then(|| serde_json::to_string(rule.children).unwrap());
+            sqlx::query(
+                "INSERT INTO relation_rules (namespace_id, relation_name, rule_type, \
+                 ttu_object_namespace, ttu_relation, child_relations, expression, priority) \
+                 VALUES ($1, $2, $3, $4, $5, $6, $7, $8)",
+            )
+            .bind(namespace)
+            .bind(relation)
+            .bind(rule.rule_type)
+            .bind(rule.ttu_object_namespace)
+            .bind(rule.ttu_relation)
+            .bind(children)
+            .bind(rule.expression)
+            .bind(rule.priority)
+            .execute(pool)
+            .await
+            .unwrap();
+        }
+        for tuple in tuples {
+            let (object, subject) = tuple.split_once('@').unwrap();
+            let (object, subject) = (parse_object(object), parse_subject(subject));
+            sqlx::query(
+                "INSERT INTO relationship_tuples (namespace_id, object_id, relation, \
+                 subject_type, subject_id, userset_namespace, userset_relation, zookie_token) \
+                 VALUES ($1, $2, $3, $4, $5, $6, $7, 'test')",
+            )
+            .bind(&object.namespace)
+            .bind(&object.object_id)
+            .bind(&object.relation)
+            .bind(&subject.subject_type)
+            .bind(&subject.subject_id)
+            .bind(
+                subject
+                    .subject_relation
+                    .as_ref()
+                    .map(|_| &subject.subject_type),
+            )
+            .bind(&subject.subject_relation)
+            .execute(pool)
+            .await
+            .unwrap();
+        }
+    });
+    pool
+}
+
+pub(crate) fn parse_object(object: &str) -> ObjectRelation {
+    let (object, relation) = object.split_once('#').unwrap();
+    let (namespace, object_id) = object.split_once(':').unwrap();
+    ObjectRelation {
+        namespace: namespace.to_string(),
+        object_id: object_id.to_string(),
+        relation: relation.to_string(),
+    }
+}
+
+pub(crate) fn parse_subject(subject: &str) -> SubjectRef {
+    let (subject, subject_relation) = match subject.split_once('#') {
+        Some((subject, relation)) => (subject, Some(relation.to_string())),
+        None => (subject, None),
+    };
+    let (subject_type, subject_id) = subject.split_once(':').unwrap();
+    SubjectRef {
+        subject_type: subject_type.to_string(),
+        subject_id: subject_id.to_string(),
+        subject_relation,
+    }
+}
@@ -0,0 +1,148 @@
+use std::{collections::VecDeque, sync::Arc, time::Duration};
+
+use futures_util::{Stream, stream};
+use tokio::sync::watch;
+
+use crate::{
+    database::DatabasePool,
+    entities::OperationType,
+    error::HeimdallResult,
+    models::{ChangeOperation, ChangeSet, RelationshipChange, TupleKey},
+    repositories::{RelationshipRepository, TransactionLogRepository},
+};
+
+use super::{consistency::ZookieCodec, validate_identifier};
+
+/// Delay between two looks at `transaction_log` once a watch has caught up.
+const POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Most versions read from `transaction_log` at once while catching up.
+const VERSIONS_PER_READ: i64 = 100;
+
+/// Streams the changes of committed writes in version order.
+#[derive(Debug, Clone)]
+pub struct WatchService {
+    relationships: RelationshipRepository,
+    transaction_log: TransactionLogRepository,
+    zookies: ZookieCodec,
+    closed: Arc<watch::Sender<bool>>,
+}
+
+impl WatchService {
+    pub fn new(pool: DatabasePool, zookies: ZookieCodec) -> Self {
+        Self {
+            relationships: RelationshipRepository::new(pool.clone()),
+            transaction_log: TransactionLogRepository::new(pool),
+            zookies,
+            closed: Arc::new(watch::channel(false).0),
+        }
+    }
+
+    /// Change sets of every write after the one `zookie` was returned for, or after the latest
+    /// write without one, touching `namespaces` (any namespace if empty).
+    ///
+    /// Writes commit in version order, so a version is only read once every earlier one is
+    /// visible and resuming from the zookie of the last change set received misses nothing.
+    /// The stream ends after the first error and once [`WatchService::close`] is called.
+    pub async fn watch(
+        &self,
+        namespaces: Vec<String>,
+        zookie: Option<&str>,
+    ) -> HeimdallResult<impl Stream<Item = HeimdallResult<ChangeSet>> + use<>> {
+        for namespace in &namespaces {
+            validate_identifier("namespaces", namespace)?;
+        }
+        let after = match zookie {
+            Some(zookie) => self.zookies.decode(zookie)?,
+            None => self.relationships.current_version().await?,
+        };
+
+        let watcher = Watcher {
+            service: self.clone(),
+            namespaces,
+            after,
+            pending: VecDeque::new(),
+            closed: self.closed.subscribe(),
+            done: false,
+        };
+        Ok(stream::unfold(watcher, |mut watcher| async move {
+            let next = watcher.next().await?;
+            Some((next, watcher))
+        }))
+    }
+
+    /// Ends every watch stream, which would otherwise keep the server from shutting down.
+    pub fn close(&self) {
+        self.closed.send_replace(true);
+    }
+}
+
+struct Watcher {
+    service: WatchService,
+    namespaces: Vec<String>,
+    /// Version up to which every change has been read.
+    after: i64,
+    pending: VecDeque<ChangeSet>,
+    closed: watch::Receiver<bool>,
+    done: bool,
+}
+
+impl Watcher {
+    async fn next(&mut self) -> Option<HeimdallResult<ChangeSet>> {
+        loop {
+            if let Some(change_set) = self.pending.pop_front() {
+                return Some(Ok(change_set));
+            }
+            if self.done || *self.closed.borrow() {
+                return None;
+            }
+            if let Err(error) = self.read().await {
+                self.done = true;
+                return Some(Err(error));
+            }
+        }
+    }
+
+    /// Queues the change sets of the next versions, waiting for new writes if there are none.
+    async fn read(&mut self) -> HeimdallResult<()> {
+        let current = self.service.relationships.current_version().await?;
+        if current <= self.after {
+            tokio::select! {
+                _ = tokio::time::sleep(POLL_INTERVAL) => {}
+                _ = self.closed.changed() => self.done = true,
+            }
+            return Ok(());
+        }
+
+        let up_to = current.min(self.after + VERSIONS_PER_READ);
+        let entries = self
+            .service
+            .transaction_log
+            .find_between(self.after, up_to, &self.namespaces)
+            .await?;
+        for entry in entries {
+            let operation = match entry.operation {
+                OperationType::Create => ChangeOperation::Create,
+                OperationType::Delete => ChangeOperation::Delete,
+                OperationType::Update => continue,
+            };
+            let version = entry.version_number;
+            let change = RelationshipChange {
+                operation,
+                relationship: TupleKey::from(entry),
+            };
+            match self.pending.back_mut() {
+                Some(change_set) if change_set.version == version => {
+                    change_set.changes.push(change)
+                }
+                _ => self.pending.push_back(ChangeSet {
+                    version,
+                    zookie: self.service.zookies.encode(version),
+                    changes: vec![change],
+                }),
+            }
+        }
+        self.after = up_to;
+        Ok(())
+    }
+}
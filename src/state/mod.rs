@@ -1,10 +1,85 @@
-use crate::config::AppConfig;
+use crate::{
+    config::AppConfig,
+    database::{self, DatabasePool},
+    error::HeimdallError,
+    services::{
+        CheckCache, CheckService, ConsistencyResolver, DecisionRecorder, ExpandService,
+        LookupService, NamespaceService, RelationshipService, SchemaService, WatchService,
+        ZookieCodec,
+    },
+};
 
-#[derive(Debug)]
-pub struct AppState {}
+#[derive(Debug, Clone)]
+pub struct AppState {
+    pub pool: DatabasePool,
+    pub check_cache: CheckCache,
+    pub decision_recorder: DecisionRecorder,
+    pub namespace_service: NamespaceService,
+    pub schema_service: SchemaService,
+    pub relationship_service: RelationshipService,
+    pub check_service: CheckService,
+    pub expand_service: ExpandService,
+    pub lookup_service: LookupService,
+    pub watch_service: WatchService,
+}
 
 impl AppState {
-    pub fn new(_app_config: AppConfig) -> Self {
-        Self {}
+    pub async fn new(app_config: &AppConfig) -> Result<Self, HeimdallError> {
+        let pool = connect(app_config).await?;
+        let evaluation_config = &app_config.evaluation_config;
+        let zookies = ZookieCodec::new(&app_config.consistency_config);
+        let check_cache = CheckCache::new(&app_config.cache_config);
+        let decision_recorder = DecisionRecorder::new(pool.clone(), &app_config.audit_config);
+        let consistency = ConsistencyResolver::new(
+            pool.clone(),
+            zookies.clone(),
+            &app_config.consistency_config,
+        );
+        Ok(Self {
+            namespace_service: NamespaceService::new(pool.clone(), zookies.clone()),
+            schema_service: SchemaService::new(pool.clone(), zookies.clone(), check_cache.clone()),
+            watch_service: WatchService::new(pool.clone(), zookies.clone()),
+            relationship_service: RelationshipService::new(
+                pool.clone(),
+                zookies,
+                consistency.clone(),
+            ),
+            check_service: CheckService::new(
+                pool.clone(),
+                evaluation_config,
+                &app_config.cache_config,
+                check_cache.clone(),
+                decision_recorder.clone(),
+                consistency.clone(),
+            ),
+            expand_service: ExpandService::new(
+                pool.clone(),
+                evaluation_config,
+                consistency.clone(),
+            ),
+            lookup_service: LookupService::new(pool.clone(), evaluation_config, consistency),
+            check_cache,
+            decision_recorder,
+            pool,
+        })
+    }
+
+    /// Releases everything the state holds once the server stopped accepting requests.
+    pub async fn shutdown(&self) {
+        // Queued decisions are written before the pool they are written to goes away.
+        self.decision_recorder.close().await;
+        self.pool.close().await;
     }
 }
+
+/// Connects to the configured database, naming the target in the error so an unreachable
+/// database is obvious from the startup failure alone.
+pub async fn connect(app_config: &AppConfig) -> Result<DatabasePool, HeimdallError> {
+    let database_config = &app_config.database_config;
+    DatabasePool::connect(database_config)
+        .await
+        .map_err(|source| HeimdallError::DatabaseConnection {
+            target: database::redacted_dsn(database_config),
+            source,
+        })
+}
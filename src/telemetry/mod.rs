@@ -0,0 +1,30 @@
+use tracing_subscriber::{EnvFilter, fmt::format::FmtSpan};
+
+use crate::config::{LogFormat, LoggingConfig, SpanEvents};
+
+/// Installs the global tracing subscriber. Must be called once, before anything is logged.
+pub fn init_tracing(logging_config: &LoggingConfig) {
+    // The filter was already parsed successfully by `LoggingConfig::validate`.
+    let filter =
+        EnvFilter::try_new(&logging_config.level).unwrap_or_else(|_| EnvFilter::new("info"));
+    let span_events = match logging_config.span_events {
+        SpanEvents::None => FmtSpan::NONE,
+        SpanEvents::New => FmtSpan::NEW,
+        SpanEvents::Close => FmtSpan::CLOSE,
+        SpanEvents::Active => FmtSpan::ACTIVE,
+        SpanEvents::Full => FmtSpan::FULL,
+    };
+
+    let builder = tracing_subscriber::fmt()
+        .with_env_filter(filter)
+        .with_span_events(span_events);
+    match logging_config.format {
+        LogFormat::Pretty => builder.pretty().init(),
+        LogFormat::Json => builder
+            .json()
+            .with_current_span(true)
+            .with_span_list(true)
+            .flatten_event(true)
+            .init(),
+    }
+}